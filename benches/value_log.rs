@@ -3,7 +3,6 @@ use rand::{Rng, RngCore};
 use std::{
     collections::BTreeMap,
     fs::File,
-    io::BufReader,
     sync::{Arc, RwLock},
 };
 use value_log::{
@@ -92,10 +91,10 @@ impl BlobCache for NoCacher {
 }
 
 impl FDCache for NoCacher {
-    fn get(&self, _: ValueLogId, _: BlobFileId) -> Option<BufReader<File>> {
+    fn get(&self, _: ValueLogId, _: BlobFileId) -> Option<Arc<File>> {
         None
     }
-    fn insert(&self, _: ValueLogId, _: BlobFileId, _: BufReader<File>) {}
+    fn insert(&self, _: ValueLogId, _: BlobFileId, _: Arc<File>) {}
 }
 
 fn prefetch(c: &mut Criterion) {
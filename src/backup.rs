@@ -0,0 +1,404 @@
+// Copyright (c) 2024-present, fjall-rs
+// This source code is licensed under both the Apache 2.0 and MIT License
+// (found in the LICENSE-* files in the repository)
+
+use crate::{
+    coding::{Decode, DecodeError, Encode, EncodeError},
+    id::SegmentId,
+    manifest::{
+        rewrite_atomic, write_manifest, write_marker, SegmentMap, MANIFEST_FILE, SEGMENTS_FOLDER,
+    },
+    path::absolute_path,
+    segment::trailer::SegmentFileTrailer,
+    version::Version,
+    Compressor,
+};
+use byteorder::{BigEndian, ReadBytesExt, WriteBytesExt};
+use std::{
+    io::{Read, Write},
+    path::Path,
+};
+
+pub const BACKUP_MANIFEST_FILE: &str = "backup_manifest";
+
+const BACKUP_MANIFEST_MAGIC: &[u8] = &[b'V', b'L', b'O', b'G', b'B', b'A', b'K', 1];
+
+/// A segment that is part of a backup
+#[derive(Clone, Debug, Eq, PartialEq)]
+#[allow(clippy::module_name_repetitions)]
+pub struct BackupSegment {
+    /// Segment ID
+    pub id: SegmentId,
+
+    /// Size of the segment file in bytes
+    pub file_size: u64,
+
+    /// Amount of blobs in the segment
+    pub item_count: u64,
+}
+
+/// Manifest of a value log backup
+///
+/// Lists the segments that make up the backup, and which segments were
+/// added and removed since the previous backup.
+#[derive(Clone, Debug, Eq, PartialEq)]
+#[allow(clippy::module_name_repetitions)]
+pub struct BackupManifest {
+    /// Disk format version of the value log
+    pub version: Version,
+
+    /// Segments that make up the backup, sorted by ID
+    pub segments: Vec<BackupSegment>,
+
+    /// Segments that were copied by this backup
+    pub added: Vec<SegmentId>,
+
+    /// Segments that were part of the previous backup,
+    /// but have since been dropped from the value log
+    ///
+    /// Their files are kept in the backup directory, so older backups
+    /// stay restorable; they can be deleted if they are not needed anymore.
+    pub removed: Vec<SegmentId>,
+}
+
+fn write_ids<W: Write>(writer: &mut W, ids: &[SegmentId]) -> std::io::Result<()> {
+    writer.write_u64::<BigEndian>(ids.len() as u64)?;
+
+    for id in ids {
+        writer.write_u64::<BigEndian>(*id)?;
+    }
+
+    Ok(())
+}
+
+fn read_ids<R: Read>(reader: &mut R) -> std::io::Result<Vec<SegmentId>> {
+    let cnt = reader.read_u64::<BigEndian>()?;
+
+    let mut ids = vec![];
+
+    for _ in 0..cnt {
+        ids.push(reader.read_u64::<BigEndian>()?);
+    }
+
+    Ok(ids)
+}
+
+impl Encode for BackupManifest {
+    fn encode_into<W: Write>(&self, writer: &mut W) -> Result<(), EncodeError> {
+        writer.write_all(BACKUP_MANIFEST_MAGIC)?;
+        writer.write_u8(self.version.into())?;
+
+        writer.write_u64::<BigEndian>(self.segments.len() as u64)?;
+
+        for segment in &self.segments {
+            writer.write_u64::<BigEndian>(segment.id)?;
+            writer.write_u64::<BigEndian>(segment.file_size)?;
+            writer.write_u64::<BigEndian>(segment.item_count)?;
+        }
+
+        write_ids(writer, &self.added)?;
+        write_ids(writer, &self.removed)?;
+
+        Ok(())
+    }
+}
+
+impl Decode for BackupManifest {
+    fn decode_from<R: Read>(reader: &mut R) -> Result<Self, DecodeError> {
+        let mut magic = [0u8; BACKUP_MANIFEST_MAGIC.len()];
+        reader.read_exact(&mut magic)?;
+
+        if magic != BACKUP_MANIFEST_MAGIC {
+            return Err(DecodeError::InvalidHeader("BackupManifest"));
+        }
+
+        let version = reader.read_u8()?;
+        let version = Version::try_from(version).map_err(|()| DecodeError::InvalidVersion)?;
+
+        let cnt = reader.read_u64::<BigEndian>()?;
+
+        let mut segments = vec![];
+
+        for _ in 0..cnt {
+            segments.push(BackupSegment {
+                id: reader.read_u64::<BigEndian>()?,
+                file_size: reader.read_u64::<BigEndian>()?,
+                item_count: reader.read_u64::<BigEndian>()?,
+            });
+        }
+
+        Ok(Self {
+            version,
+            segments,
+            added: read_ids(reader)?,
+            removed: read_ids(reader)?,
+        })
+    }
+}
+
+/// Copies a segment file, and syncs the copy to disk
+fn copy_segment(src: &Path, dst: &Path) -> crate::Result<()> {
+    log::trace!(
+        "Copying vLog segment {} to {}",
+        src.display(),
+        dst.display()
+    );
+
+    std::fs::copy(src, dst)?;
+    std::fs::File::open(dst)?.sync_all()?;
+
+    Ok(())
+}
+
+/// Checks that a restored segment file matches the backup manifest
+fn verify_segment(segment: &BackupSegment, path: &Path) -> crate::Result<()> {
+    let file_size = std::fs::metadata(path)?.len();
+
+    if file_size != segment.file_size {
+        log::error!(
+            "Backup segment {} has size {file_size}, expected {}",
+            segment.id,
+            segment.file_size,
+        );
+        return Err(crate::Error::InvalidBackup(segment.id));
+    }
+
+    match SegmentFileTrailer::from_file(path) {
+        Ok(trailer) if trailer.metadata.item_count == segment.item_count => Ok(()),
+        Ok(trailer) => {
+            log::error!(
+                "Backup segment {} has {} items, expected {}",
+                segment.id,
+                trailer.metadata.item_count,
+                segment.item_count,
+            );
+            Err(crate::Error::InvalidBackup(segment.id))
+        }
+        Err(e) => {
+            log::error!(
+                "Backup segment {} has an invalid trailer: {e:?}",
+                segment.id
+            );
+            Err(crate::Error::InvalidBackup(segment.id))
+        }
+    }
+}
+
+impl BackupManifest {
+    /// Loads the manifest of the backup in the given directory.
+    ///
+    /// # Errors
+    ///
+    /// Will return `Err` if an IO error occurs, or the manifest is invalid.
+    pub fn from_dir<P: AsRef<Path>>(path: P) -> crate::Result<Self> {
+        let path = path.as_ref().join(BACKUP_MANIFEST_FILE);
+        log::debug!("Loading backup manifest from {}", path.display());
+
+        let bytes = std::fs::read(path)?;
+        Self::decode_from(&mut &bytes[..]).map_err(Into::into)
+    }
+
+    /// Returns `true` if the segment is part of the backup.
+    #[must_use]
+    pub fn contains(&self, id: SegmentId) -> bool {
+        self.segments.iter().any(|x| x.id == id)
+    }
+
+    /// Returns the backed up segment with the given ID.
+    #[must_use]
+    pub fn get(&self, id: SegmentId) -> Option<&BackupSegment> {
+        self.segments.iter().find(|x| x.id == id)
+    }
+
+    fn write_to_dir(&self, path: &Path) -> crate::Result<()> {
+        let path = path.join(BACKUP_MANIFEST_FILE);
+        log::trace!("Writing backup manifest to {}", path.display());
+
+        let mut bytes = vec![];
+        self.encode_into(&mut bytes)?;
+        rewrite_atomic(path, &bytes)?;
+
+        Ok(())
+    }
+
+    /// Restores the backup in the given directory into a new value log directory.
+    ///
+    /// Every segment is copied, and its trailer is checked against the backup manifest.
+    /// The restored directory can be opened as a value log right away.
+    ///
+    /// The target directory must not exist yet; if the restore fails,
+    /// it is removed again.
+    ///
+    /// # Errors
+    ///
+    /// Will return `Err` if an IO error occurs, the target directory already exists,
+    /// or [`crate::Error::InvalidBackup`] if a segment is missing or damaged.
+    pub fn restore<P: AsRef<Path>, Q: AsRef<Path>>(
+        &self,
+        backup_dir: P,
+        target: Q,
+    ) -> crate::Result<()> {
+        let backup_dir = backup_dir.as_ref();
+        let target = absolute_path(target.as_ref());
+
+        log::info!(
+            "Restoring vLog backup at {} to {}",
+            backup_dir.display(),
+            target.display(),
+        );
+
+        if target.try_exists()? {
+            return Err(crate::Error::Io(std::io::Error::new(
+                std::io::ErrorKind::AlreadyExists,
+                format!("restore target {} already exists", target.display()),
+            )));
+        }
+
+        if let Err(e) = self.restore_into(backup_dir, &target) {
+            if let Err(e) = std::fs::remove_dir_all(&target) {
+                log::warn!(
+                    "Could not clean up failed restore at {}: {e:?}",
+                    target.display()
+                );
+            }
+            return Err(e);
+        }
+
+        log::info!("Restored {} vLog segments", self.segments.len());
+
+        Ok(())
+    }
+
+    fn restore_into(&self, backup_dir: &Path, target: &Path) -> crate::Result<()> {
+        let segments_folder = target.join(SEGMENTS_FOLDER);
+        std::fs::create_dir_all(&segments_folder)?;
+
+        for segment in &self.segments {
+            let src = backup_dir
+                .join(SEGMENTS_FOLDER)
+                .join(segment.id.to_string());
+
+            if !src.try_exists()? {
+                log::error!("Backup segment {} is missing", segment.id);
+                return Err(crate::Error::InvalidBackup(segment.id));
+            }
+
+            let dst = segments_folder.join(segment.id.to_string());
+            copy_segment(&src, &dst)?;
+            verify_segment(segment, &dst)?;
+        }
+
+        let ids = self.segments.iter().map(|x| x.id).collect::<Vec<_>>();
+        write_manifest(
+            &target.join(MANIFEST_FILE),
+            &ids,
+            self.version == Version::V2,
+        )?;
+
+        // NOTE: Lastly, write .vlog marker, which contains the version
+        // -> the value log is fully initialized
+        write_marker(target, self.version)
+    }
+}
+
+/// Copies all segments that are not part of the previous backup into the backup directory,
+/// and writes the new backup manifest.
+pub fn backup<C: Compressor + Clone>(
+    version: Version,
+    segments: &SegmentMap<C>,
+    target: &Path,
+    previous: Option<&BackupManifest>,
+) -> crate::Result<BackupManifest> {
+    let segments_folder = target.join(SEGMENTS_FOLDER);
+    std::fs::create_dir_all(&segments_folder)?;
+
+    let mut list = segments.values().collect::<Vec<_>>();
+    list.sort_by_key(|x| x.id);
+
+    let mut manifest = BackupManifest {
+        version,
+        segments: Vec::with_capacity(list.len()),
+        added: vec![],
+        removed: vec![],
+    };
+
+    for segment in list {
+        let backup_path = segments_folder.join(segment.id.to_string());
+
+        // NOTE: Segments are immutable, so if the previous backup contains
+        // the segment, it does not need to be copied again
+        //
+        // IDs are reused after the value log is cleared, so the previous backup
+        // may contain a different segment with the same ID
+        let file_size = std::fs::metadata(&segment.path)?.len();
+        let seen = previous
+            .and_then(|x| x.get(segment.id))
+            .is_some_and(|x| x.file_size == file_size && x.item_count == segment.meta.item_count)
+            && backup_path.try_exists()?;
+
+        if !seen {
+            copy_segment(&segment.path, &backup_path)?;
+            manifest.added.push(segment.id);
+        }
+
+        manifest.segments.push(BackupSegment {
+            id: segment.id,
+            file_size: std::fs::metadata(&backup_path)?.len(),
+            item_count: segment.meta.item_count,
+        });
+    }
+
+    if let Some(previous) = previous {
+        manifest.removed = previous
+            .segments
+            .iter()
+            .map(|x| x.id)
+            .filter(|id| !segments.contains_key(id))
+            .collect();
+    }
+
+    #[cfg(not(target_os = "windows"))]
+    {
+        // fsync folder on Unix
+        let folder = std::fs::File::open(&segments_folder)?;
+        folder.sync_all()?;
+    }
+
+    // IMPORTANT: The manifest needs to be written last, after all segments are persisted
+    manifest.write_to_dir(target)?;
+
+    Ok(manifest)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use test_log::test;
+
+    #[test]
+    fn backup_manifest_round_trip() -> crate::Result<()> {
+        let manifest = BackupManifest {
+            version: Version::V2,
+            segments: vec![
+                BackupSegment {
+                    id: 3,
+                    file_size: 1_000,
+                    item_count: 10,
+                },
+                BackupSegment {
+                    id: 5,
+                    file_size: 2_000,
+                    item_count: 20,
+                },
+            ],
+            added: vec![5],
+            removed: vec![1, 2],
+        };
+
+        let bytes = manifest.encode_into_vec();
+        let decoded = BackupManifest::decode_from(&mut &bytes[..])?;
+        assert_eq!(manifest, decoded);
+
+        Ok(())
+    }
+}
@@ -2,7 +2,13 @@
 // This source code is licensed under both the Apache 2.0 and MIT License
 // (found in the LICENSE-* files in the repository)
 
-use crate::{value_log::ValueLogId, UserValue, ValueHandle};
+use crate::{
+    id::SegmentId,
+    lru::{GroupedKey, ShardedLru, DEFAULT_SHARD_COUNT},
+    value_log::ValueLogId,
+    UserValue, ValueHandle,
+};
+use std::sync::Arc;
 
 /// Blob cache, in which blobs are cached in-memory
 /// after being retrieved from disk
@@ -15,4 +21,127 @@ pub trait BlobCache: Clone {
 
     /// Retrieves a blob from the cache, or `None` if it could not be found.
     fn get(&self, vlog_id: ValueLogId, vhandle: &ValueHandle) -> Option<UserValue>;
+
+    /// Evicts all blobs of a segment.
+    ///
+    /// Called when the segment is dropped from the value log, or its blobs
+    /// were moved into another segment by garbage collection.
+    fn invalidate_segment(&self, vlog_id: ValueLogId, segment_id: SegmentId) {
+        let _ = (vlog_id, segment_id);
+    }
+
+    /// Evicts all blobs of a value log.
+    ///
+    /// Called when the value log is cleared or dropped.
+    fn invalidate_vlog(&self, vlog_id: ValueLogId) {
+        let _ = vlog_id;
+    }
+}
+
+type BlobCacheKey = (ValueLogId, ValueHandle);
+
+/// Size that is accounted for each cached value, on top of the value itself
+///
+/// Covers the key and bookkeeping of the entry, so caching many small
+/// (or empty) values cannot grow the cache without bounds.
+const ENTRY_OVERHEAD: u64 = 64;
+
+// NOTE: Blobs are grouped by segment, so invalidating a segment
+// does not need to scan the entire cache
+impl GroupedKey for BlobCacheKey {
+    type Group = (ValueLogId, SegmentId);
+
+    fn group(&self) -> Self::Group {
+        (self.0, self.1.segment_id)
+    }
+}
+
+/// Sharded LRU blob cache, bounded by the total size of cached values
+///
+/// Each value is accounted for with a fixed overhead of 64 bytes on top of its length.
+///
+/// Can be shared across multiple value logs by cloning it, as blobs are
+/// keyed by their [`ValueLogId`].
+#[derive(Clone)]
+#[allow(clippy::module_name_repetitions)]
+pub struct LruBlobCache(Arc<ShardedLru<BlobCacheKey, UserValue>>);
+
+impl LruBlobCache {
+    /// Creates a new blob cache that holds up to `bytes` bytes of values.
+    #[must_use]
+    pub fn with_capacity_bytes(bytes: u64) -> Self {
+        Self::with_shard_count(bytes, DEFAULT_SHARD_COUNT)
+    }
+
+    /// Creates a new blob cache that holds up to `bytes` bytes of values,
+    /// split into the given amount of shards.
+    ///
+    /// More shards reduce lock contention, but each shard only gets
+    /// its share of the capacity, so values larger than `bytes / shard_count`
+    /// are never cached.
+    #[must_use]
+    pub fn with_shard_count(bytes: u64, shard_count: usize) -> Self {
+        Self(Arc::new(ShardedLru::new(bytes, shard_count)))
+    }
+
+    /// Returns the capacity in bytes.
+    #[must_use]
+    pub fn capacity(&self) -> u64 {
+        self.0.capacity()
+    }
+
+    /// Returns the size of all cached values in bytes, including their overhead.
+    #[must_use]
+    pub fn size(&self) -> u64 {
+        self.0.weight()
+    }
+
+    /// Returns the amount of cached values.
+    #[must_use]
+    pub fn len(&self) -> usize {
+        self.0.len()
+    }
+
+    /// Returns `true` if no values are cached.
+    #[must_use]
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// Returns the amount of cache hits.
+    #[must_use]
+    pub fn hits(&self) -> u64 {
+        self.0.hits()
+    }
+
+    /// Returns the amount of cache misses.
+    #[must_use]
+    pub fn misses(&self) -> u64 {
+        self.0.misses()
+    }
+
+    /// Removes all cached values.
+    pub fn clear(&self) {
+        self.0.clear();
+    }
+}
+
+impl BlobCache for LruBlobCache {
+    fn insert(&self, vlog_id: ValueLogId, vhandle: &ValueHandle, value: UserValue) {
+        let weight = value.len() as u64 + ENTRY_OVERHEAD;
+        self.0.insert((vlog_id, vhandle.clone()), value, weight);
+    }
+
+    fn get(&self, vlog_id: ValueLogId, vhandle: &ValueHandle) -> Option<UserValue> {
+        self.0.get(&(vlog_id, vhandle.clone()))
+    }
+
+    fn invalidate_segment(&self, vlog_id: ValueLogId, segment_id: SegmentId) {
+        self.0.remove_group(&(vlog_id, segment_id));
+    }
+
+    // NOTE: This scans the entire cache, but value logs are only cleared or dropped rarely
+    fn invalidate_vlog(&self, vlog_id: ValueLogId) {
+        self.0.retain(|(id, _)| *id != vlog_id);
+    }
 }
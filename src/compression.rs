@@ -17,4 +17,29 @@ pub trait Compressor {
     ///
     /// Will return `Err` if an IO error occurs.
     fn decompress(&self, bytes: &[u8]) -> crate::Result<Vec<u8>>;
+
+    /// ID of the compression codec, in the range `1..=15`
+    ///
+    /// The ID is stored in the header of every compressed blob, so a blob is never
+    /// decompressed by a compressor using a different codec.
+    /// Compressors using different codecs need to return different IDs.
+    ///
+    /// Defaults to 1.
+    fn id(&self) -> u8 {
+        1
+    }
+
+    /// Decompresses at least the first `len` bytes of a value
+    ///
+    /// Used for partial value reads; compressors that can stop decompressing early
+    /// may override this. Returning more than `len` bytes is fine.
+    /// By default, the entire value is decompressed.
+    ///
+    /// # Errors
+    ///
+    /// Will return `Err` if an IO error occurs.
+    fn decompress_prefix(&self, bytes: &[u8], len: usize) -> crate::Result<Vec<u8>> {
+        let _ = len;
+        self.decompress(bytes)
+    }
 }
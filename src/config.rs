@@ -9,6 +9,9 @@ pub struct Config<BC: BlobCache, FDC: FDCache, C: Compressor + Clone> {
     /// Target size of vLog segments
     pub(crate) segment_size_bytes: u64,
 
+    /// Values larger than this are split into chunks of this size
+    pub(crate) chunk_size: u32,
+
     /// Blob cache to use
     pub(crate) blob_cache: BC,
 
@@ -17,6 +20,13 @@ pub struct Config<BC: BlobCache, FDC: FDCache, C: Compressor + Clone> {
 
     /// Compression to use
     pub(crate) compression: Option<C>,
+
+    /// Whether to verify blob checksums when reading
+    pub(crate) verify_checksums: bool,
+
+    /// Whether to read segments through memory mappings
+    #[cfg(feature = "mmap")]
+    pub(crate) use_mmap: bool,
 }
 
 impl<BC: BlobCache, FDC: FDCache, C: Compressor + Clone + Default> Config<BC, FDC, C> {
@@ -26,17 +36,65 @@ impl<BC: BlobCache, FDC: FDCache, C: Compressor + Clone + Default> Config<BC, FD
             blob_cache,
             fd_cache,
             compression: None,
+            verify_checksums: true,
+            #[cfg(feature = "mmap")]
+            use_mmap: false,
             segment_size_bytes: 128 * 1_024 * 1_024,
+            chunk_size: u32::MAX,
         }
     }
 
     /// Sets the compression & decompression scheme.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the compressor's ID is not in the range `1..=15`.
     #[must_use]
     pub fn compression(mut self, compressor: Option<C>) -> Self {
+        if let Some(compressor) = &compressor {
+            assert!(
+                (1..=15).contains(&compressor.id()),
+                "compressor ID needs to be in the range 1..=15",
+            );
+        }
+
         self.compression = compressor;
         self
     }
 
+    /// Sets whether blob checksums are verified when reading values.
+    ///
+    /// If a blob's checksum does not match, reading it fails
+    /// with [`crate::Error::ChecksumMismatch`].
+    ///
+    /// Default = true
+    #[must_use]
+    pub fn verify_checksums(mut self, enabled: bool) -> Self {
+        self.verify_checksums = enabled;
+        self
+    }
+
+    /// Sets whether segments are read through memory mappings.
+    ///
+    /// Instead of reading blobs using system calls, segment files are mapped
+    /// into memory, and blobs are parsed directly from the mapping.
+    /// Uncompressed values point into the mapping without being copied,
+    /// if the `bytes` feature is enabled.
+    ///
+    /// Without the `bytes` feature, values are always copied out of the mapping,
+    /// so only the system calls are saved.
+    ///
+    /// With the `bytes` feature, returned values keep the mapping alive, so the disk space
+    /// of a dropped segment is only freed once all values read from it are dropped.
+    ///
+    /// Default = false
+    #[cfg(feature = "mmap")]
+    #[must_use]
+    pub fn use_mmap(mut self, enabled: bool) -> Self {
+        self.use_mmap = enabled;
+        self
+    }
+
     /// Sets the blob cache.
     ///
     /// You can create a global [`BlobCache`] and share it between multiple
@@ -60,4 +118,19 @@ impl<BC: BlobCache, FDC: FDCache, C: Compressor + Clone + Default> Config<BC, FD
         self.segment_size_bytes = bytes;
         self
     }
+
+    /// Sets the chunk size for large values.
+    ///
+    /// Values larger than this are split into chunks of this size, which are
+    /// reassembled when reading the value. This allows storing values larger than
+    /// a single blob can hold (4 GiB). All chunks of a value are stored in the same segment.
+    ///
+    /// Values are never chunked in value logs that use the V1 disk format.
+    ///
+    /// Default = `u32::MAX` (4 GiB - 1 byte)
+    #[must_use]
+    pub fn large_value_chunk_size(mut self, bytes: u32) -> Self {
+        self.chunk_size = bytes;
+        self
+    }
 }
@@ -4,6 +4,7 @@
 
 use crate::{
     coding::{DecodeError, EncodeError},
+    id::SegmentId,
     version::Version,
 };
 
@@ -31,9 +32,36 @@ pub enum Error {
 
     /// Some required segments could not be recovered from disk
     Unrecoverable,
-    // TODO:
-    // /// Checksum check failed
-    // ChecksumMismatch,
+
+    /// The value log was opened in read-only mode, so it cannot be modified
+    ReadOnly,
+
+    /// The value log directory is locked by another value log instance
+    Locked,
+
+    /// A segment of a backup is missing or does not match the backup manifest
+    InvalidBackup(SegmentId),
+
+    /// The value is compressed, so it cannot be streamed
+    CompressedValue,
+
+    /// Checksum check failed
+    ChecksumMismatch {
+        /// Segment the corrupted blob is stored in
+        segment_id: SegmentId,
+
+        /// Offset of the corrupted blob in its segment
+        offset: u64,
+    },
+
+    /// The blob a value handle points to belongs to another key
+    KeyMismatch {
+        /// Segment the blob is stored in
+        segment_id: SegmentId,
+
+        /// Offset of the blob in its segment
+        offset: u64,
+    },
 }
 
 impl std::fmt::Display for Error {
@@ -48,9 +76,16 @@ impl std::error::Error for Error {
             Self::Io(e) => Some(e),
             Self::Encode(e) => Some(e),
             Self::Decode(e) => Some(e),
-            Self::Decompress | Self::InvalidVersion(_) | Self::Compress | Self::Unrecoverable => {
-                None
-            }
+            Self::Decompress
+            | Self::InvalidVersion(_)
+            | Self::Compress
+            | Self::Unrecoverable
+            | Self::ReadOnly
+            | Self::Locked
+            | Self::InvalidBackup(_)
+            | Self::CompressedValue
+            | Self::ChecksumMismatch { .. }
+            | Self::KeyMismatch { .. } => None,
         }
     }
 }
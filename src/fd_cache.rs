@@ -2,18 +2,138 @@
 // This source code is licensed under both the Apache 2.0 and MIT License
 // (found in the LICENSE-* files in the repository)
 
-use crate::ValueLogId;
-use std::{fs::File, io::BufReader};
+use crate::{
+    lru::{GroupedKey, ShardedLru, DEFAULT_SHARD_COUNT},
+    ValueLogId,
+};
+use std::{fs::File, sync::Arc};
 
 /// The unique identifier for a value log blob file. Another name for SegmentId
 pub type BlobFileId = u64;
 
 /// File descriptor cache, to cache file descriptors after an fopen().
 /// Reduces the number of fopen() needed when accessing the same blob file.
+///
+/// Cached files are read using positional reads, so a single file
+/// can be shared by any amount of concurrent readers.
 pub trait FDCache: Clone {
     /// Caches a file descriptor
-    fn insert(&self, vlog_id: ValueLogId, blob_file_id: BlobFileId, fd: BufReader<File>);
+    fn insert(&self, vlog_id: ValueLogId, blob_file_id: BlobFileId, fd: Arc<File>);
 
     /// Retrieves a file descriptor from the cache, or `None` if it could not be found
-    fn get(&self, vlog_id: ValueLogId, blob_file_id: BlobFileId) -> Option<BufReader<File>>;
+    fn get(&self, vlog_id: ValueLogId, blob_file_id: BlobFileId) -> Option<Arc<File>>;
+
+    /// Evicts all file descriptors of a blob file
+    ///
+    /// Called when the blob file is dropped from the value log,
+    /// so cached descriptors do not keep deleted files alive.
+    fn invalidate_segment(&self, vlog_id: ValueLogId, blob_file_id: BlobFileId) {
+        let _ = (vlog_id, blob_file_id);
+    }
+
+    /// Evicts all file descriptors of a value log
+    ///
+    /// Called when the value log is cleared or dropped.
+    fn invalidate_vlog(&self, vlog_id: ValueLogId) {
+        let _ = vlog_id;
+    }
+}
+
+/// Smallest capacity a shard of [`LruFDCache::with_capacity`] gets
+const MIN_SHARD_CAPACITY: usize = 64;
+
+type FdCacheKey = (ValueLogId, BlobFileId);
+
+// NOTE: Each segment has a single descriptor, so descriptors are grouped by value log
+impl GroupedKey for FdCacheKey {
+    type Group = ValueLogId;
+
+    fn group(&self) -> Self::Group {
+        self.0
+    }
+}
+
+/// File descriptor cache that keeps up to a fixed amount of files open,
+/// evicting the least recently used ones
+///
+/// Each blob file is opened at most once, and shared by all concurrent readers.
+/// Files that are evicted while they are being read are closed once
+/// the last reader is done.
+///
+/// Can be shared across multiple value logs by cloning it, as descriptors are
+/// keyed by their [`ValueLogId`].
+#[derive(Clone)]
+#[allow(clippy::module_name_repetitions)]
+pub struct LruFDCache(Arc<ShardedLru<FdCacheKey, Arc<File>>>);
+
+impl LruFDCache {
+    /// Creates a new file descriptor cache that keeps up to `capacity` files open.
+    ///
+    /// Small caches use a single shard, so their capacity is exact.
+    /// Larger caches are sharded, with at least 64 files per shard.
+    #[must_use]
+    pub fn with_capacity(capacity: usize) -> Self {
+        let shard_count = (capacity / MIN_SHARD_CAPACITY).clamp(1, DEFAULT_SHARD_COUNT);
+        Self::with_shard_count(capacity, shard_count)
+    }
+
+    /// Creates a new file descriptor cache that keeps up to `capacity` files open,
+    /// split into the given amount of shards.
+    ///
+    /// More shards reduce lock contention, but each shard only gets
+    /// its share of the capacity, rounded up, so up to `shard_count - 1`
+    /// more files may be kept open.
+    #[must_use]
+    pub fn with_shard_count(capacity: usize, shard_count: usize) -> Self {
+        Self(Arc::new(ShardedLru::new(capacity as u64, shard_count)))
+    }
+
+    /// Returns the maximum amount of cached file descriptors.
+    #[must_use]
+    #[allow(clippy::cast_possible_truncation)]
+    pub fn capacity(&self) -> usize {
+        self.0.capacity() as usize
+    }
+
+    /// Returns the amount of cached file descriptors.
+    #[must_use]
+    pub fn len(&self) -> usize {
+        self.0.len()
+    }
+
+    /// Returns `true` if no file descriptors are cached.
+    #[must_use]
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// Returns the amount of cache hits.
+    #[must_use]
+    pub fn hits(&self) -> u64 {
+        self.0.hits()
+    }
+
+    /// Returns the amount of cache misses.
+    #[must_use]
+    pub fn misses(&self) -> u64 {
+        self.0.misses()
+    }
+}
+
+impl FDCache for LruFDCache {
+    fn insert(&self, vlog_id: ValueLogId, blob_file_id: BlobFileId, fd: Arc<File>) {
+        self.0.insert((vlog_id, blob_file_id), fd, 1);
+    }
+
+    fn get(&self, vlog_id: ValueLogId, blob_file_id: BlobFileId) -> Option<Arc<File>> {
+        self.0.get(&(vlog_id, blob_file_id))
+    }
+
+    fn invalidate_segment(&self, vlog_id: ValueLogId, blob_file_id: BlobFileId) {
+        self.0.remove(&(vlog_id, blob_file_id));
+    }
+
+    fn invalidate_vlog(&self, vlog_id: ValueLogId) {
+        self.0.remove_group(&vlog_id);
+    }
 }
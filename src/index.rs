@@ -28,6 +28,10 @@ pub trait Reader {
 pub trait Writer {
     /// Inserts a value handle into the index write batch.
     ///
+    /// `size` is the length of the value. Values of 4 GiB or more
+    /// (see [`crate::Config::large_value_chunk_size`]) need to pass `u32::MAX`,
+    /// so the size must saturate instead of being truncated.
+    ///
     /// # Errors
     ///
     /// Will return `Err` if an IO error occurs.
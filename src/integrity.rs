@@ -0,0 +1,464 @@
+// Copyright (c) 2024-present, fjall-rs
+// This source code is licensed under both the Apache 2.0 and MIT License
+// (found in the LICENSE-* files in the repository)
+
+use crate::{
+    coding::DecodeError,
+    id::SegmentId,
+    segment::{
+        header::{compute_checksum, BlobHeader, BlobKind, CompressionType},
+        trailer::SegmentFileTrailer,
+    },
+    Compressor, Slice, UserKey,
+};
+use std::{
+    fs::File,
+    io::{BufReader, ErrorKind},
+    path::Path,
+};
+
+/// Segment metadata field that can be checked against the segment's blobs
+#[derive(Copy, Clone, Debug, Eq, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Deserialize, serde::Serialize))]
+pub enum MetadataField {
+    /// Offset of the segment metadata, which is where the blobs end
+    MetadataPtr,
+
+    /// Amount of blobs
+    ItemCount,
+
+    /// Sum of blob sizes, as stored on disk
+    CompressedBytes,
+
+    /// Sum of uncompressed value sizes
+    TotalUncompressedBytes,
+}
+
+/// Kind of corruption found in a segment
+#[derive(Clone, Debug, Eq, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Deserialize, serde::Serialize))]
+pub enum CorruptionKind {
+    /// The segment file could not be read
+    Unreadable,
+
+    /// The segment trailer (or the metadata it points to) is invalid
+    InvalidTrailer,
+
+    /// A blob header could not be parsed
+    InvalidBlobHeader,
+
+    /// The segment ends in the middle of a blob
+    Truncated,
+
+    /// A blob's checksum does not match its key and value
+    ChecksumMismatch {
+        /// Checksum stored in the blob header
+        expected: u64,
+
+        /// Checksum computed over the blob's key and value
+        actual: u64,
+    },
+
+    /// A compressed blob could not be decompressed
+    Decompress,
+
+    /// A metadata field does not match what was actually read from the segment
+    MetadataMismatch {
+        /// Metadata field that does not match
+        field: MetadataField,
+
+        /// Value stored in the segment metadata
+        expected: u64,
+
+        /// Value computed from the segment's blobs
+        actual: u64,
+    },
+
+    /// The metadata key range does not match the keys actually stored in the segment
+    KeyRangeMismatch,
+}
+
+/// A single corruption found during integrity verification
+#[derive(Clone, Debug, Eq, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Deserialize, serde::Serialize))]
+pub struct Corruption {
+    /// Segment the corruption was found in
+    pub segment_id: SegmentId,
+
+    /// Offset of the affected blob, if the corruption concerns a single blob
+    pub offset: Option<u64>,
+
+    /// What is wrong
+    pub kind: CorruptionKind,
+}
+
+/// Report of a full integrity verification of a value log
+#[derive(Debug, Default)]
+#[cfg_attr(feature = "serde", derive(serde::Deserialize, serde::Serialize))]
+#[allow(clippy::module_name_repetitions)]
+pub struct IntegrityReport {
+    /// Amount of segments that were checked
+    pub segment_count: usize,
+
+    /// Amount of blobs that were checked
+    pub blob_count: u64,
+
+    /// Amount of blob bytes (as stored on disk) that were checked
+    pub blob_bytes: u64,
+
+    /// All corruptions that were found
+    pub corruptions: Vec<Corruption>,
+}
+
+impl std::fmt::Display for IntegrityReport {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        writeln!(f, "--- Integrity report ---")?;
+        writeln!(f, "# segments   : {}", self.segment_count)?;
+        writeln!(f, "# blobs      : {}", self.blob_count)?;
+        writeln!(f, "Blob bytes   : {}", self.blob_bytes)?;
+        writeln!(f, "# corruptions: {}", self.corruptions.len())?;
+        for corruption in &self.corruptions {
+            match corruption.offset {
+                Some(offset) => writeln!(
+                    f,
+                    "  segment {} @ {offset}: {:?}",
+                    corruption.segment_id, corruption.kind
+                )?,
+                None => writeln!(
+                    f,
+                    "  segment {}: {:?}",
+                    corruption.segment_id, corruption.kind
+                )?,
+            }
+        }
+        writeln!(f, "--- Integrity report done ---")?;
+        Ok(())
+    }
+}
+
+impl IntegrityReport {
+    /// Returns `true` if no corruption was found.
+    #[must_use]
+    pub fn is_ok(&self) -> bool {
+        self.corruptions.is_empty()
+    }
+
+    /// Returns the IDs of all segments that contain corruptions, in ascending order.
+    #[must_use]
+    pub fn corrupt_segments(&self) -> Vec<SegmentId> {
+        let mut ids = self
+            .corruptions
+            .iter()
+            .map(|x| x.segment_id)
+            .collect::<Vec<_>>();
+        ids.sort_unstable();
+        ids.dedup();
+        ids
+    }
+}
+
+fn decode_error_kind(e: &DecodeError) -> CorruptionKind {
+    match e {
+        DecodeError::Io(e) if e.kind() == ErrorKind::UnexpectedEof => CorruptionKind::Truncated,
+        DecodeError::Io(_) => CorruptionKind::Unreadable,
+        _ => CorruptionKind::InvalidBlobHeader,
+    }
+}
+
+fn push_corruption(
+    corruptions: &mut Vec<Corruption>,
+    segment_id: SegmentId,
+    offset: Option<u64>,
+    kind: CorruptionKind,
+) {
+    log::warn!("Found corruption in segment {segment_id} (offset={offset:?}): {kind:?}");
+
+    corruptions.push(Corruption {
+        segment_id,
+        offset,
+        kind,
+    });
+}
+
+/// Returns the uncompressed length of a blob.
+///
+/// V1 blobs do not store it, so they need to be decompressed.
+///
+/// Returns `None` if decompression failed.
+pub fn uncompressed_len<C: Compressor>(
+    header: &BlobHeader,
+    value: &[u8],
+    compressor: Option<&C>,
+) -> Option<u64> {
+    match (header.uncompressed_len, compressor) {
+        (Some(len), _) => Some(u64::from(len)),
+        (None, Some(compressor)) if header.compression != Some(CompressionType::None) => compressor
+            .decompress(value)
+            .ok()
+            .map(|decompressed| decompressed.len() as u64),
+        (None, _) => Some(u64::from(header.value_len)),
+    }
+}
+
+/// Opens a segment file and reads its trailer.
+///
+/// Returns `None` if the file could not be read at all.
+fn open_segment_file(
+    segment_id: SegmentId,
+    path: &Path,
+    corruptions: &mut Vec<Corruption>,
+) -> Option<(Option<SegmentFileTrailer>, BufReader<File>, u64)> {
+    let trailer = match SegmentFileTrailer::from_file(path) {
+        Ok(trailer) => Some(trailer),
+        Err(crate::Error::Io(e)) if e.kind() != ErrorKind::UnexpectedEof => {
+            log::error!("Could not read segment file {}: {e:?}", path.display());
+            push_corruption(corruptions, segment_id, None, CorruptionKind::Unreadable);
+            return None;
+        }
+        Err(_) => {
+            let kind = CorruptionKind::InvalidTrailer;
+            push_corruption(corruptions, segment_id, None, kind);
+            None
+        }
+    };
+
+    let file = File::open(path).and_then(|file| {
+        let len = file.metadata()?.len();
+        Ok((BufReader::new(file), len))
+    });
+
+    match file {
+        Ok((reader, len)) => Some((trailer, reader, len)),
+        Err(e) => {
+            log::error!("Could not read segment file {}: {e:?}", path.display());
+            push_corruption(corruptions, segment_id, None, CorruptionKind::Unreadable);
+            None
+        }
+    }
+}
+
+/// A blob that was read while walking through a segment file
+pub struct WalkedBlob<'a> {
+    /// Offset of the blob in its segment
+    pub offset: u64,
+
+    pub header: &'a BlobHeader,
+    pub key: &'a UserKey,
+    pub value: &'a Slice,
+
+    /// Length of the value after decompression
+    pub uncompressed_len: u64,
+
+    /// `false` if the checksum did not match, or the value could not be decompressed
+    pub intact: bool,
+}
+
+/// Result of walking through all blobs of a segment file
+#[derive(Default)]
+pub struct SegmentWalk {
+    /// Segment trailer, if it is valid
+    pub trailer: Option<SegmentFileTrailer>,
+
+    /// Offset after the last readable blob
+    pub end_offset: u64,
+
+    /// `true` if the walk reached the segment metadata without hitting a broken blob
+    pub complete: bool,
+
+    /// Amount of blobs, including the chunks of large values
+    pub blob_count: u64,
+
+    /// Amount of items, counting each large value once, like the segment metadata does
+    pub item_count: u64,
+
+    pub compressed_bytes: u64,
+    pub uncompressed_bytes: u64,
+
+    pub first_key: Option<UserKey>,
+    pub last_key: Option<UserKey>,
+}
+
+/// Walks through every blob of a segment file, verifying blob headers and checksums.
+///
+/// Every readable blob is passed to `on_blob`, every corruption is added to `corruptions`.
+///
+/// Returns `None` if the file could not be read at all.
+pub fn walk_segment_file<C: Compressor + Clone>(
+    segment_id: SegmentId,
+    path: &Path,
+    compressor: Option<&C>,
+    corruptions: &mut Vec<Corruption>,
+    mut on_blob: impl FnMut(WalkedBlob<'_>),
+) -> Option<SegmentWalk> {
+    let (trailer, mut reader, file_len) = open_segment_file(segment_id, path, corruptions)?;
+
+    // NOTE: Blobs can never extend past the metadata (or the end of file)
+    let blob_limit = trailer.as_ref().map_or(file_len, |x| x.metadata_ptr);
+
+    let mut walk = SegmentWalk {
+        trailer,
+        ..Default::default()
+    };
+
+    loop {
+        let offset = walk.end_offset;
+
+        // NOTE: Without a valid trailer, walk until the metadata magic or the first error
+        if offset >= blob_limit && walk.trailer.is_some() {
+            walk.complete = true;
+            break;
+        }
+
+        let (header, key) = match BlobHeader::decode_with_key(&mut reader) {
+            Ok(Some(item)) => item,
+            Ok(None) => {
+                walk.complete = true;
+                break;
+            }
+            Err(e) => {
+                push_corruption(corruptions, segment_id, Some(offset), decode_error_kind(&e));
+                break;
+            }
+        };
+
+        // NOTE: Check the length before reading, so a corrupt header
+        // does not cause a huge allocation
+        if offset + header.blob_len() > blob_limit {
+            push_corruption(
+                corruptions,
+                segment_id,
+                Some(offset),
+                CorruptionKind::Truncated,
+            );
+            break;
+        }
+
+        let value = match Slice::from_reader(&mut reader, header.value_len as usize) {
+            Ok(value) => value,
+            Err(e) => {
+                let kind = decode_error_kind(&DecodeError::Io(e));
+                push_corruption(corruptions, segment_id, Some(offset), kind);
+                break;
+            }
+        };
+
+        let mut intact = true;
+
+        let checksum = compute_checksum(&key, &value);
+        if checksum != header.checksum {
+            intact = false;
+
+            let kind = CorruptionKind::ChecksumMismatch {
+                expected: header.checksum,
+                actual: checksum,
+            };
+            push_corruption(corruptions, segment_id, Some(offset), kind);
+        }
+
+        let uncompressed_len = uncompressed_len(&header, &value, compressor).unwrap_or_else(|| {
+            intact = false;
+            push_corruption(
+                corruptions,
+                segment_id,
+                Some(offset),
+                CorruptionKind::Decompress,
+            );
+            u64::from(header.value_len)
+        });
+
+        on_blob(WalkedBlob {
+            offset,
+            header: &header,
+            key: &key,
+            value: &value,
+            uncompressed_len,
+            intact,
+        });
+
+        if walk.first_key.is_none() {
+            walk.first_key = Some(key.clone());
+        }
+        walk.last_key = Some(key);
+
+        walk.blob_count += 1;
+        walk.compressed_bytes += u64::from(header.value_len);
+
+        if header.kind != BlobKind::Chunk {
+            walk.item_count += 1;
+        }
+        if header.kind != BlobKind::ChunkManifest {
+            walk.uncompressed_bytes += uncompressed_len;
+        }
+        walk.end_offset += header.blob_len();
+    }
+
+    Some(walk)
+}
+
+/// Walks through every blob of a segment file, verifying headers, checksums,
+/// the trailer and the segment metadata.
+///
+/// Every readable blob is passed to `on_blob`, corruptions are added to the report.
+pub fn verify_segment_file<C: Compressor + Clone>(
+    segment_id: SegmentId,
+    path: &Path,
+    compressor: Option<&C>,
+    report: &mut IntegrityReport,
+    on_blob: impl FnMut(WalkedBlob<'_>),
+) {
+    let corruptions = &mut report.corruptions;
+
+    let Some(walk) = walk_segment_file(segment_id, path, compressor, corruptions, on_blob) else {
+        return;
+    };
+
+    report.blob_count += walk.blob_count;
+    report.blob_bytes += walk.compressed_bytes;
+
+    // NOTE: If the walk was aborted, the corruption was already reported,
+    // and comparing the metadata would only add noise
+    let Some(trailer) = walk.trailer.filter(|_| walk.complete) else {
+        return;
+    };
+
+    let meta = &trailer.metadata;
+
+    for (field, expected, actual) in [
+        (
+            MetadataField::MetadataPtr,
+            trailer.metadata_ptr,
+            walk.end_offset,
+        ),
+        (MetadataField::ItemCount, meta.item_count, walk.item_count),
+        (
+            MetadataField::CompressedBytes,
+            meta.compressed_bytes,
+            walk.compressed_bytes,
+        ),
+        (
+            MetadataField::TotalUncompressedBytes,
+            meta.total_uncompressed_bytes,
+            walk.uncompressed_bytes,
+        ),
+    ] {
+        if expected != actual {
+            let kind = CorruptionKind::MetadataMismatch {
+                field,
+                expected,
+                actual,
+            };
+            push_corruption(corruptions, segment_id, None, kind);
+        }
+    }
+
+    if let (Some(first_key), Some(last_key)) = (walk.first_key, walk.last_key) {
+        if meta.key_range.min() != &first_key || meta.key_range.max() != &last_key {
+            push_corruption(
+                corruptions,
+                segment_id,
+                None,
+                CorruptionKind::KeyRangeMismatch,
+            );
+        }
+    }
+}
@@ -46,10 +46,12 @@
 #![warn(clippy::expect_used)]
 #![allow(clippy::missing_const_for_fn)]
 #![warn(clippy::multiple_crate_versions)]
-// the bytes feature uses unsafe to improve from_reader performance; so we need to relax this lint
-#![cfg_attr(feature = "bytes", deny(unsafe_code))]
-#![cfg_attr(not(feature = "bytes"), forbid(unsafe_code))]
+// the bytes feature uses unsafe to improve from_reader performance,
+// and the mmap feature needs unsafe to map segment files; so we need to relax this lint
+#![cfg_attr(any(feature = "bytes", feature = "mmap"), deny(unsafe_code))]
+#![cfg_attr(not(any(feature = "bytes", feature = "mmap")), forbid(unsafe_code))]
 
+mod backup;
 mod blob_cache;
 mod fd_cache;
 
@@ -63,35 +65,50 @@ mod gc;
 mod handle;
 mod id;
 mod index;
+mod integrity;
 mod key_range;
+mod lock;
+mod lru;
 mod manifest;
 mod path;
+mod prefetch;
+mod repair;
 mod slice;
 
 #[doc(hidden)]
 pub mod scanner;
 
 mod segment;
+mod snapshot;
+mod stat;
 mod value;
 mod value_log;
+mod value_reader;
 mod version;
 
 pub(crate) type HashMap<K, V> = std::collections::HashMap<K, V, xxhash_rust::xxh3::Xxh3Builder>;
 
 pub use {
-    blob_cache::BlobCache,
+    backup::{BackupManifest, BackupSegment},
+    blob_cache::{BlobCache, LruBlobCache},
     compression::Compressor,
     config::Config,
     error::{Error, Result},
-    fd_cache::{BlobFileId, FDCache},
+    fd_cache::{BlobFileId, FDCache, LruFDCache},
     gc::report::GcReport,
     gc::{GcStrategy, SpaceAmpStrategy, StaleThresholdStrategy},
     handle::ValueHandle,
     index::{Reader as IndexReader, Writer as IndexWriter},
+    integrity::{Corruption, CorruptionKind, IntegrityReport, MetadataField},
+    prefetch::{Prefetch, DEFAULT_READAHEAD},
+    repair::RepairReport,
     segment::multi_writer::MultiWriter as SegmentWriter,
     slice::Slice,
+    snapshot::Snapshot,
+    stat::BlobStat,
     value::{UserKey, UserValue},
     value_log::{ValueLog, ValueLogId},
+    value_reader::ValueReader,
     version::Version,
 };
 
@@ -0,0 +1,52 @@
+// Copyright (c) 2024-present, fjall-rs
+// This source code is licensed under both the Apache 2.0 and MIT License
+// (found in the LICENSE-* files in the repository)
+
+use fs2::FileExt;
+use std::{fs::File, path::Path};
+
+pub const LOCK_FILE: &str = ".vlog.lock";
+
+/// Exclusive advisory lock on a value log directory
+///
+/// Prevents multiple value log instances (in the same or different processes)
+/// from modifying the same directory.
+///
+/// The lock is released when dropped.
+pub struct DirectoryLock(File);
+
+impl DirectoryLock {
+    /// Acquires the lock of the given value log directory.
+    ///
+    /// Returns [`crate::Error::Locked`] if the lock is already held.
+    pub fn acquire(folder: &Path) -> crate::Result<Self> {
+        let path = folder.join(LOCK_FILE);
+        log::trace!("Acquiring vLog lock at {}", path.display());
+
+        let file = std::fs::OpenOptions::new()
+            .create(true)
+            .truncate(false)
+            .write(true)
+            .open(&path)?;
+
+        if let Err(e) = file.try_lock_exclusive() {
+            if e.raw_os_error() == fs2::lock_contended_error().raw_os_error() {
+                log::error!("vLog at {} is already locked", folder.display());
+                return Err(crate::Error::Locked);
+            }
+
+            return Err(e.into());
+        }
+
+        Ok(Self(file))
+    }
+}
+
+impl Drop for DirectoryLock {
+    fn drop(&mut self) {
+        // NOTE: Closing the file releases the lock anyway
+        if let Err(e) = FileExt::unlock(&self.0) {
+            log::warn!("Could not release vLog lock: {e:?}");
+        }
+    }
+}
@@ -0,0 +1,382 @@
+// Copyright (c) 2024-present, fjall-rs
+// This source code is licensed under both the Apache 2.0 and MIT License
+// (found in the LICENSE-* files in the repository)
+
+use crate::HashMap;
+use std::{
+    collections::{BTreeMap, HashSet},
+    hash::{BuildHasher, Hash},
+    sync::{
+        atomic::{AtomicU64, Ordering},
+        Mutex,
+    },
+};
+
+/// Default amount of shards of a cache
+pub const DEFAULT_SHARD_COUNT: usize = 16;
+
+/// Key of a cached item, which belongs to a group (e.g. the segment of a blob)
+///
+/// Every shard keeps an index of the keys of each group,
+/// so a group can be removed without scanning all cached items.
+pub trait GroupedKey: Clone + Eq + Hash {
+    type Group: Eq + Hash;
+
+    fn group(&self) -> Self::Group;
+}
+
+type KeySet<K> = HashSet<K, xxhash_rust::xxh3::Xxh3Builder>;
+
+struct Entry<V> {
+    value: V,
+    weight: u64,
+
+    /// Position in the recency list
+    tick: u64,
+}
+
+/// A single shard of a cache, which evicts the least recently used items
+/// once its capacity is exceeded
+struct Shard<K: GroupedKey, V> {
+    items: HashMap<K, Entry<V>>,
+
+    /// Keys ordered by last access, oldest first
+    recency: BTreeMap<u64, K>,
+
+    /// Keys of each group
+    groups: HashMap<K::Group, KeySet<K>>,
+
+    tick: u64,
+    weight: u64,
+    capacity: u64,
+}
+
+fn unlink_group<K: GroupedKey>(groups: &mut HashMap<K::Group, KeySet<K>>, key: &K) {
+    let group = key.group();
+
+    if let Some(keys) = groups.get_mut(&group) {
+        keys.remove(key);
+
+        if keys.is_empty() {
+            groups.remove(&group);
+        }
+    }
+}
+
+impl<K: GroupedKey, V: Clone> Shard<K, V> {
+    fn new(capacity: u64) -> Self {
+        Self {
+            items: HashMap::default(),
+            recency: BTreeMap::new(),
+            groups: HashMap::default(),
+            tick: 0,
+            weight: 0,
+            capacity,
+        }
+    }
+
+    fn next_tick(&mut self) -> u64 {
+        self.tick += 1;
+        self.tick
+    }
+
+    fn get(&mut self, key: &K) -> Option<V> {
+        let tick = self.next_tick();
+
+        let entry = self.items.get_mut(key)?;
+        self.recency.remove(&entry.tick);
+        entry.tick = tick;
+        self.recency.insert(tick, key.clone());
+
+        Some(entry.value.clone())
+    }
+
+    fn insert(&mut self, key: K, value: V, weight: u64) {
+        self.remove(&key);
+
+        // NOTE: Items that can never fit are not cached,
+        // otherwise they would evict everything else
+        if weight > self.capacity {
+            return;
+        }
+
+        let tick = self.next_tick();
+        self.recency.insert(tick, key.clone());
+        self.groups
+            .entry(key.group())
+            .or_default()
+            .insert(key.clone());
+        self.items.insert(
+            key,
+            Entry {
+                value,
+                weight,
+                tick,
+            },
+        );
+        self.weight += weight;
+
+        while self.weight > self.capacity {
+            let Some((_, key)) = self.recency.pop_first() else {
+                break;
+            };
+
+            self.remove(&key);
+        }
+    }
+
+    fn remove(&mut self, key: &K) -> Option<V> {
+        let entry = self.items.remove(key)?;
+        self.recency.remove(&entry.tick);
+        unlink_group(&mut self.groups, key);
+        self.weight -= entry.weight;
+        Some(entry.value)
+    }
+
+    fn remove_group(&mut self, group: &K::Group) {
+        let Some(keys) = self.groups.remove(group) else {
+            return;
+        };
+
+        for key in keys {
+            if let Some(entry) = self.items.remove(&key) {
+                self.recency.remove(&entry.tick);
+                self.weight -= entry.weight;
+            }
+        }
+    }
+
+    fn retain(&mut self, mut f: impl FnMut(&K) -> bool) {
+        let Self {
+            items,
+            recency,
+            groups,
+            weight,
+            ..
+        } = self;
+
+        items.retain(|key, entry| {
+            let keep = f(key);
+
+            if !keep {
+                recency.remove(&entry.tick);
+                unlink_group(groups, key);
+                *weight -= entry.weight;
+            }
+
+            keep
+        });
+    }
+
+    fn clear(&mut self) {
+        self.items.clear();
+        self.recency.clear();
+        self.groups.clear();
+        self.weight = 0;
+    }
+}
+
+/// Sharded, weight-bounded least-recently-used cache
+///
+/// Each shard is protected by its own lock, and has an equal share of the capacity.
+pub struct ShardedLru<K: GroupedKey, V> {
+    shards: Box<[Mutex<Shard<K, V>>]>,
+    hasher: xxhash_rust::xxh3::Xxh3Builder,
+    capacity: u64,
+
+    hits: AtomicU64,
+    misses: AtomicU64,
+}
+
+impl<K: GroupedKey, V: Clone> ShardedLru<K, V> {
+    /// Creates a new cache with the given total capacity (in weight units).
+    pub fn new(capacity: u64, shard_count: usize) -> Self {
+        let shard_count = shard_count.max(1);
+        let shard_capacity = capacity.div_ceil(shard_count as u64);
+
+        Self {
+            shards: (0..shard_count)
+                .map(|_| Mutex::new(Shard::new(shard_capacity)))
+                .collect(),
+            hasher: xxhash_rust::xxh3::Xxh3Builder::new(),
+            capacity,
+            hits: AtomicU64::default(),
+            misses: AtomicU64::default(),
+        }
+    }
+
+    fn shard(&self, key: &K) -> &Mutex<Shard<K, V>> {
+        // NOTE: Truncation is fine, we only need the hash to pick a shard
+        #[allow(clippy::cast_possible_truncation)]
+        let idx = self.hasher.hash_one(key) as usize % self.shards.len();
+
+        // NOTE: The index is always in bounds because of the modulo
+        #[allow(clippy::expect_used)]
+        self.shards.get(idx).expect("shard should exist")
+    }
+
+    fn for_each_shard(&self, mut f: impl FnMut(&mut Shard<K, V>)) {
+        for shard in &*self.shards {
+            f(&mut shard.lock().expect("lock is poisoned"));
+        }
+    }
+
+    pub fn get(&self, key: &K) -> Option<V> {
+        let item = self.shard(key).lock().expect("lock is poisoned").get(key);
+
+        if item.is_some() {
+            self.hits.fetch_add(1, Ordering::Relaxed);
+        } else {
+            self.misses.fetch_add(1, Ordering::Relaxed);
+        }
+
+        item
+    }
+
+    pub fn insert(&self, key: K, value: V, weight: u64) {
+        self.shard(&key)
+            .lock()
+            .expect("lock is poisoned")
+            .insert(key, value, weight);
+    }
+
+    pub fn remove(&self, key: &K) -> Option<V> {
+        self.shard(key)
+            .lock()
+            .expect("lock is poisoned")
+            .remove(key)
+    }
+
+    /// Removes all items of a group.
+    ///
+    /// Only the group's keys are visited, using the group index of each shard.
+    pub fn remove_group(&self, group: &K::Group) {
+        self.for_each_shard(|shard| shard.remove_group(group));
+    }
+
+    /// Removes all items for which the predicate returns `false`.
+    ///
+    /// This visits every cached item, so prefer [`ShardedLru::remove_group`] if possible.
+    pub fn retain(&self, mut f: impl FnMut(&K) -> bool) {
+        self.for_each_shard(|shard| shard.retain(&mut f));
+    }
+
+    pub fn clear(&self) {
+        self.for_each_shard(Shard::clear);
+    }
+
+    pub fn capacity(&self) -> u64 {
+        self.capacity
+    }
+
+    /// Returns the total weight of all cached items.
+    pub fn weight(&self) -> u64 {
+        let mut sum = 0;
+        self.for_each_shard(|shard| sum += shard.weight);
+        sum
+    }
+
+    pub fn len(&self) -> usize {
+        let mut sum = 0;
+        self.for_each_shard(|shard| sum += shard.items.len());
+        sum
+    }
+
+    pub fn hits(&self) -> u64 {
+        self.hits.load(Ordering::Relaxed)
+    }
+
+    pub fn misses(&self) -> u64 {
+        self.misses.load(Ordering::Relaxed)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use test_log::test;
+
+    impl GroupedKey for u64 {
+        type Group = u64;
+
+        fn group(&self) -> u64 {
+            self / 10
+        }
+    }
+
+    #[test]
+    fn lru_evicts_least_recently_used() {
+        let cache = ShardedLru::<u64, u64>::new(3, 1);
+
+        cache.insert(1, 1, 1);
+        cache.insert(2, 2, 1);
+        cache.insert(3, 3, 1);
+
+        // NOTE: Touch 1, so 2 is the least recently used item
+        assert_eq!(Some(1), cache.get(&1));
+
+        cache.insert(4, 4, 1);
+        assert_eq!(3, cache.len());
+        assert_eq!(None, cache.get(&2));
+        assert_eq!(Some(1), cache.get(&1));
+        assert_eq!(Some(3), cache.get(&3));
+        assert_eq!(Some(4), cache.get(&4));
+
+        assert_eq!(4, cache.hits());
+        assert_eq!(1, cache.misses());
+    }
+
+    #[test]
+    fn lru_weight() {
+        let cache = ShardedLru::<u64, u64>::new(10, 1);
+
+        cache.insert(1, 1, 4);
+        cache.insert(2, 2, 4);
+        assert_eq!(8, cache.weight());
+
+        cache.insert(3, 3, 4);
+        assert_eq!(8, cache.weight());
+        assert_eq!(None, cache.get(&1));
+
+        // NOTE: Overwriting an item replaces its weight
+        cache.insert(2, 2, 1);
+        assert_eq!(5, cache.weight());
+
+        // NOTE: Items larger than the capacity are never cached
+        cache.insert(5, 5, 11);
+        assert_eq!(None, cache.get(&5));
+        assert_eq!(5, cache.weight());
+
+        cache.retain(|&key| key != 3);
+        assert_eq!(1, cache.weight());
+        assert_eq!(1, cache.len());
+
+        cache.clear();
+        assert_eq!(0, cache.weight());
+        assert_eq!(0, cache.len());
+    }
+
+    #[test]
+    fn lru_remove_group() {
+        let cache = ShardedLru::<u64, u64>::new(100, 4);
+
+        for key in [1, 2, 11, 12, 13, 21] {
+            cache.insert(key, key, 1);
+        }
+
+        cache.remove_group(&1);
+        assert_eq!(3, cache.len());
+        assert_eq!(3, cache.weight());
+        assert_eq!(None, cache.get(&11));
+        assert_eq!(Some(1), cache.get(&1));
+        assert_eq!(Some(21), cache.get(&21));
+
+        // NOTE: Empty groups are dropped from the group index
+        cache.retain(|&key| key != 1 && key != 2);
+        assert_eq!(1, cache.len());
+        cache.for_each_shard(|shard| {
+            assert!(!shard.groups.contains_key(&0));
+            assert!(!shard.groups.contains_key(&1));
+        });
+    }
+}
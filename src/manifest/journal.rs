@@ -0,0 +1,513 @@
+// Copyright (c) 2024-present, fjall-rs
+// This source code is licensed under both the Apache 2.0 and MIT License
+// (found in the LICENSE-* files in the repository)
+
+use super::rewrite_atomic;
+use crate::{
+    coding::{DecodeError, EncodeError},
+    id::SegmentId,
+};
+use byteorder::{BigEndian, ReadBytesExt, WriteBytesExt};
+use std::{
+    fs::File,
+    io::{Cursor, Read, Write},
+    path::{Path, PathBuf},
+};
+
+pub const JOURNAL_MAGIC: &[u8] = &[b'V', b'L', b'O', b'G', b'M', b'F', b'J', 1];
+
+/// Amount of edits after which the journal is compacted into a single snapshot
+pub const DEFAULT_MAX_EDITS: usize = 1_000;
+
+const TAG_SNAPSHOT: u8 = 0;
+const TAG_EDIT: u8 = 1;
+
+/// Size of a record, excluding its payload
+///
+/// tag + payload len + checksum
+const RECORD_OVERHEAD: usize = 1 + 4 + 8;
+
+/// Outcome of parsing a record from the journal
+#[derive(Debug, Eq, PartialEq)]
+enum Decoded {
+    /// A valid record and its length in bytes
+    Record(Record, usize),
+
+    /// The journal ends before the record does
+    Incomplete,
+
+    /// The record is complete, but its checksum does not match
+    ChecksumMismatch(usize),
+}
+
+/// A single record in the manifest journal
+///
+/// \[tag; 1\] \[payload len; 4\] \[payload\] \[checksum; 8\]
+///
+/// The checksum is computed over the tag, the payload length and the payload.
+#[derive(Debug, Eq, PartialEq)]
+pub enum Record {
+    /// Full list of segment IDs
+    Snapshot(Vec<SegmentId>),
+
+    /// Segments that were added or removed since the previous record
+    Edit {
+        added: Vec<SegmentId>,
+        removed: Vec<SegmentId>,
+    },
+}
+
+fn write_ids<W: Write>(writer: &mut W, ids: &[SegmentId]) -> std::io::Result<()> {
+    writer.write_u64::<BigEndian>(ids.len() as u64)?;
+
+    for id in ids {
+        writer.write_u64::<BigEndian>(*id)?;
+    }
+
+    Ok(())
+}
+
+fn read_ids<R: Read>(reader: &mut R) -> std::io::Result<Vec<SegmentId>> {
+    let cnt = reader.read_u64::<BigEndian>()?;
+
+    let mut ids = vec![];
+
+    for _ in 0..cnt {
+        ids.push(reader.read_u64::<BigEndian>()?);
+    }
+
+    Ok(ids)
+}
+
+fn compute_checksum(tag: u8, payload_len: u32, payload: &[u8]) -> u64 {
+    let mut hasher = xxhash_rust::xxh3::Xxh3::new();
+    hasher.update(&[tag]);
+    hasher.update(&payload_len.to_be_bytes());
+    hasher.update(payload);
+    hasher.digest()
+}
+
+impl Record {
+    /// Serializes the record, including its header and checksum.
+    pub fn encode_into_vec(&self) -> Result<Vec<u8>, EncodeError> {
+        let mut payload = vec![];
+
+        let tag = match self {
+            Self::Snapshot(ids) => {
+                write_ids(&mut payload, ids)?;
+                TAG_SNAPSHOT
+            }
+            Self::Edit { added, removed } => {
+                write_ids(&mut payload, added)?;
+                write_ids(&mut payload, removed)?;
+                TAG_EDIT
+            }
+        };
+
+        let mut bytes = Vec::with_capacity(RECORD_OVERHEAD + payload.len());
+        bytes.write_u8(tag)?;
+
+        // NOTE: Truncation is OK, a payload cannot realistically exceed 4 GiB
+        #[allow(clippy::cast_possible_truncation)]
+        let payload_len = payload.len() as u32;
+
+        bytes.write_u32::<BigEndian>(payload_len)?;
+        bytes.write_all(&payload)?;
+        bytes.write_u64::<BigEndian>(compute_checksum(tag, payload_len, &payload))?;
+
+        Ok(bytes)
+    }
+
+    /// Parses the next record from the journal.
+    fn decode_from(bytes: &[u8]) -> Result<Decoded, DecodeError> {
+        let mut cursor = Cursor::new(bytes);
+
+        let Ok(tag) = cursor.read_u8() else {
+            return Ok(Decoded::Incomplete);
+        };
+        let Ok(payload_len) = cursor.read_u32::<BigEndian>() else {
+            return Ok(Decoded::Incomplete);
+        };
+
+        let payload_start = 1 + 4;
+        let payload_end = payload_start + payload_len as usize;
+        let record_len = payload_end + 8;
+
+        let (Some(payload), Some(mut checksum)) = (
+            bytes.get(payload_start..payload_end),
+            bytes.get(payload_end..record_len),
+        ) else {
+            return Ok(Decoded::Incomplete);
+        };
+
+        if checksum.read_u64::<BigEndian>()? != compute_checksum(tag, payload_len, payload) {
+            return Ok(Decoded::ChecksumMismatch(record_len));
+        }
+
+        let mut reader = Cursor::new(payload);
+
+        let record = match tag {
+            TAG_SNAPSHOT => Self::Snapshot(read_ids(&mut reader)?),
+            TAG_EDIT => Self::Edit {
+                added: read_ids(&mut reader)?,
+                removed: read_ids(&mut reader)?,
+            },
+            _ => return Err(DecodeError::InvalidTag(("ManifestRecord", tag))),
+        };
+
+        Ok(Decoded::Record(record, record_len))
+    }
+}
+
+/// Returns `true` if a valid record starts anywhere after the given position.
+///
+/// A torn append can only ever be followed by garbage, so a valid record after
+/// a broken one means the broken record is corrupt, and not the journal's torn tail.
+fn has_record_after(bytes: &[u8], pos: usize) -> bool {
+    (pos + 1..bytes.len()).any(|start| {
+        matches!(
+            Record::decode_from(bytes.get(start..).unwrap_or_default()),
+            Ok(Decoded::Record(..))
+        )
+    })
+}
+
+/// Returns `true` if the manifest file is a journal (and not a flat list of IDs).
+pub fn is_journal(bytes: &[u8]) -> bool {
+    bytes.starts_with(JOURNAL_MAGIC)
+}
+
+/// Result of replaying a journal
+pub struct Replay {
+    /// Segment IDs after applying all records
+    pub ids: Vec<SegmentId>,
+
+    /// Amount of edits since the last snapshot
+    pub edit_count: usize,
+
+    /// `true` if the journal ended with an incomplete record
+    pub torn: bool,
+}
+
+/// Replays all records of a journal.
+///
+/// An incomplete or corrupt record at the end of the journal is ignored,
+/// because it is the result of a crash while appending to the journal.
+///
+/// # Errors
+///
+/// Returns error if a corrupt record is followed by more data, or by another
+/// valid record, as ignoring it would silently drop segments from the manifest.
+pub fn replay(bytes: &[u8]) -> Result<Replay, DecodeError> {
+    if !is_journal(bytes) {
+        return Err(DecodeError::InvalidHeader("ManifestJournal"));
+    }
+
+    let mut ids: Vec<SegmentId> = vec![];
+    let mut edit_count = 0;
+    let mut torn = false;
+
+    let mut pos = JOURNAL_MAGIC.len();
+
+    while pos < bytes.len() {
+        let rest = bytes.get(pos..).unwrap_or_default();
+
+        let (record, len) = match Record::decode_from(rest)? {
+            Decoded::Record(record, len) => (record, len),
+            Decoded::ChecksumMismatch(len) if len < rest.len() => {
+                log::error!("Corrupt vLog manifest journal record at offset {pos}");
+                return Err(DecodeError::InvalidTrailer);
+            }
+            Decoded::Incomplete | Decoded::ChecksumMismatch(_) if has_record_after(bytes, pos) => {
+                // NOTE: A corrupt length can make a record look like it reaches past the end
+                log::error!("Corrupt vLog manifest journal record at offset {pos}");
+                return Err(DecodeError::InvalidTrailer);
+            }
+            Decoded::Incomplete | Decoded::ChecksumMismatch(_) => {
+                log::warn!(
+                    "Ignoring incomplete vLog manifest journal record at offset {pos} ({} bytes)",
+                    rest.len(),
+                );
+                torn = true;
+                break;
+            }
+        };
+
+        match record {
+            Record::Snapshot(snapshot) => {
+                ids = snapshot;
+                edit_count = 0;
+            }
+            Record::Edit { added, removed } => {
+                ids.retain(|x| !removed.contains(x));
+                ids.extend(added);
+                edit_count += 1;
+            }
+        }
+
+        pos += len;
+    }
+
+    Ok(Replay {
+        ids,
+        edit_count,
+        torn,
+    })
+}
+
+/// Append-only journal of segment list changes
+///
+/// Each change appends a small checksummed edit record instead of rewriting
+/// the whole segment list. Once too many edits have accumulated, the journal
+/// is compacted into a single snapshot.
+pub struct Journal {
+    path: PathBuf,
+    file: File,
+    edit_count: usize,
+    max_edits: usize,
+
+    /// `true` if appending failed, which may have left a partial record behind
+    needs_compaction: bool,
+}
+
+impl Journal {
+    fn open_for_append(path: &Path) -> std::io::Result<File> {
+        std::fs::OpenOptions::new().append(true).open(path)
+    }
+
+    /// Creates a new journal, containing a snapshot of the given segment IDs.
+    ///
+    /// If a manifest already exists at the path, it is atomically replaced.
+    pub fn create(path: &Path, ids: &[SegmentId], max_edits: usize) -> crate::Result<Self> {
+        log::trace!(
+            "Writing vLog manifest journal snapshot to {}",
+            path.display()
+        );
+
+        let mut bytes = JOURNAL_MAGIC.to_vec();
+        bytes.extend(Record::Snapshot(ids.to_vec()).encode_into_vec()?);
+        rewrite_atomic(path, &bytes)?;
+
+        Ok(Self {
+            path: path.into(),
+            file: Self::open_for_append(path)?,
+            edit_count: 0,
+            max_edits,
+            needs_compaction: false,
+        })
+    }
+
+    /// Opens an existing journal for appending.
+    ///
+    /// If the journal ended with an incomplete record, or has accumulated too many edits,
+    /// it is compacted, so new records are never appended after garbage.
+    pub fn recover(path: &Path, replay: &Replay, max_edits: usize) -> crate::Result<Self> {
+        if replay.torn || replay.edit_count >= max_edits {
+            return Self::create(path, &replay.ids, max_edits);
+        }
+
+        Ok(Self {
+            path: path.into(),
+            file: Self::open_for_append(path)?,
+            edit_count: replay.edit_count,
+            max_edits,
+            needs_compaction: false,
+        })
+    }
+
+    /// Appends an edit to the journal.
+    ///
+    /// `ids` needs to be the full list of segment IDs after applying the edit,
+    /// which is used to compact the journal if needed.
+    ///
+    /// If appending fails, the journal is compacted on the next append,
+    /// so a partially written record never ends up in the middle of the journal.
+    pub fn append(
+        &mut self,
+        added: Vec<SegmentId>,
+        removed: Vec<SegmentId>,
+        ids: &[SegmentId],
+    ) -> crate::Result<()> {
+        if self.needs_compaction || self.edit_count + 1 >= self.max_edits {
+            log::debug!(
+                "Compacting vLog manifest journal at {}",
+                self.path.display()
+            );
+            *self = Self::create(&self.path, ids, self.max_edits)?;
+            return Ok(());
+        }
+
+        let bytes = Record::Edit { added, removed }.encode_into_vec()?;
+
+        if let Err(e) = self
+            .file
+            .write_all(&bytes)
+            .and_then(|()| self.file.sync_data())
+        {
+            self.needs_compaction = true;
+            return Err(e.into());
+        }
+
+        self.edit_count += 1;
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use test_log::test;
+
+    #[test]
+    fn manifest_journal_record_round_trip() -> crate::Result<()> {
+        let records = [
+            Record::Snapshot(vec![]),
+            Record::Snapshot(vec![1, 2, 3]),
+            Record::Edit {
+                added: vec![4],
+                removed: vec![1, 2],
+            },
+        ];
+
+        for record in records {
+            let bytes = record.encode_into_vec()?;
+            let len = bytes.len();
+            assert_eq!(Decoded::Record(record, len), Record::decode_from(&bytes)?);
+        }
+
+        Ok(())
+    }
+
+    #[test]
+    fn manifest_journal_replay() -> crate::Result<()> {
+        let dir = tempfile::tempdir()?;
+        let path = dir.path().join("manifest");
+
+        let mut journal = Journal::create(&path, &[1, 2], DEFAULT_MAX_EDITS)?;
+        journal.append(vec![3], vec![], &[1, 2, 3])?;
+        journal.append(vec![4], vec![1, 3], &[2, 4])?;
+
+        let result = replay(&std::fs::read(&path)?)?;
+        assert_eq!(vec![2, 4], result.ids);
+        assert_eq!(2, result.edit_count);
+        assert!(!result.torn);
+
+        Ok(())
+    }
+
+    #[test]
+    fn manifest_journal_torn_tail() -> crate::Result<()> {
+        let dir = tempfile::tempdir()?;
+        let path = dir.path().join("manifest");
+
+        let mut journal = Journal::create(&path, &[1, 2], DEFAULT_MAX_EDITS)?;
+        journal.append(vec![3], vec![], &[1, 2, 3])?;
+        journal.append(vec![4], vec![], &[1, 2, 3, 4])?;
+        drop(journal);
+
+        // Cut the last record in half
+        let bytes = std::fs::read(&path)?;
+        std::fs::write(&path, bytes.get(..bytes.len() - 10).expect("should exist"))?;
+
+        let result = replay(&std::fs::read(&path)?)?;
+        assert_eq!(vec![1, 2, 3], result.ids);
+        assert!(result.torn);
+
+        // Corrupt the last record
+        let mut bytes = std::fs::read(&path)?;
+        let len = bytes.len();
+        *bytes.get_mut(len - 1).expect("should exist") ^= 0xFF;
+        std::fs::write(&path, bytes)?;
+
+        let result = replay(&std::fs::read(&path)?)?;
+        assert_eq!(vec![1, 2, 3], result.ids);
+        assert!(result.torn);
+
+        // NOTE: Recovering compacts the journal, so new edits are not appended after garbage
+        let mut journal = Journal::recover(&path, &result, DEFAULT_MAX_EDITS)?;
+        journal.append(vec![5], vec![], &[1, 2, 3, 5])?;
+
+        let result = replay(&std::fs::read(&path)?)?;
+        assert_eq!(vec![1, 2, 3, 5], result.ids);
+        assert_eq!(1, result.edit_count);
+        assert!(!result.torn);
+
+        Ok(())
+    }
+
+    #[test]
+    fn manifest_journal_corrupt_record_in_middle() -> crate::Result<()> {
+        let dir = tempfile::tempdir()?;
+        let path = dir.path().join("manifest");
+
+        let mut journal = Journal::create(&path, &[1, 2], DEFAULT_MAX_EDITS)?;
+        let edit_start = std::fs::metadata(&path)?.len() as usize;
+        journal.append(vec![3], vec![], &[1, 2, 3])?;
+        journal.append(vec![4], vec![], &[1, 2, 3, 4])?;
+        drop(journal);
+
+        // Corrupt the payload of the first edit, which is followed by another edit
+        let mut bytes = std::fs::read(&path)?;
+        *bytes.get_mut(edit_start + 5).expect("should exist") ^= 0xFF;
+
+        assert!(matches!(replay(&bytes), Err(DecodeError::InvalidTrailer)));
+
+        Ok(())
+    }
+
+    #[test]
+    fn manifest_journal_corrupt_length_in_middle() -> crate::Result<()> {
+        let dir = tempfile::tempdir()?;
+        let path = dir.path().join("manifest");
+
+        let mut journal = Journal::create(&path, &[1, 2], DEFAULT_MAX_EDITS)?;
+        let edit_start = std::fs::metadata(&path)?.len() as usize;
+        journal.append(vec![3], vec![], &[1, 2, 3])?;
+        journal.append(vec![4], vec![], &[1, 2, 3, 4])?;
+        drop(journal);
+
+        let bytes = std::fs::read(&path)?;
+
+        // Make the first edit claim to reach past the end of the journal
+        let mut corrupt = bytes.clone();
+        *corrupt.get_mut(edit_start + 1).expect("should exist") = 0xFF;
+        assert!(matches!(replay(&corrupt), Err(DecodeError::InvalidTrailer)));
+
+        // Make the first edit end exactly at the end of the journal
+        let mut corrupt = bytes.clone();
+        let len = (bytes.len() - edit_start - RECORD_OVERHEAD) as u32;
+        corrupt
+            .get_mut(edit_start + 1..edit_start + 5)
+            .expect("should exist")
+            .copy_from_slice(&len.to_be_bytes());
+        assert!(matches!(replay(&corrupt), Err(DecodeError::InvalidTrailer)));
+
+        // Shrinking the length breaks the checksum
+        let mut corrupt = bytes;
+        *corrupt.get_mut(edit_start + 4).expect("should exist") -= 8;
+        assert!(matches!(replay(&corrupt), Err(DecodeError::InvalidTrailer)));
+
+        Ok(())
+    }
+
+    #[test]
+    fn manifest_journal_compaction() -> crate::Result<()> {
+        let dir = tempfile::tempdir()?;
+        let path = dir.path().join("manifest");
+
+        let mut journal = Journal::create(&path, &[], 5)?;
+        let mut ids = vec![];
+
+        for id in 0..20 {
+            ids.push(id);
+            journal.append(vec![id], vec![], &ids)?;
+
+            let result = replay(&std::fs::read(&path)?)?;
+            assert_eq!(ids, result.ids);
+            assert!(result.edit_count < 5);
+        }
+
+        Ok(())
+    }
+}
@@ -2,26 +2,35 @@
 // This source code is licensed under both the Apache 2.0 and MIT License
 // (found in the LICENSE-* files in the repository)
 
+mod journal;
+
 use crate::{
+    coding::{Decode, Encode},
     id::SegmentId,
     key_range::KeyRange,
     segment::{gc_stats::GcStats, meta::Metadata, trailer::SegmentFileTrailer},
+    version::Version,
     Compressor, HashMap, Segment, SegmentWriter as MultiWriter,
 };
 use byteorder::{BigEndian, ReadBytesExt, WriteBytesExt};
+use journal::{Journal, DEFAULT_MAX_EDITS};
 use std::{
     io::{Cursor, Write},
     marker::PhantomData,
     path::{Path, PathBuf},
-    sync::{Arc, RwLock},
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        Arc, Mutex, RwLock,
+    },
 };
 
 pub const VLOG_MARKER: &str = ".vlog";
 pub const SEGMENTS_FOLDER: &str = "segments";
-const MANIFEST_FILE: &str = "vlog_manifest";
+pub const MANIFEST_FILE: &str = "vlog_manifest";
+const GC_STATS_FILE: &str = "vlog_gc_stats";
 
 /// Atomically rewrites a file
-fn rewrite_atomic<P: AsRef<Path>>(path: P, content: &[u8]) -> std::io::Result<()> {
+pub fn rewrite_atomic<P: AsRef<Path>>(path: P, content: &[u8]) -> std::io::Result<()> {
     let path = path.as_ref();
     let folder = path.parent().expect("should have a parent");
 
@@ -40,10 +49,77 @@ fn rewrite_atomic<P: AsRef<Path>>(path: P, content: &[u8]) -> std::io::Result<()
     Ok(())
 }
 
+/// Map of segments, keyed by their ID
+pub type SegmentMap<C> = HashMap<SegmentId, Arc<Segment<C>>>;
+
+/// Writes the `.vlog` marker, which contains the version, and syncs the folders
+///
+/// Needs to be written last, as it marks the value log as fully initialized.
+pub fn write_marker(folder: &Path, version: Version) -> crate::Result<()> {
+    let mut file = std::fs::File::create(folder.join(VLOG_MARKER))?;
+    version.write_file_header(&mut file)?;
+    file.sync_all()?;
+
+    #[cfg(not(target_os = "windows"))]
+    {
+        // fsync folders on Unix
+
+        let segments_folder = std::fs::File::open(folder.join(SEGMENTS_FOLDER))?;
+        segments_folder.sync_all()?;
+
+        let folder = std::fs::File::open(folder)?;
+        folder.sync_all()?;
+    }
+
+    Ok(())
+}
+
+/// Writes a manifest containing the given segment IDs, replacing an existing one
+///
+/// If `use_journal` is set, the manifest is written as a journal, otherwise as a flat list of IDs.
+pub fn write_manifest(
+    path: &Path,
+    segment_ids: &[SegmentId],
+    use_journal: bool,
+) -> crate::Result<()> {
+    if use_journal {
+        Journal::create(path, segment_ids, DEFAULT_MAX_EDITS)?;
+        return Ok(());
+    }
+
+    log::trace!("Writing segment manifest to {}", path.display());
+
+    let mut bytes = Vec::new();
+
+    let cnt = segment_ids.len() as u64;
+    bytes.write_u64::<BigEndian>(cnt)?;
+
+    for id in segment_ids {
+        bytes.write_u64::<BigEndian>(*id)?;
+    }
+
+    rewrite_atomic(path, &bytes)?;
+
+    Ok(())
+}
+
 #[allow(clippy::module_name_repetitions)]
 pub struct SegmentManifestInner<C: Compressor + Clone> {
     path: PathBuf,
-    pub segments: RwLock<HashMap<SegmentId, Arc<Segment<C>>>>,
+    gc_stats_path: PathBuf,
+
+    /// Current list of segments
+    ///
+    /// The map is never modified in place, but swapped out as a whole,
+    /// so snapshots can hold on to a previous version.
+    pub segments: RwLock<Arc<SegmentMap<C>>>,
+
+    /// Manifest journal, `None` if the manifest uses the flat format
+    /// (or is read-only)
+    journal: Option<Mutex<Journal>>,
+
+    /// `true` if the GC stats have changed since they were last persisted
+    gc_stats_dirty: AtomicBool,
 }
 
 #[allow(clippy::module_name_repetitions)]
@@ -89,13 +165,32 @@ impl<C: Compressor + Clone> SegmentManifest<C> {
         Ok(())
     }
 
+    /// Parses segment IDs from manifest file, which is either a journal or a flat list of IDs
+    pub(crate) fn load_ids_from_disk<P: AsRef<Path>>(path: P) -> crate::Result<Vec<SegmentId>> {
+        Self::load_from_disk(path).map(|(ids, _)| ids)
+    }
+
     /// Parses segment IDs from manifest file
-    fn load_ids_from_disk<P: AsRef<Path>>(path: P) -> crate::Result<Vec<SegmentId>> {
+    ///
+    /// If the manifest is a journal, the replay result is returned as well.
+    fn load_from_disk<P: AsRef<Path>>(
+        path: P,
+    ) -> crate::Result<(Vec<SegmentId>, Option<journal::Replay>)> {
         let path = path.as_ref();
         log::debug!("Loading manifest from {}", path.display());
 
         let bytes = std::fs::read(path)?;
 
+        if journal::is_journal(&bytes) {
+            let replay = journal::replay(&bytes)?;
+            return Ok((replay.ids.clone(), Some(replay)));
+        }
+
+        Self::decode_flat(bytes).map(|ids| (ids, None))
+    }
+
+    /// Parses segment IDs from a flat manifest file
+    fn decode_flat(bytes: Vec<u8>) -> crate::Result<Vec<SegmentId>> {
         let mut ids = vec![];
 
         let mut cursor = Cursor::new(bytes);
@@ -109,16 +204,73 @@ impl<C: Compressor + Clone> SegmentManifest<C> {
         Ok(ids)
     }
 
+    /// Parses the persisted GC stats of each segment
+    fn decode_gc_stats(bytes: Vec<u8>) -> crate::Result<HashMap<SegmentId, GcStats>> {
+        let mut map = HashMap::default();
+
+        let mut cursor = Cursor::new(bytes);
+
+        let cnt = cursor.read_u64::<BigEndian>()?;
+
+        for _ in 0..cnt {
+            let id = cursor.read_u64::<BigEndian>()?;
+            let stats = GcStats::decode_from(&mut cursor)?;
+            map.insert(id, stats);
+        }
+
+        Ok(map)
+    }
+
+    /// Loads the persisted GC stats of each segment
+    ///
+    /// GC stats are only a hint for GC strategies, so if the file is missing
+    /// or cannot be parsed, all segments start with empty stats until the next
+    /// scan rebuilds them.
+    fn load_gc_stats<P: AsRef<Path>>(path: P) -> crate::Result<HashMap<SegmentId, GcStats>> {
+        let path = path.as_ref();
+
+        if !path.try_exists()? {
+            log::debug!("No GC stats found at {}", path.display());
+            return Ok(HashMap::default());
+        }
+
+        log::debug!("Loading GC stats from {}", path.display());
+
+        let bytes = std::fs::read(path)?;
+
+        match Self::decode_gc_stats(bytes) {
+            Ok(map) => Ok(map),
+            Err(e) => {
+                log::warn!(
+                    "Could not parse GC stats at {}, resetting them: {e:?}",
+                    path.display(),
+                );
+                Ok(HashMap::default())
+            }
+        }
+    }
+
     /// Recovers a value log from disk
-    pub(crate) fn recover<P: AsRef<Path>>(folder: P) -> crate::Result<Self> {
+    ///
+    /// In read-only mode, unfinished segments are left on disk.
+    ///
+    /// If `use_journal` is set, a flat manifest is migrated to a journal.
+    pub(crate) fn recover<P: AsRef<Path>>(
+        folder: P,
+        read_only: bool,
+        use_journal: bool,
+    ) -> crate::Result<Self> {
         let folder = folder.as_ref();
         let manifest_path = folder.join(MANIFEST_FILE);
 
         log::info!("Recovering vLog at {folder:?}");
 
-        let ids = Self::load_ids_from_disk(&manifest_path)?;
+        let (ids, replay) = Self::load_from_disk(&manifest_path)?;
         let cnt = ids.len();
 
+        let gc_stats_path = folder.join(GC_STATS_FILE);
+        let mut gc_stats = Self::load_gc_stats(&gc_stats_path)?;
+
         let progress_mod = match cnt {
             _ if cnt <= 20 => 1,
             _ if cnt <= 100 => 10,
@@ -128,7 +280,9 @@ impl<C: Compressor + Clone> SegmentManifest<C> {
         log::debug!("Recovering {cnt} vLog segments from {folder:?}");
 
         let segments_folder = folder.join(SEGMENTS_FOLDER);
-        Self::remove_unfinished_segments(&segments_folder, &ids)?;
+        if !read_only {
+            Self::remove_unfinished_segments(&segments_folder, &ids)?;
+        }
 
         let segments = {
             let mut map =
@@ -146,7 +300,10 @@ impl<C: Compressor + Clone> SegmentManifest<C> {
                         id,
                         path,
                         meta: trailer.metadata,
-                        gc_stats: GcStats::default(),
+                        gc_stats: gc_stats.remove(&id).unwrap_or_default(),
+                        unlink_on_drop: AtomicBool::new(false),
+                        #[cfg(feature = "mmap")]
+                        mmap: std::sync::OnceLock::new(),
                         _phantom: PhantomData,
                     }),
                 );
@@ -163,43 +320,84 @@ impl<C: Compressor + Clone> SegmentManifest<C> {
             return Err(crate::Error::Unrecoverable);
         }
 
+        let journal = match (replay, read_only, use_journal) {
+            (_, true, _) | (None, false, false) => None,
+            (Some(replay), false, _) => Some(Journal::recover(
+                &manifest_path,
+                &replay,
+                DEFAULT_MAX_EDITS,
+            )?),
+            (None, false, true) => {
+                log::debug!("Migrating vLog manifest to journal");
+                Some(Journal::create(&manifest_path, &ids, DEFAULT_MAX_EDITS)?)
+            }
+        };
+
         Ok(Self(Arc::new(SegmentManifestInner {
             path: manifest_path,
-            segments: RwLock::new(segments),
+            gc_stats_path,
+            segments: RwLock::new(Arc::new(segments)),
+            journal: journal.map(Mutex::new),
+            gc_stats_dirty: AtomicBool::new(false),
         })))
     }
 
-    pub(crate) fn create_new<P: AsRef<Path>>(folder: P) -> crate::Result<Self> {
-        let path = folder.as_ref().join(MANIFEST_FILE);
+    pub(crate) fn create_new<P: AsRef<Path>>(folder: P, use_journal: bool) -> crate::Result<Self> {
+        let folder = folder.as_ref();
+        let path = folder.join(MANIFEST_FILE);
 
-        let m = Self(Arc::new(SegmentManifestInner {
-            path,
-            segments: RwLock::new(HashMap::default()),
-        }));
-        Self::write_to_disk(&m.path, &[])?;
+        let journal = if use_journal {
+            Some(Journal::create(&path, &[], DEFAULT_MAX_EDITS)?)
+        } else {
+            Self::write_to_disk(&path, &[])?;
+            None
+        };
 
-        Ok(m)
+        Ok(Self(Arc::new(SegmentManifestInner {
+            path,
+            gc_stats_path: folder.join(GC_STATS_FILE),
+            segments: RwLock::new(Arc::default()),
+            journal: journal.map(Mutex::new),
+            gc_stats_dirty: AtomicBool::new(false),
+        })))
     }
 
     /// Modifies the level manifest atomically.
-    pub(crate) fn atomic_swap<F: FnOnce(&mut HashMap<SegmentId, Arc<Segment<C>>>)>(
-        &self,
-        f: F,
-    ) -> crate::Result<()> {
+    pub(crate) fn atomic_swap<F: FnOnce(&mut SegmentMap<C>)>(&self, f: F) -> crate::Result<()> {
         let mut prev_segments = self.segments.write().expect("lock is poisoned");
 
         // NOTE: Create a copy of the levels we can operate on
         // without mutating the current level manifest
         // If persisting to disk fails, this way the level manifest
         // is unchanged
-        let mut working_copy = prev_segments.clone();
+        let mut working_copy = (**prev_segments).clone();
 
         f(&mut working_copy);
 
         let ids = working_copy.keys().copied().collect::<Vec<_>>();
 
-        Self::write_to_disk(&self.path, &ids)?;
-        *prev_segments = working_copy;
+        if let Some(journal) = &self.journal {
+            let added = ids
+                .iter()
+                .filter(|id| !prev_segments.contains_key(id))
+                .copied()
+                .collect();
+
+            let removed = prev_segments
+                .keys()
+                .filter(|id| !working_copy.contains_key(id))
+                .copied()
+                .collect();
+
+            journal
+                .lock()
+                .expect("lock is poisoned")
+                .append(added, removed, &ids)?;
+        } else {
+            Self::write_to_disk(&self.path, &ids)?;
+        }
+
+        *prev_segments = Arc::new(working_copy);
 
         // NOTE: Lock needs to live until end of function because
         // writing to disk needs to be exclusive
@@ -278,6 +476,9 @@ impl<C: Compressor + Clone> SegmentManifest<C> {
                             )),
                         },
                         gc_stats: GcStats::default(),
+                        unlink_on_drop: AtomicBool::new(false),
+                        #[cfg(feature = "mmap")]
+                        mmap: std::sync::OnceLock::new(),
                         _phantom: PhantomData,
                     }),
                 );
@@ -296,17 +497,48 @@ impl<C: Compressor + Clone> SegmentManifest<C> {
         Ok(())
     }
 
-    fn write_to_disk<P: AsRef<Path>>(path: P, segment_ids: &[SegmentId]) -> crate::Result<()> {
-        let path = path.as_ref();
-        log::trace!("Writing segment manifest to {}", path.display());
+    pub(crate) fn write_to_disk<P: AsRef<Path>>(
+        path: P,
+        segment_ids: &[SegmentId],
+    ) -> crate::Result<()> {
+        write_manifest(path.as_ref(), segment_ids, false)
+    }
+
+    /// Persists the GC stats of all segments, so they survive a restart.
+    ///
+    /// Needs to be called while holding the rollover lock, so stats writes do not race.
+    pub(crate) fn persist_gc_stats(&self) -> crate::Result<()> {
+        log::trace!("Writing GC stats to {}", self.gc_stats_path.display());
+
+        // NOTE: Stats that change while writing mark the stats as dirty again
+        self.gc_stats_dirty.store(false, Ordering::Release);
+
+        let segments = self.snapshot();
+
+        let result = Self::write_gc_stats(&self.gc_stats_path, &segments);
+
+        if result.is_err() {
+            self.gc_stats_dirty.store(true, Ordering::Release);
+        }
+
+        result
+    }
+
+    /// Returns `true` if the GC stats have changed since they were last persisted.
+    pub(crate) fn gc_stats_dirty(&self) -> bool {
+        self.gc_stats_dirty.load(Ordering::Acquire)
+    }
 
+    /// Writes the GC stats of the given segments to a file.
+    fn write_gc_stats(path: &Path, segments: &SegmentMap<C>) -> crate::Result<()> {
         let mut bytes = Vec::new();
 
-        let cnt = segment_ids.len() as u64;
+        let cnt = segments.len() as u64;
         bytes.write_u64::<BigEndian>(cnt)?;
 
-        for id in segment_ids {
+        for (id, segment) in segments {
             bytes.write_u64::<BigEndian>(*id)?;
+            segment.gc_stats.encode_into(&mut bytes)?;
         }
 
         rewrite_atomic(path, &bytes)?;
@@ -314,6 +546,29 @@ impl<C: Compressor + Clone> SegmentManifest<C> {
         Ok(())
     }
 
+    /// Writes the manifest and GC stats of the given segments into another value log folder.
+    ///
+    /// Used to create checkpoints, the segment files need to be put into place by the caller.
+    pub(crate) fn write_checkpoint(
+        folder: &Path,
+        segments: &SegmentMap<C>,
+        use_journal: bool,
+    ) -> crate::Result<()> {
+        let ids = segments.keys().copied().collect::<Vec<_>>();
+        write_manifest(&folder.join(MANIFEST_FILE), &ids, use_journal)?;
+
+        Self::write_gc_stats(&folder.join(GC_STATS_FILE), segments)
+    }
+
+    /// Returns the current list of segments.
+    ///
+    /// The returned map keeps all its segments alive, even if they are
+    /// dropped from the manifest afterwards.
+    #[must_use]
+    pub fn snapshot(&self) -> Arc<SegmentMap<C>> {
+        self.segments.read().expect("lock is poisoned").clone()
+    }
+
     /// Gets a segment
     #[must_use]
     pub fn get_segment(&self, id: SegmentId) -> Option<Arc<Segment<C>>> {
@@ -0,0 +1,62 @@
+// Copyright (c) 2024-present, fjall-rs
+// This source code is licensed under both the Apache 2.0 and MIT License
+// (found in the LICENSE-* files in the repository)
+
+/// Default size of the read buffer used to read blobs
+///
+/// Matches the default capacity of [`std::io::BufReader`].
+pub const DEFAULT_READAHEAD: usize = 8 * 1_024;
+
+/// Determines which blobs following a requested blob are read into the blob cache
+///
+/// Prefetching is useful for range scans, where the following blobs
+/// are likely to be requested next.
+///
+/// Prefetching stops at the first large value (see [`crate::Config::large_value_chunk_size`]),
+/// because that would read all of its chunks.
+#[derive(Copy, Clone, Debug, Default, Eq, PartialEq)]
+pub enum Prefetch {
+    /// Only the requested blob is read
+    #[default]
+    None,
+
+    /// Up to the given amount of following blobs are read
+    Count(usize),
+
+    /// Following blobs are read until the given amount of bytes (as stored on disk)
+    /// has been prefetched
+    ///
+    /// The blob crossing the limit is still cached, because it has already been read.
+    Bytes(u64),
+
+    /// Only following blobs that are already contained in the read buffer are cached,
+    /// so prefetching never causes additional I/O
+    ///
+    /// The size of the read buffer can be increased using a readahead hint.
+    Buffered,
+}
+
+impl From<usize> for Prefetch {
+    fn from(value: usize) -> Self {
+        if value == 0 {
+            Self::None
+        } else {
+            Self::Count(value)
+        }
+    }
+}
+
+impl Prefetch {
+    /// Returns `true` if another blob may be prefetched, after `count` blobs
+    /// totalling `bytes` bytes on disk have been prefetched.
+    ///
+    /// For [`Prefetch::Buffered`], the caller needs to check the read buffer.
+    pub(crate) fn allows(self, count: usize, bytes: u64) -> bool {
+        match self {
+            Self::None => false,
+            Self::Count(max) => count < max,
+            Self::Bytes(max) => bytes < max,
+            Self::Buffered => true,
+        }
+    }
+}
@@ -0,0 +1,426 @@
+// Copyright (c) 2024-present, fjall-rs
+// This source code is licensed under both the Apache 2.0 and MIT License
+// (found in the LICENSE-* files in the repository)
+
+use crate::{
+    id::{IdGenerator, SegmentId},
+    index::{Reader as IndexReader, Writer as IndexWriter},
+    integrity::{verify_segment_file, walk_segment_file, Corruption, IntegrityReport, WalkedBlob},
+    lock::DirectoryLock,
+    manifest::{SegmentManifest, MANIFEST_FILE, SEGMENTS_FOLDER, VLOG_MARKER},
+    segment::{
+        chunked::ChunkManifest,
+        header::{BlobKind, CompressionType},
+    },
+    version::Version,
+    BlobCache, Compressor, Config, FDCache, SegmentWriter, UserKey, ValueHandle,
+};
+use std::{
+    collections::HashSet,
+    path::{Path, PathBuf},
+};
+
+/// Report of a value log repair
+#[derive(Debug)]
+#[cfg_attr(feature = "serde", derive(serde::Deserialize, serde::Serialize))]
+#[allow(clippy::module_name_repetitions)]
+pub struct RepairReport {
+    /// Path of value log
+    pub path: PathBuf,
+
+    /// Amount of segments that were checked
+    pub segment_count: usize,
+
+    /// Segments that were damaged, and have been replaced
+    pub damaged_segments: Vec<SegmentId>,
+
+    /// Segments that were referenced in the manifest, but do not exist on disk
+    pub missing_segments: Vec<SegmentId>,
+
+    /// New segments the salvaged blobs were written into
+    pub new_segments: Vec<SegmentId>,
+
+    /// Amount of live blobs that were salvaged from damaged segments
+    pub salvaged_blobs: u64,
+
+    /// Keys whose live blob could not be salvaged
+    ///
+    /// Keys of blobs in truncated parts of a segment (or in missing segments)
+    /// cannot be recovered, so they are not listed here; their index entries
+    /// point into the segments listed in `damaged_segments` and `missing_segments`.
+    pub lost_keys: Vec<UserKey>,
+
+    /// All corruptions that were found
+    pub corruptions: Vec<Corruption>,
+}
+
+impl std::fmt::Display for RepairReport {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        writeln!(
+            f,
+            "--- Repair report for vLog @ {} ---",
+            self.path.display()
+        )?;
+        writeln!(f, "# segments     : {}", self.segment_count)?;
+        writeln!(f, "Damaged        : {:?}", self.damaged_segments)?;
+        writeln!(f, "Missing        : {:?}", self.missing_segments)?;
+        writeln!(f, "New segments   : {:?}", self.new_segments)?;
+        writeln!(f, "Salvaged blobs : {}", self.salvaged_blobs)?;
+        writeln!(f, "Lost keys      : {}", self.lost_keys.len())?;
+        writeln!(f, "# corruptions  : {}", self.corruptions.len())?;
+        writeln!(f, "--- Repair report done ---")?;
+        Ok(())
+    }
+}
+
+impl RepairReport {
+    /// Returns `true` if nothing needed to be repaired.
+    #[must_use]
+    pub fn is_clean(&self) -> bool {
+        self.damaged_segments.is_empty() && self.missing_segments.is_empty()
+    }
+}
+
+/// Lists the segment IDs of all segment files in the segments folder
+fn list_segment_files(folder: &Path) -> crate::Result<Vec<SegmentId>> {
+    let mut ids = vec![];
+
+    for dirent in std::fs::read_dir(folder)? {
+        let dirent = dirent?;
+
+        if !dirent.file_type()?.is_file() {
+            continue;
+        }
+
+        match dirent.file_name().to_str().map(str::parse::<SegmentId>) {
+            Some(Ok(id)) => ids.push(id),
+            _ => log::debug!("Skipping unknown file {}", dirent.path().display()),
+        }
+    }
+
+    ids.sort_unstable();
+
+    Ok(ids)
+}
+
+/// Verifies all segments, adding damaged and missing segments to the report.
+///
+/// Returns the IDs of all healthy segments.
+fn check_segments<C: Compressor + Clone>(
+    segments_folder: &Path,
+    ids: &[SegmentId],
+    compressor: Option<&C>,
+    report: &mut RepairReport,
+) -> crate::Result<Vec<SegmentId>> {
+    let mut healthy_ids = vec![];
+
+    for &id in ids {
+        let segment_path = segments_folder.join(id.to_string());
+
+        if !segment_path.try_exists()? {
+            log::error!("vLog segment {id} is missing");
+            report.missing_segments.push(id);
+            continue;
+        }
+
+        let mut integrity = IntegrityReport::default();
+        verify_segment_file(id, &segment_path, compressor, &mut integrity, |_| {});
+
+        if integrity.is_ok() {
+            healthy_ids.push(id);
+        } else {
+            report.damaged_segments.push(id);
+            report.corruptions.extend(integrity.corruptions);
+        }
+    }
+
+    Ok(healthy_ids)
+}
+
+/// Returns the offsets of all chunk manifests of a segment file whose chunks are all intact.
+fn intact_chunk_manifests<C: Compressor + Clone>(
+    segment_id: SegmentId,
+    path: &Path,
+    compressor: Option<&C>,
+) -> HashSet<u64> {
+    let mut intact = HashSet::new();
+
+    // Offset and remaining chunk count of the current chunk manifest
+    let mut chunks: Option<(u64, u64)> = None;
+
+    // NOTE: Corruptions were already reported when checking the segment
+    walk_segment_file(segment_id, path, compressor, &mut vec![], |blob| {
+        chunks = match blob.header.kind {
+            BlobKind::Value => None,
+            BlobKind::ChunkManifest => ChunkManifest::from_value(blob.value)
+                .ok()
+                .filter(|_| blob.intact)
+                .map(|manifest| (blob.offset, manifest.chunk_count)),
+            BlobKind::Chunk => chunks
+                .filter(|_| blob.intact)
+                .and_then(|(offset, remaining)| {
+                    remaining
+                        .checked_sub(1)
+                        .map(|remaining| (offset, remaining))
+                }),
+        };
+
+        if let Some((offset, 0)) = chunks {
+            intact.insert(offset);
+        }
+    });
+
+    intact
+}
+
+/// Copies a single blob of a damaged segment into the new segments,
+/// if it is still referenced by the index.
+///
+/// Returns `true` if the blob was salvaged.
+#[allow(clippy::too_many_arguments)]
+fn salvage_blob<C: Compressor + Clone, R: IndexReader, W: IndexWriter>(
+    blob: &WalkedBlob<'_>,
+    vhandle: &ValueHandle,
+    intact: bool,
+    default_compression: CompressionType,
+    index_reader: &R,
+    index_writer: &mut W,
+    writer: &mut SegmentWriter<C>,
+    report: &mut RepairReport,
+) -> crate::Result<bool> {
+    // IMPORTANT: Only salvage live blobs
+    if index_reader.get(blob.key)?.as_ref() != Some(vhandle) {
+        return Ok(false);
+    }
+
+    if !intact {
+        report.lost_keys.push(blob.key.clone());
+        return Ok(false);
+    }
+
+    // NOTE: Truncation is OK because we know values are u32 max
+    #[allow(clippy::cast_possible_truncation)]
+    let uncompressed_len = blob.uncompressed_len as u32;
+
+    let mut header = blob.header.clone();
+    header.uncompressed_len = Some(uncompressed_len);
+    header.compression.get_or_insert(default_compression);
+
+    let size = if header.kind == BlobKind::ChunkManifest {
+        ChunkManifest::from_value(blob.value)?.index_size()
+    } else {
+        uncompressed_len
+    };
+
+    let new_vhandle = writer.get_next_value_handle();
+    index_writer.insert_indirect(blob.key, new_vhandle, size)?;
+
+    writer.write_raw(blob.key, &header, blob.value)?;
+
+    report.salvaged_blobs += 1;
+
+    Ok(true)
+}
+
+/// Salvages all live, intact blobs of a damaged segment into the new segments.
+fn salvage_segment<C: Compressor + Clone, R: IndexReader, W: IndexWriter>(
+    segment_id: SegmentId,
+    path: &Path,
+    compressor: Option<&C>,
+    index_reader: &R,
+    index_writer: &mut W,
+    writer: &mut SegmentWriter<C>,
+    report: &mut RepairReport,
+) -> crate::Result<()> {
+    let default_compression = CompressionType::of(compressor);
+
+    // NOTE: A large value can only be salvaged if its manifest and all of its chunks are intact
+    let intact_manifests = intact_chunk_manifests(segment_id, path, compressor);
+
+    let mut result = Ok(());
+    let mut copy_chunks = false;
+
+    // NOTE: Corruptions were already reported when checking the segment
+    verify_segment_file(
+        segment_id,
+        path,
+        compressor,
+        &mut IntegrityReport::default(),
+        |blob| {
+            if result.is_err() {
+                return;
+            }
+
+            // NOTE: Chunks are copied right after their salvaged manifest
+            if blob.header.kind == BlobKind::Chunk {
+                if copy_chunks {
+                    result = writer.write_raw(blob.key, blob.header, blob.value);
+                }
+                return;
+            }
+
+            let vhandle = ValueHandle {
+                segment_id,
+                offset: blob.offset,
+            };
+
+            let is_manifest = blob.header.kind == BlobKind::ChunkManifest;
+            let intact = blob.intact && (!is_manifest || intact_manifests.contains(&blob.offset));
+
+            result = salvage_blob(
+                &blob,
+                &vhandle,
+                intact,
+                default_compression,
+                index_reader,
+                index_writer,
+                writer,
+                report,
+            )
+            .map(|salvaged| copy_chunks = salvaged && is_manifest);
+        },
+    );
+
+    result
+}
+
+/// Repairs a value log that cannot be opened anymore.
+///
+/// Live blobs are salvaged from damaged segments into new segments,
+/// the index is updated to point to them, and the manifest is rewritten.
+pub fn repair<BC, FDC, C, R, W>(
+    path: &Path,
+    config: &Config<BC, FDC, C>,
+    index_reader: &R,
+    mut index_writer: W,
+) -> crate::Result<RepairReport>
+where
+    BC: BlobCache,
+    FDC: FDCache,
+    C: Compressor + Clone,
+    R: IndexReader,
+    W: IndexWriter,
+{
+    log::info!("Repairing vLog at {}", path.display());
+
+    let _lock = DirectoryLock::acquire(path)?;
+
+    let version = {
+        let bytes = std::fs::read(path.join(VLOG_MARKER))?;
+
+        match Version::parse_file_header(&bytes) {
+            Some(version @ (Version::V1 | Version::V2)) => version,
+            None => return Err(crate::Error::InvalidVersion(None)),
+        }
+    };
+
+    let segments_folder = path.join(SEGMENTS_FOLDER);
+    let manifest_path = path.join(MANIFEST_FILE);
+
+    let ids = match SegmentManifest::<C>::load_ids_from_disk(&manifest_path) {
+        Ok(ids) => ids,
+        Err(e) => {
+            // NOTE: Without a manifest, fall back to all segment files on disk
+            log::warn!("Could not load vLog manifest, using segment files on disk: {e:?}");
+            list_segment_files(&segments_folder)?
+        }
+    };
+
+    let mut report = RepairReport {
+        path: path.into(),
+        segment_count: ids.len(),
+        damaged_segments: vec![],
+        missing_segments: vec![],
+        new_segments: vec![],
+        salvaged_blobs: 0,
+        lost_keys: vec![],
+        corruptions: vec![],
+    };
+
+    let healthy_ids = check_segments(
+        &segments_folder,
+        &ids,
+        config.compression.as_ref(),
+        &mut report,
+    )?;
+
+    if report.is_clean() {
+        log::info!("vLog at {} does not need to be repaired", path.display());
+        return Ok(report);
+    }
+
+    let highest_id = ids.iter().max().copied().unwrap_or_default();
+
+    let mut writer = SegmentWriter::<C>::new(
+        IdGenerator::new(highest_id + 1),
+        config.segment_size_bytes,
+        &segments_folder,
+    )?
+    .use_version(version);
+
+    for id in report.damaged_segments.clone() {
+        salvage_segment(
+            id,
+            &segments_folder.join(id.to_string()),
+            config.compression.as_ref(),
+            index_reader,
+            &mut index_writer,
+            &mut writer,
+            &mut report,
+        )?;
+    }
+
+    let mut new_ids = healthy_ids;
+
+    for writer in writer.finish()? {
+        if writer.item_count == 0 {
+            if let Err(e) = std::fs::remove_file(&writer.path) {
+                log::warn!(
+                    "Could not delete empty vLog segment file at {}: {e:?}",
+                    writer.path.display()
+                );
+            }
+            continue;
+        }
+
+        report.new_segments.push(writer.segment_id);
+        new_ids.push(writer.segment_id);
+    }
+
+    // IMPORTANT: New segments need to be persisted before adding to index
+    // to avoid dangling pointers
+    //
+    // The damaged segments stay registered until the index points to the salvaged
+    // blobs, so a crash in between never loses blobs the index still references
+    SegmentManifest::<C>::write_to_disk(
+        &manifest_path,
+        &ids.iter()
+            .chain(&report.new_segments)
+            .copied()
+            .collect::<Vec<_>>(),
+    )?;
+
+    index_writer.finish()?;
+
+    SegmentManifest::<C>::write_to_disk(&manifest_path, &new_ids)?;
+
+    for &id in &report.damaged_segments {
+        let segment_path = segments_folder.join(id.to_string());
+
+        if let Err(e) = std::fs::remove_file(&segment_path) {
+            log::warn!(
+                "Could not delete damaged vLog segment file at {}: {e:?}",
+                segment_path.display(),
+            );
+        }
+    }
+
+    log::info!(
+        "Repaired vLog at {}: salvaged {} blobs, lost {} keys",
+        path.display(),
+        report.salvaged_blobs,
+        report.lost_keys.len(),
+    );
+
+    Ok(report)
+}
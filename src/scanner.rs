@@ -42,7 +42,23 @@ impl<'a, I: Iterator<Item = std::io::Result<(ValueHandle, u32)>>> Scanner<'a, I>
         self.size_map
     }
 
+    /// Like [`Scanner::finish`], but also hands back the lock guard,
+    /// so the scan result can be applied before anyone else takes the lock.
+    pub fn finish_locked(self) -> (SizeMap, MutexGuard<'a, ()>) {
+        (self.size_map, self.lock_guard)
+    }
+
     pub fn scan(&mut self) -> crate::Result<()> {
+        self.scan_with(|_, size| Ok(size.into()))
+    }
+
+    /// Sums up the sizes and item counts of the referenced values per segment.
+    ///
+    /// `value_len` returns the length of a value, given the size stored in the index.
+    pub fn scan_with(
+        &mut self,
+        value_len: impl Fn(&ValueHandle, u32) -> crate::Result<u64>,
+    ) -> crate::Result<()> {
         for vhandle in self.iter.by_ref() {
             let (vhandle, size) = vhandle.map_err(|_| {
                 crate::Error::Io(std::io::Error::new(
@@ -50,7 +66,7 @@ impl<'a, I: Iterator<Item = std::io::Result<(ValueHandle, u32)>>> Scanner<'a, I>
                     "Index returned error",
                 ))
             })?;
-            let size = u64::from(size);
+            let size = value_len(&vhandle, size)?;
 
             self.size_map
                 .entry(vhandle.segment_id)
@@ -0,0 +1,88 @@
+// Copyright (c) 2024-present, fjall-rs
+// This source code is licensed under both the Apache 2.0 and MIT License
+// (found in the LICENSE-* files in the repository)
+
+use super::header::{BlobHeader, BlobKind};
+use crate::coding::{Decode, DecodeError, Encode, EncodeError};
+use byteorder::{BigEndian, ReadBytesExt, WriteBytesExt};
+use std::io::{Read, Write};
+
+/// Manifest of a large value, which is split into several chunk blobs
+///
+/// The manifest is stored as the (uncompressed) value of a [`BlobKind::ChunkManifest`] blob,
+/// which is directly followed by its [`BlobKind::Chunk`] blobs in the same segment.
+/// All chunks use the same key as the manifest.
+///
+/// Value handles point to the manifest blob.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct ChunkManifest {
+    /// Length of the entire value
+    pub len: u64,
+
+    /// Number of chunks following the manifest
+    pub chunk_count: u64,
+}
+
+impl ChunkManifest {
+    /// Parses a manifest from the value of a [`BlobKind::ChunkManifest`] blob.
+    pub fn from_value(mut value: &[u8]) -> crate::Result<Self> {
+        Self::decode_from(&mut value).map_err(Into::into)
+    }
+
+    /// Returns the size of the value as passed to the index, saturating at `u32::MAX`.
+    #[must_use]
+    pub fn index_size(&self) -> u32 {
+        u32::try_from(self.len).unwrap_or(u32::MAX)
+    }
+}
+
+impl Encode for ChunkManifest {
+    fn encode_into<W: Write>(&self, writer: &mut W) -> Result<(), EncodeError> {
+        writer.write_u64::<BigEndian>(self.len)?;
+        writer.write_u64::<BigEndian>(self.chunk_count)?;
+        Ok(())
+    }
+}
+
+impl Decode for ChunkManifest {
+    fn decode_from<R: Read>(reader: &mut R) -> Result<Self, DecodeError> {
+        let len = reader.read_u64::<BigEndian>()?;
+        let chunk_count = reader.read_u64::<BigEndian>()?;
+        Ok(Self { len, chunk_count })
+    }
+}
+
+/// Returns the error for a chunk manifest that is not followed by its chunks.
+#[must_use]
+pub fn broken_chunks() -> crate::Error {
+    crate::Error::Decode(DecodeError::InvalidHeader("Chunk"))
+}
+
+/// Checks that a blob following a chunk manifest is one of its chunks.
+pub fn check_chunk(header: &BlobHeader, key: &[u8], manifest_key: &[u8]) -> crate::Result<()> {
+    if header.kind == BlobKind::Chunk && key == manifest_key {
+        Ok(())
+    } else {
+        Err(broken_chunks())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use test_log::test;
+
+    #[test]
+    fn chunk_manifest_round_trip() -> crate::Result<()> {
+        let manifest = ChunkManifest {
+            len: 5 * 1_024 * 1_024 * 1_024,
+            chunk_count: 80,
+        };
+
+        let bytes = manifest.encode_into_vec();
+        assert_eq!(manifest, ChunkManifest::from_value(&bytes)?);
+        assert_eq!(u32::MAX, manifest.index_size());
+
+        Ok(())
+    }
+}
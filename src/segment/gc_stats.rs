@@ -2,7 +2,12 @@
 // This source code is licensed under both the Apache 2.0 and MIT License
 // (found in the LICENSE-* files in the repository)
 
-use std::sync::atomic::AtomicU64;
+use crate::coding::{Decode, DecodeError, Encode, EncodeError};
+use byteorder::{BigEndian, ReadBytesExt, WriteBytesExt};
+use std::{
+    io::{Read, Write},
+    sync::atomic::AtomicU64,
+};
 
 #[derive(Debug, Default)]
 #[cfg_attr(feature = "serde", derive(serde::Deserialize, serde::Serialize))]
@@ -22,6 +27,24 @@ impl GcStats {
             .store(x, std::sync::atomic::Ordering::Release);
     }
 
+    /// Adds to the amount of dead items, never exceeding `max`
+    pub(crate) fn add_stale_items(&self, x: u64, max: u64) {
+        let _ = self.stale_items.fetch_update(
+            std::sync::atomic::Ordering::AcqRel,
+            std::sync::atomic::Ordering::Acquire,
+            |v| Some(v.saturating_add(x).min(max)),
+        );
+    }
+
+    /// Adds to the amount of dead bytes, never exceeding `max`
+    pub(crate) fn add_stale_bytes(&self, x: u64, max: u64) {
+        let _ = self.stale_bytes.fetch_update(
+            std::sync::atomic::Ordering::AcqRel,
+            std::sync::atomic::Ordering::Acquire,
+            |v| Some(v.saturating_add(x).min(max)),
+        );
+    }
+
     /// Returns the amount of dead items in the segment
     pub fn stale_items(&self) -> u64 {
         self.stale_items.load(std::sync::atomic::Ordering::Acquire)
@@ -32,3 +55,23 @@ impl GcStats {
         self.stale_bytes.load(std::sync::atomic::Ordering::Acquire)
     }
 }
+
+impl Encode for GcStats {
+    fn encode_into<W: Write>(&self, writer: &mut W) -> Result<(), EncodeError> {
+        writer.write_u64::<BigEndian>(self.stale_items())?;
+        writer.write_u64::<BigEndian>(self.stale_bytes())?;
+        Ok(())
+    }
+}
+
+impl Decode for GcStats {
+    fn decode_from<R: Read>(reader: &mut R) -> Result<Self, DecodeError> {
+        let stale_items = reader.read_u64::<BigEndian>()?;
+        let stale_bytes = reader.read_u64::<BigEndian>()?;
+
+        Ok(Self {
+            stale_items: stale_items.into(),
+            stale_bytes: stale_bytes.into(),
+        })
+    }
+}
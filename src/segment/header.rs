@@ -0,0 +1,364 @@
+// Copyright (c) 2024-present, fjall-rs
+// This source code is licensed under both the Apache 2.0 and MIT License
+// (found in the LICENSE-* files in the repository)
+
+use super::meta::METADATA_HEADER_MAGIC;
+use crate::{coding::DecodeError, version::Version, Slice, UserKey};
+use byteorder::{BigEndian, ReadBytesExt, WriteBytesExt};
+use std::io::{Read, Write};
+
+pub const BLOB_HEADER_MAGIC_V1: &[u8] = &[b'V', b'L', b'G', b'B', b'L', b'O', b'B', 1];
+pub const BLOB_HEADER_MAGIC_V2: &[u8] = &[b'V', b'L', b'G', b'B', b'L', b'O', b'B', 2];
+
+/// Computes the checksum of a blob, over its key and its value as stored on disk
+#[must_use]
+pub fn compute_checksum(key: &[u8], value: &[u8]) -> u64 {
+    let mut hasher = xxhash_rust::xxh3::Xxh3::new();
+    hasher.update(key);
+    hasher.update(value);
+    hasher.digest()
+}
+
+/// Compression type of a single blob
+#[derive(Copy, Clone, Debug, Eq, PartialEq)]
+pub enum CompressionType {
+    /// The value is stored as-is
+    None,
+
+    /// The value was compressed using the codec with the given ID (see [`crate::Compressor::id`])
+    Codec(u8),
+}
+
+impl CompressionType {
+    /// Returns the compression type of values written using the given compressor.
+    pub fn of<C: crate::Compressor>(compressor: Option<&C>) -> Self {
+        compressor.map_or(Self::None, |compressor| Self::Codec(compressor.id()))
+    }
+}
+
+impl From<CompressionType> for u8 {
+    fn from(value: CompressionType) -> Self {
+        match value {
+            CompressionType::None => 0,
+            CompressionType::Codec(id) => id,
+        }
+    }
+}
+
+impl TryFrom<u8> for CompressionType {
+    type Error = DecodeError;
+
+    fn try_from(value: u8) -> Result<Self, Self::Error> {
+        match value {
+            0 => Ok(Self::None),
+            1..=15 => Ok(Self::Codec(value)),
+            _ => Err(DecodeError::InvalidTag(("CompressionType", value))),
+        }
+    }
+}
+
+/// Kind of a blob
+#[derive(Copy, Clone, Debug, Default, Eq, PartialEq)]
+pub enum BlobKind {
+    /// The blob stores an entire value
+    #[default]
+    Value,
+
+    /// The blob stores the manifest of a large value,
+    /// which is split into the chunk blobs directly following it
+    ChunkManifest,
+
+    /// The blob stores a chunk of a large value
+    Chunk,
+}
+
+impl From<BlobKind> for u8 {
+    fn from(value: BlobKind) -> Self {
+        match value {
+            BlobKind::Value => 0,
+            BlobKind::ChunkManifest => 1,
+            BlobKind::Chunk => 2,
+        }
+    }
+}
+
+impl TryFrom<u8> for BlobKind {
+    type Error = DecodeError;
+
+    fn try_from(value: u8) -> Result<Self, Self::Error> {
+        match value {
+            0 => Ok(Self::Value),
+            1 => Ok(Self::ChunkManifest),
+            2 => Ok(Self::Chunk),
+            _ => Err(DecodeError::InvalidTag(("BlobKind", value))),
+        }
+    }
+}
+
+/// Header of a blob, as stored on disk
+///
+/// V1: \[magic; 8\] \[checksum; 8\] \[key len; 2\] \[key\] \[value len; 4\] \[value\]
+///
+/// V2: \[magic; 8\] \[checksum; 8\] \[kind & compression; 1\] \[key len; 2\] \[uncompressed len; 4\] \[value len; 4\] \[key\] \[value\]
+///
+/// In V2, the kind is stored in the upper 4 bits, and the compression type in the lower 4 bits.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct BlobHeader {
+    /// Blob format version
+    pub version: Version,
+
+    /// Checksum over the key and the stored value
+    pub checksum: u64,
+
+    /// Key length
+    pub key_len: u16,
+
+    /// Length of the value as stored on disk
+    pub value_len: u32,
+
+    /// Compression type of the value
+    ///
+    /// `None` for V1 blobs, which do not store it, so it depends
+    /// on the value log's configuration.
+    pub compression: Option<CompressionType>,
+
+    /// Length of the value after decompression
+    ///
+    /// `None` for V1 blobs, which do not store it.
+    pub uncompressed_len: Option<u32>,
+
+    /// Kind of the blob
+    ///
+    /// Always [`BlobKind::Value`] for V1 blobs.
+    pub kind: BlobKind,
+}
+
+impl BlobHeader {
+    /// Returns the size of the header, excluding key and value.
+    #[must_use]
+    pub fn header_len(version: Version) -> u64 {
+        match version {
+            // magic + checksum + key len + value len
+            Version::V1 => 8 + 8 + 2 + 4,
+
+            // magic + checksum + compression + key len + uncompressed len + value len
+            Version::V2 => 8 + 8 + 1 + 2 + 4 + 4,
+        }
+    }
+
+    /// Returns the size of the entire blob on disk.
+    #[must_use]
+    pub fn blob_len(&self) -> u64 {
+        Self::header_len(self.version) + u64::from(self.key_len) + u64::from(self.value_len)
+    }
+
+    /// Writes the header and the key into the writer.
+    ///
+    /// The value needs to be written right after.
+    pub fn encode_with_key<W: Write>(&self, writer: &mut W, key: &[u8]) -> std::io::Result<()> {
+        match self.version {
+            Version::V1 => {
+                debug_assert_eq!(BlobKind::Value, self.kind, "V1 blobs cannot be chunked");
+
+                writer.write_all(BLOB_HEADER_MAGIC_V1)?;
+                writer.write_u64::<BigEndian>(self.checksum)?;
+
+                writer.write_u16::<BigEndian>(self.key_len)?;
+                writer.write_all(key)?;
+
+                writer.write_u32::<BigEndian>(self.value_len)?;
+            }
+            Version::V2 => {
+                writer.write_all(BLOB_HEADER_MAGIC_V2)?;
+                writer.write_u64::<BigEndian>(self.checksum)?;
+
+                let compression = u8::from(self.compression.unwrap_or(CompressionType::None));
+                debug_assert!(compression <= 0x0F, "codec ID does not fit into the header");
+
+                writer.write_u8(u8::from(self.kind) << 4 | compression)?;
+                writer.write_u16::<BigEndian>(self.key_len)?;
+                writer.write_u32::<BigEndian>(self.uncompressed_len.unwrap_or(self.value_len))?;
+                writer.write_u32::<BigEndian>(self.value_len)?;
+
+                writer.write_all(key)?;
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Reads a blob header and the blob's key from the reader.
+    ///
+    /// After this, the reader is positioned at the start of the value.
+    ///
+    /// Returns `None` if the segment's metadata was reached, meaning there are no more blobs.
+    pub fn decode_with_key<R: Read>(
+        reader: &mut R,
+    ) -> Result<Option<(Self, UserKey)>, DecodeError> {
+        let mut magic = [0; BLOB_HEADER_MAGIC_V1.len()];
+        reader.read_exact(&mut magic)?;
+
+        if magic == METADATA_HEADER_MAGIC {
+            return Ok(None);
+        }
+
+        if magic == BLOB_HEADER_MAGIC_V1 {
+            let checksum = reader.read_u64::<BigEndian>()?;
+
+            let key_len = reader.read_u16::<BigEndian>()?;
+            let key = Slice::from_reader(reader, key_len.into())?;
+
+            let value_len = reader.read_u32::<BigEndian>()?;
+
+            return Ok(Some((
+                Self {
+                    version: Version::V1,
+                    checksum,
+                    key_len,
+                    value_len,
+                    compression: None,
+                    uncompressed_len: None,
+                    kind: BlobKind::Value,
+                },
+                key,
+            )));
+        }
+
+        if magic == BLOB_HEADER_MAGIC_V2 {
+            let checksum = reader.read_u64::<BigEndian>()?;
+
+            let tag = reader.read_u8()?;
+            let kind = BlobKind::try_from(tag >> 4)?;
+            let compression = CompressionType::try_from(tag & 0x0F)?;
+            let key_len = reader.read_u16::<BigEndian>()?;
+            let uncompressed_len = reader.read_u32::<BigEndian>()?;
+            let value_len = reader.read_u32::<BigEndian>()?;
+
+            let key = Slice::from_reader(reader, key_len.into())?;
+
+            return Ok(Some((
+                Self {
+                    version: Version::V2,
+                    checksum,
+                    key_len,
+                    value_len,
+                    compression: Some(compression),
+                    uncompressed_len: Some(uncompressed_len),
+                    kind,
+                },
+                key,
+            )));
+        }
+
+        Err(DecodeError::InvalidHeader("Blob"))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::Cursor;
+    use test_log::test;
+
+    #[test]
+    fn blob_header_v1_round_trip() -> crate::Result<()> {
+        let key = b"abc";
+        let value = b"def";
+
+        let header = BlobHeader {
+            version: Version::V1,
+            checksum: compute_checksum(key, value),
+            key_len: 3,
+            value_len: 3,
+            compression: None,
+            uncompressed_len: None,
+            kind: BlobKind::Value,
+        };
+
+        let mut bytes = vec![];
+        header.encode_with_key(&mut bytes, key)?;
+        bytes.extend_from_slice(value);
+        assert_eq!(header.blob_len(), bytes.len() as u64);
+
+        let mut reader = Cursor::new(bytes);
+        let (decoded, decoded_key) =
+            BlobHeader::decode_with_key(&mut reader)?.expect("should exist");
+        assert_eq!(header, decoded);
+        assert_eq!(decoded_key, key);
+
+        Ok(())
+    }
+
+    #[test]
+    fn blob_header_v2_round_trip() -> crate::Result<()> {
+        let key = b"abc";
+        let value = b"compressed";
+
+        let header = BlobHeader {
+            version: Version::V2,
+            checksum: compute_checksum(key, value),
+            key_len: 3,
+            value_len: 10,
+            compression: Some(CompressionType::Codec(1)),
+            uncompressed_len: Some(100),
+            kind: BlobKind::Value,
+        };
+
+        let mut bytes = vec![];
+        header.encode_with_key(&mut bytes, key)?;
+        bytes.extend_from_slice(value);
+        assert_eq!(header.blob_len(), bytes.len() as u64);
+
+        let mut reader = Cursor::new(bytes);
+        let (decoded, decoded_key) =
+            BlobHeader::decode_with_key(&mut reader)?.expect("should exist");
+        assert_eq!(header, decoded);
+        assert_eq!(decoded_key, key);
+
+        Ok(())
+    }
+
+    #[test]
+    fn blob_header_v2_chunk_round_trip() -> crate::Result<()> {
+        let key = b"abc";
+        let value = b"chunk";
+
+        for kind in [BlobKind::ChunkManifest, BlobKind::Chunk] {
+            let header = BlobHeader {
+                version: Version::V2,
+                checksum: compute_checksum(key, value),
+                key_len: 3,
+                value_len: 5,
+                compression: Some(CompressionType::Codec(1)),
+                uncompressed_len: Some(50),
+                kind,
+            };
+
+            let mut bytes = vec![];
+            header.encode_with_key(&mut bytes, key)?;
+            bytes.extend_from_slice(value);
+
+            let mut reader = Cursor::new(bytes);
+            let (decoded, _) = BlobHeader::decode_with_key(&mut reader)?.expect("should exist");
+            assert_eq!(header, decoded);
+        }
+
+        Ok(())
+    }
+
+    #[test]
+    fn blob_header_metadata_magic() -> crate::Result<()> {
+        let mut reader = Cursor::new(METADATA_HEADER_MAGIC);
+        assert!(BlobHeader::decode_with_key(&mut reader)?.is_none());
+        Ok(())
+    }
+
+    #[test]
+    fn blob_header_invalid_magic() {
+        let mut reader = Cursor::new(b"VLGBLOB\x09");
+        assert!(matches!(
+            BlobHeader::decode_with_key(&mut reader),
+            Err(DecodeError::InvalidHeader("Blob")),
+        ));
+    }
+}
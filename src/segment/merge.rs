@@ -2,7 +2,11 @@
 // This source code is licensed under both the Apache 2.0 and MIT License
 // (found in the LICENSE-* files in the repository)
 
-use crate::{id::SegmentId, Compressor, SegmentReader, UserKey, UserValue};
+use super::{
+    chunked::{broken_chunks, check_chunk, ChunkManifest},
+    header::{BlobHeader, BlobKind},
+};
+use crate::{id::SegmentId, Compressor, SegmentReader, Slice, UserKey, UserValue};
 use interval_heap::IntervalHeap;
 use std::cmp::Reverse;
 
@@ -20,10 +24,10 @@ type IteratorIndex = usize;
 #[derive(Debug)]
 struct IteratorValue {
     index: IteratorIndex,
+    header: BlobHeader,
     key: UserKey,
-    value: UserValue,
+    value: Slice,
     segment_id: SegmentId,
-    checksum: u64,
 }
 
 impl PartialEq for IteratorValue {
@@ -46,32 +50,61 @@ impl Ord for IteratorValue {
 }
 
 /// Interleaves multiple segment readers into a single, sorted stream
+///
+/// Large values are returned as their chunk manifest, directly followed by their chunks.
 #[allow(clippy::module_name_repetitions)]
 pub struct MergeReader<C: Compressor + Clone> {
     readers: Vec<SegmentReader<C>>,
     heap: IntervalHeap<IteratorValue>,
+
+    /// Reader, key and remaining chunk count of the last returned chunk manifest
+    chunks: Option<(IteratorIndex, UserKey, u64)>,
 }
 
 impl<C: Compressor + Clone> MergeReader<C> {
     /// Initializes a new merging reader
     pub fn new(readers: Vec<SegmentReader<C>>) -> Self {
         let heap = IntervalHeap::with_capacity(readers.len());
-        Self { readers, heap }
+        Self {
+            readers,
+            heap,
+            chunks: None,
+        }
+    }
+
+    /// Reads the next chunk of the last returned chunk manifest.
+    fn next_chunk(&mut self, idx: IteratorIndex, key: &UserKey) -> crate::Result<IteratorValue> {
+        let reader = self.readers.get_mut(idx).expect("iter should exist");
+
+        let Some(item) = reader.next_raw() else {
+            return Err(broken_chunks());
+        };
+        let (header, k, v) = item?;
+
+        check_chunk(&header, &k, key)?;
+
+        Ok(IteratorValue {
+            index: idx,
+            header,
+            key: k,
+            value: v,
+            segment_id: reader.segment_id,
+        })
     }
 
     fn advance_reader(&mut self, idx: usize) -> crate::Result<()> {
         let reader = self.readers.get_mut(idx).expect("iter should exist");
 
-        if let Some(value) = reader.next() {
-            let (k, v, checksum) = value?;
+        if let Some(value) = reader.next_raw() {
+            let (header, k, v) = value?;
             let segment_id = reader.segment_id;
 
             self.heap.push(IteratorValue {
                 index: idx,
+                header,
                 key: k,
                 value: v,
                 segment_id,
-                checksum,
             });
         }
 
@@ -85,18 +118,31 @@ impl<C: Compressor + Clone> MergeReader<C> {
 
         Ok(())
     }
-}
 
-impl<C: Compressor + Clone> Iterator for MergeReader<C> {
-    type Item = crate::Result<(UserKey, UserValue, SegmentId, u64)>;
+    fn next_entry(&mut self) -> Option<crate::Result<IteratorValue>> {
+        if let Some((idx, key, remaining)) = self.chunks.take() {
+            if remaining > 0 {
+                let chunk = fail_iter!(self.next_chunk(idx, &key));
+                self.chunks = Some((idx, key, remaining - 1));
+                return Some(Ok(chunk));
+            }
+
+            fail_iter!(self.advance_reader(idx));
+        }
 
-    fn next(&mut self) -> Option<Self::Item> {
         if self.heap.is_empty() {
             fail_iter!(self.push_next());
         }
 
         if let Some(head) = self.heap.pop_min() {
-            fail_iter!(self.advance_reader(head.index));
+            // NOTE: The chunks of a large value share the key of its manifest,
+            // so they are read from the manifest's reader, instead of being deduplicated
+            if head.header.kind == BlobKind::ChunkManifest {
+                let manifest = fail_iter!(ChunkManifest::from_value(&head.value));
+                self.chunks = Some((head.index, head.key.clone(), manifest.chunk_count));
+            } else {
+                fail_iter!(self.advance_reader(head.index));
+            }
 
             // Discard old items
             while let Some(next) = self.heap.pop_min() {
@@ -110,9 +156,30 @@ impl<C: Compressor + Clone> Iterator for MergeReader<C> {
                 }
             }
 
-            return Some(Ok((head.key, head.value, head.segment_id, head.checksum)));
+            return Some(Ok(head));
         }
 
         None
     }
+
+    /// Returns the next blob, without decompressing its value.
+    pub(crate) fn next_raw(
+        &mut self,
+    ) -> Option<crate::Result<(BlobHeader, UserKey, Slice, SegmentId)>> {
+        let head = fail_iter!(self.next_entry()?);
+        Some(Ok((head.header, head.key, head.value, head.segment_id)))
+    }
+}
+
+impl<C: Compressor + Clone> Iterator for MergeReader<C> {
+    type Item = crate::Result<(UserKey, UserValue, SegmentId, u64)>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let head = fail_iter!(self.next_entry()?);
+
+        let reader = self.readers.get(head.index).expect("iter should exist");
+        let value = fail_iter!(reader.decompress(&head.header, head.value));
+
+        Some(Ok((head.key, value, head.segment_id, head.header.checksum)))
+    }
 }
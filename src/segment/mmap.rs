@@ -0,0 +1,70 @@
+// Copyright (c) 2024-present, fjall-rs
+// This source code is licensed under both the Apache 2.0 and MIT License
+// (found in the LICENSE-* files in the repository)
+
+use super::header::BlobHeader;
+use crate::{Slice, UserKey};
+use memmap2::Mmap;
+use std::{fs::File, path::Path, sync::Arc};
+
+/// Maps a segment file into memory.
+pub fn map(path: &Path) -> std::io::Result<Mmap> {
+    let file = File::open(path)?;
+
+    // SAFETY: The only invariant relied on is that segment files are never truncated
+    // or modified once written, neither by us nor by anyone else.
+    //
+    // The mapping may outlive the segment: with the `bytes` feature, values point into
+    // the mapping and keep it alive, even after the segment was dropped and its file
+    // was unlinked. That is fine, because unlinking does not change the mapped pages.
+    #[allow(unsafe_code)]
+    unsafe {
+        Mmap::map(&file)
+    }
+}
+
+/// A blob that was parsed from a memory-mapped segment
+pub struct MappedBlob {
+    pub header: BlobHeader,
+    pub key: UserKey,
+
+    /// The value as stored on disk (possibly compressed)
+    pub value: Slice,
+
+    /// Offset of the next blob in the segment
+    pub next_offset: u64,
+}
+
+/// Parses the blob at the given offset directly from the mapping.
+///
+/// Returns `None` if the offset is out of bounds, or the segment's metadata was reached.
+pub fn read_blob(map: &Arc<Mmap>, offset: u64) -> crate::Result<Option<MappedBlob>> {
+    let Ok(start) = usize::try_from(offset) else {
+        return Ok(None);
+    };
+
+    let Some(mut reader) = map.get(start..) else {
+        return Ok(None);
+    };
+    let len = reader.len();
+
+    let Some((header, key)) = BlobHeader::decode_with_key(&mut reader)? else {
+        return Ok(None);
+    };
+
+    let value_start = start + (len - reader.len());
+    let value_end = value_start + header.value_len as usize;
+
+    if value_end > map.len() {
+        return Err(crate::Error::Io(std::io::Error::from(
+            std::io::ErrorKind::UnexpectedEof,
+        )));
+    }
+
+    Ok(Some(MappedBlob {
+        header,
+        key,
+        value: Slice::from_mmap(map.clone(), value_start..value_end),
+        next_offset: value_end as u64,
+    }))
+}
@@ -2,10 +2,17 @@
 // This source code is licensed under both the Apache 2.0 and MIT License
 // (found in the LICENSE-* files in the repository)
 
+pub mod chunked;
 pub mod gc_stats;
+pub mod header;
 pub mod merge;
 pub mod meta;
+
+#[cfg(feature = "mmap")]
+pub mod mmap;
+
 pub mod multi_writer;
+pub mod pread;
 pub mod reader;
 pub mod trailer;
 pub mod writer;
@@ -13,7 +20,11 @@ pub mod writer;
 use crate::{id::SegmentId, Compressor};
 use gc_stats::GcStats;
 use meta::Metadata;
-use std::{marker::PhantomData, path::PathBuf};
+use std::{
+    marker::PhantomData,
+    path::PathBuf,
+    sync::atomic::{AtomicBool, Ordering},
+};
 
 /// A disk segment is an immutable, sorted, contiguous file
 /// that contains key-value pairs.
@@ -31,9 +42,33 @@ pub struct Segment<C: Compressor + Clone> {
     /// Runtime stats for garbage collection
     pub gc_stats: GcStats,
 
+    /// If `true`, the segment file is deleted once the segment is dropped,
+    /// which happens when no snapshot references it anymore
+    pub(crate) unlink_on_drop: AtomicBool,
+
+    /// Memory mapping of the segment file, created on first access
+    #[cfg(feature = "mmap")]
+    pub(crate) mmap: std::sync::OnceLock<std::sync::Arc<memmap2::Mmap>>,
+
     pub(crate) _phantom: PhantomData<C>,
 }
 
+impl<C: Compressor + Clone> Drop for Segment<C> {
+    fn drop(&mut self) {
+        // NOTE: Unmap the file first, some platforms cannot delete mapped files
+        #[cfg(feature = "mmap")]
+        drop(self.mmap.take());
+
+        if self.unlink_on_drop.load(Ordering::Acquire) {
+            log::trace!("Deleting vLog segment file at {}", self.path.display());
+
+            if let Err(e) = std::fs::remove_file(&self.path) {
+                log::error!("Could not free blob file at {}: {e:?}", self.path.display());
+            }
+        }
+    }
+}
+
 impl<C: Compressor + Clone> Segment<C> {
     /// Returns a scanner that can iterate through the segment.
     ///
@@ -54,6 +89,32 @@ impl<C: Compressor + Clone> Segment<C> {
         self.meta.item_count
     }
 
+    /// Returns the memory mapping of the segment file, mapping it if needed.
+    #[cfg(feature = "mmap")]
+    pub(crate) fn mmap(&self) -> std::io::Result<std::sync::Arc<memmap2::Mmap>> {
+        if let Some(map) = self.mmap.get() {
+            return Ok(map.clone());
+        }
+
+        log::trace!("Mapping vLog segment file at {}", self.path.display());
+        let map = std::sync::Arc::new(mmap::map(&self.path)?);
+
+        // NOTE: Another reader may have mapped the file concurrently, in which case ours is dropped
+        Ok(self.mmap.get_or_init(|| map).clone())
+    }
+
+    /// Schedules the segment file for deletion.
+    ///
+    /// The file is deleted as soon as the last reference to the segment is dropped.
+    pub(crate) fn mark_for_deletion(&self) {
+        self.unlink_on_drop.store(true, Ordering::Release);
+    }
+
+    /// Returns `true` if the segment file is deleted once the segment is dropped.
+    pub(crate) fn is_marked_for_deletion(&self) -> bool {
+        self.unlink_on_drop.load(Ordering::Acquire)
+    }
+
     /// Marks the segment as fully stale.
     pub(crate) fn mark_as_stale(&self) {
         self.gc_stats.set_stale_items(self.meta.item_count);
@@ -62,6 +123,17 @@ impl<C: Compressor + Clone> Segment<C> {
             .set_stale_bytes(self.meta.total_uncompressed_bytes);
     }
 
+    /// Marks some blobs of the segment as stale.
+    ///
+    /// The stats are capped at the segment's totals, so they never
+    /// exceed a fully stale segment.
+    pub(crate) fn mark_blobs_as_stale(&self, items: u64, bytes: u64) {
+        self.gc_stats.add_stale_items(items, self.meta.item_count);
+
+        self.gc_stats
+            .add_stale_bytes(bytes, self.meta.total_uncompressed_bytes);
+    }
+
     /// Returns `true` if the segment is fully stale.
     pub fn is_stale(&self) -> bool {
         self.gc_stats.stale_items() == self.meta.item_count
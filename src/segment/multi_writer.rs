@@ -2,10 +2,15 @@
 // This source code is licensed under both the Apache 2.0 and MIT License
 // (found in the LICENSE-* files in the repository)
 
-use super::writer::Writer;
+use super::{
+    chunked::ChunkManifest,
+    header::{BlobHeader, BlobKind},
+    writer::Writer,
+};
 use crate::{
     compression::Compressor,
     id::{IdGenerator, SegmentId},
+    version::Version,
     ValueHandle,
 };
 use std::path::{Path, PathBuf};
@@ -20,6 +25,13 @@ pub struct MultiWriter<C: Compressor + Clone> {
     id_generator: IdGenerator,
 
     compression: Option<C>,
+
+    version: Version,
+
+    chunk_size: u32,
+
+    /// Number of chunks that still need to follow the last written chunk manifest
+    pending_chunks: u64,
 }
 
 impl<C: Compressor + Clone> MultiWriter<C> {
@@ -47,6 +59,11 @@ impl<C: Compressor + Clone> MultiWriter<C> {
             writers: vec![Writer::new(segment_path, segment_id)?],
 
             compression: None,
+
+            version: Version::V2,
+
+            chunk_size: u32::MAX,
+            pending_chunks: 0,
         })
     }
 
@@ -59,6 +76,28 @@ impl<C: Compressor + Clone> MultiWriter<C> {
         self
     }
 
+    /// Sets the blob format version
+    #[must_use]
+    pub(crate) fn use_version(mut self, version: Version) -> Self {
+        self.version = version;
+
+        let writer = self.get_active_writer_mut();
+        writer.version = version;
+
+        self
+    }
+
+    /// Sets the size of chunks that large values are split into
+    #[must_use]
+    pub(crate) fn use_chunk_size(mut self, chunk_size: u32) -> Self {
+        self.chunk_size = chunk_size;
+
+        let writer = self.get_active_writer_mut();
+        writer.chunk_size = chunk_size;
+
+        self
+    }
+
     #[doc(hidden)]
     #[must_use]
     pub fn get_active_writer(&self) -> &Writer<C> {
@@ -102,8 +141,10 @@ impl<C: Compressor + Clone> MultiWriter<C> {
         let new_segment_id = self.id_generator.next();
         let segment_path = self.folder.join(new_segment_id.to_string());
 
-        let new_writer =
-            Writer::new(segment_path, new_segment_id)?.use_compression(self.compression.clone());
+        let new_writer = Writer::new(segment_path, new_segment_id)?
+            .use_compression(self.compression.clone())
+            .use_version(self.version)
+            .use_chunk_size(self.chunk_size);
 
         self.writers.push(new_writer);
 
@@ -112,6 +153,9 @@ impl<C: Compressor + Clone> MultiWriter<C> {
 
     /// Writes an item.
     ///
+    /// Values larger than the configured chunk size are split into chunks,
+    /// which are stored in the same segment.
+    ///
     /// # Errors
     ///
     /// Will return `Err` if an IO error occurs.
@@ -138,6 +182,43 @@ impl<C: Compressor + Clone> MultiWriter<C> {
         Ok(bytes_written)
     }
 
+    /// Writes an item whose value is already encoded as described by its blob header.
+    ///
+    /// # Errors
+    ///
+    /// Will return `Err` if an IO error occurs.
+    pub(crate) fn write_raw(
+        &mut self,
+        key: &[u8],
+        header: &BlobHeader,
+        value: &[u8],
+    ) -> crate::Result<()> {
+        let target_size = self.target_size;
+
+        // NOTE: The chunks of a large value need to directly follow their manifest,
+        // so we cannot rotate in between
+        match header.kind {
+            BlobKind::Value => self.pending_chunks = 0,
+            BlobKind::ChunkManifest => {
+                self.pending_chunks = ChunkManifest::from_value(value)?.chunk_count;
+            }
+            BlobKind::Chunk => self.pending_chunks = self.pending_chunks.saturating_sub(1),
+        }
+        let pending_chunks = self.pending_chunks;
+
+        // Write actual value into segment
+        let writer = self.get_active_writer_mut();
+        writer.write_raw(key, header, value)?;
+
+        // Check for segment size target, maybe rotate to next writer
+        if pending_chunks == 0 && writer.offset() >= target_size {
+            writer.flush()?;
+            self.rotate()?;
+        }
+
+        Ok(())
+    }
+
     pub(crate) fn finish(mut self) -> crate::Result<Vec<Writer<C>>> {
         let writer = self.get_active_writer_mut();
 
@@ -0,0 +1,106 @@
+// Copyright (c) 2024-present, fjall-rs
+// This source code is licensed under both the Apache 2.0 and MIT License
+// (found in the LICENSE-* files in the repository)
+
+use std::{
+    fs::File,
+    io::{Read, Seek, SeekFrom},
+    sync::Arc,
+};
+
+/// Reads a file using positional reads (`pread`), starting at some offset
+///
+/// The file's cursor is never used, so the same file can be read by
+/// many threads concurrently.
+pub struct PositionalReader {
+    file: Arc<File>,
+    offset: u64,
+}
+
+impl PositionalReader {
+    /// Creates a reader that starts reading at the given offset.
+    pub fn new(file: Arc<File>, offset: u64) -> Self {
+        Self { file, offset }
+    }
+
+    #[cfg(unix)]
+    fn read_at(&self, buf: &mut [u8]) -> std::io::Result<usize> {
+        use std::os::unix::fs::FileExt;
+        self.file.read_at(buf, self.offset)
+    }
+
+    #[cfg(windows)]
+    fn read_at(&self, buf: &mut [u8]) -> std::io::Result<usize> {
+        use std::os::windows::fs::FileExt;
+        self.file.seek_read(buf, self.offset)
+    }
+}
+
+impl Read for PositionalReader {
+    fn read(&mut self, buf: &mut [u8]) -> std::io::Result<usize> {
+        let n = self.read_at(buf)?;
+        self.offset += n as u64;
+        Ok(n)
+    }
+}
+
+impl Seek for PositionalReader {
+    fn seek(&mut self, pos: SeekFrom) -> std::io::Result<u64> {
+        let offset = match pos {
+            SeekFrom::Start(offset) => Some(offset),
+            SeekFrom::Current(delta) => self.offset.checked_add_signed(delta),
+            SeekFrom::End(delta) => self.file.metadata()?.len().checked_add_signed(delta),
+        };
+
+        let Some(offset) = offset else {
+            return Err(std::io::Error::new(
+                std::io::ErrorKind::InvalidInput,
+                "invalid seek to a negative or overflowing position",
+            ));
+        };
+
+        self.offset = offset;
+        Ok(offset)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::{BufReader, Write};
+    use test_log::test;
+
+    #[test]
+    fn pread_concurrent_readers() -> crate::Result<()> {
+        let dir = tempfile::tempdir()?;
+        let path = dir.path().join("file");
+
+        {
+            let mut file = File::create(&path)?;
+            file.write_all(b"0123456789")?;
+            file.sync_all()?;
+        }
+
+        let file = Arc::new(File::open(&path)?);
+
+        let mut a = BufReader::new(PositionalReader::new(file.clone(), 2));
+        let mut b = BufReader::new(PositionalReader::new(file, 7));
+
+        let mut buf = [0; 3];
+
+        a.read_exact(&mut buf)?;
+        assert_eq!(b"234", &buf);
+
+        b.read_exact(&mut buf)?;
+        assert_eq!(b"789", &buf);
+
+        assert_eq!(5, a.stream_position()?);
+        assert_eq!(10, b.stream_position()?);
+
+        a.seek(SeekFrom::Start(0))?;
+        a.read_exact(&mut buf)?;
+        assert_eq!(b"012", &buf);
+
+        Ok(())
+    }
+}
@@ -2,9 +2,8 @@
 // This source code is licensed under both the Apache 2.0 and MIT License
 // (found in the LICENSE-* files in the repository)
 
-use super::{meta::METADATA_HEADER_MAGIC, writer::BLOB_HEADER_MAGIC};
-use crate::{coding::DecodeError, id::SegmentId, Compressor, Slice, UserKey, UserValue};
-use byteorder::{BigEndian, ReadBytesExt};
+use super::header::{BlobHeader, BlobKind, CompressionType};
+use crate::{id::SegmentId, Compressor, Slice, UserKey, UserValue};
 use std::{
     fs::File,
     io::{BufReader, Read, Seek},
@@ -21,9 +20,9 @@ macro_rules! fail_iter {
 }
 
 /// Reads through a segment in order.
-pub struct Reader<C: Compressor + Clone> {
+pub struct Reader<C: Compressor + Clone, R: Read + Seek = BufReader<File>> {
     pub(crate) segment_id: SegmentId,
-    inner: BufReader<File>,
+    inner: R,
     is_terminated: bool,
     compression: Option<C>,
 }
@@ -39,14 +38,16 @@ impl<C: Compressor + Clone> Reader<C> {
 
         Ok(Self::with_reader(segment_id, file_reader))
     }
+}
 
+impl<C: Compressor + Clone, R: Read + Seek> Reader<C, R> {
     pub(crate) fn get_offset(&mut self) -> std::io::Result<u64> {
         self.inner.stream_position()
     }
 
     /// Initializes a new segment reader.
     #[must_use]
-    pub fn with_reader(segment_id: SegmentId, file_reader: BufReader<File>) -> Self {
+    pub fn with_reader(segment_id: SegmentId, file_reader: R) -> Self {
         Self {
             segment_id,
             inner: file_reader,
@@ -60,55 +61,105 @@ impl<C: Compressor + Clone> Reader<C> {
         self
     }
 
-    pub(crate) fn into_inner(self) -> BufReader<File> {
-        self.inner
-    }
-}
-
-impl<C: Compressor + Clone> Iterator for Reader<C> {
-    type Item = crate::Result<(UserKey, UserValue, u64)>;
-
-    fn next(&mut self) -> Option<Self::Item> {
+    /// Reads the next blob, without decompressing its value.
+    pub(crate) fn next_raw(&mut self) -> Option<crate::Result<(BlobHeader, UserKey, Slice)>> {
         if self.is_terminated {
             return None;
         }
 
-        {
-            let mut buf = [0; BLOB_HEADER_MAGIC.len()];
-            fail_iter!(self.inner.read_exact(&mut buf));
+        let Some((header, key)) = fail_iter!(BlobHeader::decode_with_key(&mut self.inner)) else {
+            self.is_terminated = true;
+            return None;
+        };
+
+        let value = fail_iter!(Slice::from_reader(
+            &mut self.inner,
+            header.value_len as usize
+        ));
 
-            if buf == METADATA_HEADER_MAGIC {
-                self.is_terminated = true;
-                return None;
-            }
+        Some(Ok((header, key, value)))
+    }
 
-            if buf != BLOB_HEADER_MAGIC {
-                return Some(Err(crate::Error::Decode(DecodeError::InvalidHeader(
-                    "Blob",
-                ))));
-            }
+    /// Decompresses a raw value read by [`Reader::next_raw`].
+    ///
+    /// If the reader has no compressor, values are returned as stored on disk.
+    pub(crate) fn decompress(&self, header: &BlobHeader, value: Slice) -> crate::Result<UserValue> {
+        decompress(self.compression.as_ref(), header, value)
+    }
+}
+
+/// Decompresses a value as stored on disk.
+///
+/// If no compressor is given, V1 values are returned as stored on disk.
+///
+/// # Errors
+///
+/// Returns error if the value is compressed, but no compressor is given.
+pub fn decompress<C: Compressor>(
+    compression: Option<&C>,
+    header: &BlobHeader,
+    value: Slice,
+) -> crate::Result<UserValue> {
+    match compressor_for(compression, header)? {
+        Some(compressor) => Ok(Slice::from(compressor.decompress(&value)?)),
+        None => Ok(value),
+    }
+}
+
+/// Returns the compressor needed to decompress a value as stored on disk,
+/// or `None` if the value is stored uncompressed.
+///
+/// # Errors
+///
+/// Returns error if the value is compressed, but no compressor is given,
+/// or the given compressor uses a different codec.
+pub fn compressor_for<'a, C: Compressor>(
+    compression: Option<&'a C>,
+    header: &BlobHeader,
+) -> crate::Result<Option<&'a C>> {
+    match (compression, header.compression) {
+        (_, Some(CompressionType::None)) => Ok(None),
+        (Some(compressor), Some(CompressionType::Codec(id))) if id != compressor.id() => {
+            log::error!(
+                "Blob is compressed with codec {id}, but the configured compressor uses codec {}",
+                compressor.id(),
+            );
+            Err(crate::Error::Decompress)
+        }
+        (None, Some(CompressionType::Codec(_))) => {
+            log::error!("Blob is compressed, but no compressor is configured");
+            Err(crate::Error::Decompress)
         }
 
-        let checksum = fail_iter!(self.inner.read_u64::<BigEndian>());
+        // NOTE: V1 blobs do not store their compression type,
+        // so we need to trust the configured compression
+        (compression, _) => Ok(compression),
+    }
+}
 
-        let key_len = fail_iter!(self.inner.read_u16::<BigEndian>());
-        let key = fail_iter!(Slice::from_reader(&mut self.inner, key_len as usize));
+impl<C: Compressor + Clone, R: Read + Seek> Reader<C, BufReader<R>> {
+    /// Returns `true` if the next blob is fully contained in the read buffer,
+    /// so reading it does not cause any I/O.
+    ///
+    /// The chunks of a large value are never considered to be buffered.
+    pub(crate) fn next_is_buffered(&self) -> bool {
+        let mut buffer = self.inner.buffer();
 
-        let val_len = fail_iter!(self.inner.read_u32::<BigEndian>());
-        let val = match &self.compression {
-            Some(compressor) => {
-                // TODO: https://github.com/PSeitz/lz4_flex/issues/166
-                let mut val = vec![0; val_len as usize];
-                fail_iter!(self.inner.read_exact(&mut val));
-                Slice::from(fail_iter!(compressor.decompress(&val)))
+        match BlobHeader::decode_with_key(&mut buffer) {
+            Ok(Some((header, _))) => {
+                header.kind != BlobKind::ChunkManifest && buffer.len() >= header.value_len as usize
             }
-            None => {
-                // NOTE: When not using compression, we can skip
-                // the intermediary heap allocation and read directly into a Slice
-                fail_iter!(Slice::from_reader(&mut self.inner, val_len as usize))
-            }
-        };
+            _ => false,
+        }
+    }
+}
+
+impl<C: Compressor + Clone, R: Read + Seek> Iterator for Reader<C, R> {
+    type Item = crate::Result<(UserKey, UserValue, u64)>;
 
-        Some(Ok((key, val, checksum)))
+    fn next(&mut self) -> Option<Self::Item> {
+        let (header, key, value) = fail_iter!(self.next_raw()?);
+        let value = fail_iter!(self.decompress(&header, value));
+        Some(Ok((key, value, header.checksum)))
     }
 }
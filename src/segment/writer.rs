@@ -2,17 +2,22 @@
 // This source code is licensed under both the Apache 2.0 and MIT License
 // (found in the LICENSE-* files in the repository)
 
-use super::{meta::Metadata, trailer::SegmentFileTrailer};
-use crate::{coding::Encode, compression::Compressor, id::SegmentId, key_range::KeyRange, UserKey};
-use byteorder::{BigEndian, WriteBytesExt};
+use super::{
+    chunked::ChunkManifest,
+    header::{compute_checksum, BlobHeader, BlobKind, CompressionType},
+    meta::Metadata,
+    trailer::SegmentFileTrailer,
+};
+use crate::{
+    coding::Encode, compression::Compressor, id::SegmentId, key_range::KeyRange, version::Version,
+    UserKey,
+};
 use std::{
     fs::File,
     io::{BufWriter, Seek, Write},
     path::{Path, PathBuf},
 };
 
-pub const BLOB_HEADER_MAGIC: &[u8] = &[b'V', b'L', b'G', b'B', b'L', b'O', b'B', 1];
-
 /// Segment writer
 pub struct Writer<C: Compressor + Clone> {
     pub path: PathBuf,
@@ -31,6 +36,12 @@ pub struct Writer<C: Compressor + Clone> {
     pub(crate) last_key: Option<UserKey>,
 
     pub(crate) compression: Option<C>,
+
+    /// Blob format version
+    pub(crate) version: Version,
+
+    /// Values larger than this are split into chunks of this size
+    pub(crate) chunk_size: u32,
 }
 
 impl<C: Compressor + Clone> Writer<C> {
@@ -58,6 +69,10 @@ impl<C: Compressor + Clone> Writer<C> {
             last_key: None,
 
             compression: None,
+
+            version: Version::V2,
+
+            chunk_size: u32::MAX,
         })
     }
 
@@ -66,6 +81,18 @@ impl<C: Compressor + Clone> Writer<C> {
         self
     }
 
+    /// Sets the blob format version.
+    pub(crate) fn use_version(mut self, version: Version) -> Self {
+        self.version = version;
+        self
+    }
+
+    /// Sets the size of chunks that large values are split into.
+    pub(crate) fn use_chunk_size(mut self, chunk_size: u32) -> Self {
+        self.chunk_size = chunk_size;
+        self
+    }
+
     /// Returns the current offset in the file.
     ///
     /// This can be used to index an item into an external `Index`.
@@ -82,16 +109,56 @@ impl<C: Compressor + Clone> Writer<C> {
 
     /// Writes an item into the file
     ///
+    /// Values larger than the chunk size are split into a chunk manifest,
+    /// followed by the value's chunks (see [`ChunkManifest`]).
+    /// The V1 blob format does not support chunks, so V1 segments never chunk values.
+    ///
+    /// Returns the length of the value as stored on disk, saturating at `u32::MAX`.
+    ///
     /// # Errors
     ///
     /// Will return `Err` if an IO error occurs.
     ///
     /// # Panics
     ///
-    /// Panics if the key length is empty or greater than 2^16, or the value length is greater than 2^32.
+    /// Panics if the key length is empty or greater than 2^16.
     pub fn write(&mut self, key: &[u8], value: &[u8]) -> crate::Result<u32> {
         assert!(!key.is_empty());
         assert!(key.len() <= u16::MAX.into());
+
+        if self.version == Version::V2 && value.len() as u64 > u64::from(self.chunk_size) {
+            return self.write_chunked(key, value);
+        }
+
+        self.write_blob(key, value, BlobKind::Value)
+    }
+
+    /// Writes a large value as a chunk manifest, directly followed by its chunks.
+    fn write_chunked(&mut self, key: &[u8], value: &[u8]) -> crate::Result<u32> {
+        let chunk_size = self.chunk_size.max(1) as usize;
+
+        let manifest = ChunkManifest {
+            len: value.len() as u64,
+            chunk_count: value.len().div_ceil(chunk_size) as u64,
+        };
+
+        let mut written = u64::from(self.write_blob(
+            key,
+            &manifest.encode_into_vec(),
+            BlobKind::ChunkManifest,
+        )?);
+
+        for chunk in value.chunks(chunk_size) {
+            written += u64::from(self.write_blob(key, chunk, BlobKind::Chunk)?);
+        }
+
+        Ok(u32::try_from(written).unwrap_or(u32::MAX))
+    }
+
+    /// Writes a single blob into the file.
+    ///
+    /// Chunk manifests are never compressed.
+    fn write_blob(&mut self, key: &[u8], value: &[u8], kind: BlobKind) -> crate::Result<u32> {
         assert!(u32::try_from(value.len()).is_ok());
 
         if self.first_key.is_none() {
@@ -99,66 +166,99 @@ impl<C: Compressor + Clone> Writer<C> {
         }
         self.last_key = Some(key.into());
 
-        self.uncompressed_bytes += value.len() as u64;
+        // NOTE: A chunk manifest is not part of the value
+        if kind != BlobKind::ChunkManifest {
+            self.uncompressed_bytes += value.len() as u64;
+        }
 
-        let value = match &self.compression {
-            Some(compressor) => compressor.compress(value)?,
-            None => value.to_vec(),
+        // NOTE: Truncation is okay because we asserted the value length above
+        #[allow(clippy::cast_possible_truncation)]
+        let uncompressed_len = value.len() as u32;
+
+        let (value, compression) = match &self.compression {
+            Some(compressor) if kind != BlobKind::ChunkManifest => (
+                compressor.compress(value)?,
+                CompressionType::Codec(compressor.id()),
+            ),
+            _ => (value.to_vec(), CompressionType::None),
         };
 
-        let mut hasher = xxhash_rust::xxh3::Xxh3::new();
-        hasher.update(key);
-        hasher.update(&value);
-        let checksum = hasher.digest();
+        // NOTE: Truncation is okay and actually needed
+        #[allow(clippy::cast_possible_truncation)]
+        let header = BlobHeader {
+            version: self.version,
+            checksum: compute_checksum(key, &value),
+            key_len: key.len() as u16,
+            value_len: value.len() as u32,
+            compression: Some(compression),
+            uncompressed_len: Some(uncompressed_len),
+            kind,
+        };
 
-        // TODO: 2.0.0 formalize blob header
-        // into struct... store uncompressed len as well
-        // so we can optimize rollover by avoiding
-        // repeated compression & decompression
+        header.encode_with_key(&mut self.active_writer, key)?;
+        self.active_writer.write_all(&value)?;
 
-        // Write header
-        self.active_writer.write_all(BLOB_HEADER_MAGIC)?;
+        self.offset += header.blob_len();
 
-        // Write checksum
-        self.active_writer.write_u64::<BigEndian>(checksum)?;
+        // Update metadata
+        self.written_blob_bytes += value.len() as u64;
 
-        // Write key
+        // NOTE: A large value counts as a single item, no matter how many chunks it has
+        if kind != BlobKind::Chunk {
+            self.item_count += 1;
+        }
 
-        // NOTE: Truncation is okay and actually needed
+        // NOTE: Truncation is okay
         #[allow(clippy::cast_possible_truncation)]
-        self.active_writer
-            .write_u16::<BigEndian>(key.len() as u16)?;
-        self.active_writer.write_all(key)?;
+        Ok(value.len() as u32)
+    }
 
-        // Write value
+    /// Writes an item into the file, using a value that is already encoded
+    /// (e.g. compressed) as described by its blob header.
+    ///
+    /// The header's checksum is reused, and its uncompressed length needs to be known.
+    ///
+    /// # Errors
+    ///
+    /// Will return `Err` if an IO error occurs.
+    pub(crate) fn write_raw(
+        &mut self,
+        key: &[u8],
+        header: &BlobHeader,
+        value: &[u8],
+    ) -> crate::Result<()> {
+        debug_assert_eq!(usize::from(header.key_len), key.len());
+        debug_assert_eq!(header.value_len as usize, value.len());
+        debug_assert!(header.uncompressed_len.is_some());
 
-        // NOTE: Truncation is okay and actually needed
-        #[allow(clippy::cast_possible_truncation)]
-        self.active_writer
-            .write_u32::<BigEndian>(value.len() as u32)?;
-        self.active_writer.write_all(&value)?;
+        if self.first_key.is_none() {
+            self.first_key = Some(key.into());
+        }
+        self.last_key = Some(key.into());
 
-        // Header
-        self.offset += BLOB_HEADER_MAGIC.len() as u64;
+        if header.kind != BlobKind::ChunkManifest {
+            self.uncompressed_bytes +=
+                u64::from(header.uncompressed_len.unwrap_or(header.value_len));
+        }
 
-        // Checksum
-        self.offset += std::mem::size_of::<u64>() as u64;
+        let header = BlobHeader {
+            version: self.version,
+            ..header.clone()
+        };
 
-        // Key
-        self.offset += std::mem::size_of::<u16>() as u64;
-        self.offset += key.len() as u64;
+        header.encode_with_key(&mut self.active_writer, key)?;
+        self.active_writer.write_all(value)?;
 
-        // Value
-        self.offset += std::mem::size_of::<u32>() as u64;
-        self.offset += value.len() as u64;
+        self.offset += header.blob_len();
 
         // Update metadata
         self.written_blob_bytes += value.len() as u64;
-        self.item_count += 1;
 
-        // NOTE: Truncation is okay
-        #[allow(clippy::cast_possible_truncation)]
-        Ok(value.len() as u32)
+        if header.kind != BlobKind::Chunk {
+            self.item_count += 1;
+        }
+
+        Ok(())
     }
 
     pub(crate) fn flush(&mut self) -> crate::Result<()> {
@@ -98,6 +98,27 @@ impl Slice {
 
         Ok(Self(builder.freeze()))
     }
+
+    /// Constructs a [`Slice`] that points into a memory-mapped file, without copying.
+    ///
+    /// The mapping is kept alive as long as the slice (or any clone of it) is alive.
+    #[cfg(feature = "mmap")]
+    pub(crate) fn from_mmap(
+        map: std::sync::Arc<memmap2::Mmap>,
+        range: std::ops::Range<usize>,
+    ) -> Self {
+        Self(Bytes::from_owner(MmapOwner(map)).slice(range))
+    }
+}
+
+#[cfg(feature = "mmap")]
+struct MmapOwner(std::sync::Arc<memmap2::Mmap>);
+
+#[cfg(feature = "mmap")]
+impl AsRef<[u8]> for MmapOwner {
+    fn as_ref(&self) -> &[u8] {
+        &self.0
+    }
 }
 
 impl From<Bytes> for Slice {
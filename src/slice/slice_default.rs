@@ -59,6 +59,18 @@ impl Slice {
         let view = ByteView::from_reader(reader, len)?;
         Ok(Self(view))
     }
+
+    /// Constructs a [`Slice`] from a range of a memory-mapped file.
+    ///
+    /// [`ByteView`] cannot point into foreign memory, so the bytes are copied
+    /// out of the mapping; use the `bytes` feature to avoid the copy.
+    #[cfg(feature = "mmap")]
+    pub(crate) fn from_mmap(
+        map: std::sync::Arc<memmap2::Mmap>,
+        range: std::ops::Range<usize>,
+    ) -> Self {
+        Self::new(map.get(range).unwrap_or_default())
+    }
 }
 
 // Arc::from<Vec<u8>> is specialized
@@ -0,0 +1,163 @@
+// Copyright (c) 2024-present, fjall-rs
+// This source code is licensed under both the Apache 2.0 and MIT License
+// (found in the LICENSE-* files in the repository)
+
+use crate::{
+    id::SegmentId,
+    manifest::SegmentMap,
+    prefetch::{Prefetch, DEFAULT_READAHEAD},
+    segment::Segment,
+    stat::BlobStat,
+    value_reader::ValueReader,
+    BlobCache, Compressor, FDCache, UserKey, UserValue, ValueHandle, ValueLog,
+};
+use std::sync::Arc;
+
+/// A consistent view of the segments of a value log
+///
+/// While a snapshot is alive, none of the segments it references are deleted
+/// from disk, even if they are dropped from the value log in the meantime.
+/// So value handles that were read from the index while the snapshot was taken
+/// can still be resolved, even if garbage collection has moved their blobs.
+///
+/// Created using [`ValueLog::snapshot`].
+pub struct Snapshot<BC: BlobCache, FDC: FDCache, C: Compressor + Clone> {
+    vlog: ValueLog<BC, FDC, C>,
+    segments: Arc<SegmentMap<C>>,
+}
+
+impl<BC: BlobCache, FDC: FDCache, C: Compressor + Clone> Snapshot<BC, FDC, C> {
+    pub(crate) fn new(vlog: ValueLog<BC, FDC, C>, segments: Arc<SegmentMap<C>>) -> Self {
+        Self { vlog, segments }
+    }
+
+    /// Resolves a value handle.
+    ///
+    /// # Errors
+    ///
+    /// Will return `Err` if an IO error occurs.
+    pub fn get(&self, vhandle: &ValueHandle) -> crate::Result<Option<UserValue>> {
+        self.get_with_prefetch(vhandle, Prefetch::None)
+    }
+
+    /// Resolves a value handle, and prefetches some values after it.
+    ///
+    /// # Errors
+    ///
+    /// Will return `Err` if an IO error occurs.
+    pub fn get_with_prefetch(
+        &self,
+        vhandle: &ValueHandle,
+        prefetch: impl Into<Prefetch>,
+    ) -> crate::Result<Option<UserValue>> {
+        self.get_with_readahead(vhandle, prefetch, DEFAULT_READAHEAD)
+    }
+
+    /// Resolves a value handle, and prefetches some values after it,
+    /// reading the segment file in chunks of `readahead` bytes.
+    ///
+    /// # Errors
+    ///
+    /// Will return `Err` if an IO error occurs.
+    pub fn get_with_readahead(
+        &self,
+        vhandle: &ValueHandle,
+        prefetch: impl Into<Prefetch>,
+        readahead: usize,
+    ) -> crate::Result<Option<UserValue>> {
+        self.vlog.get_with(vhandle, prefetch.into(), readahead, || {
+            self.get_segment(vhandle.segment_id)
+        })
+    }
+
+    /// Resolves a value handle, returning the blob's key alongside its value.
+    ///
+    /// See [`ValueLog::get_entry`].
+    ///
+    /// # Errors
+    ///
+    /// Will return `Err` if an IO error occurs.
+    pub fn get_entry(&self, vhandle: &ValueHandle) -> crate::Result<Option<(UserKey, UserValue)>> {
+        self.vlog.get_entry_with(vhandle, |id| self.get_segment(id))
+    }
+
+    /// Resolves a value handle, checking that the blob belongs to the expected key.
+    ///
+    /// See [`ValueLog::get_checked`].
+    ///
+    /// # Errors
+    ///
+    /// Will return `Err` if an IO error occurs,
+    /// or [`crate::Error::KeyMismatch`] if the blob belongs to another key.
+    pub fn get_checked(
+        &self,
+        vhandle: &ValueHandle,
+        expected_key: &[u8],
+    ) -> crate::Result<Option<UserValue>> {
+        self.vlog
+            .get_checked_with(vhandle, expected_key, |id| self.get_segment(id))
+    }
+
+    /// Resolves many value handles at once.
+    ///
+    /// See [`ValueLog::multi_get`].
+    ///
+    /// # Errors
+    ///
+    /// Will return `Err` if an IO error occurs.
+    pub fn multi_get(&self, vhandles: &[ValueHandle]) -> crate::Result<Vec<Option<UserValue>>> {
+        self.vlog
+            .multi_get_with(vhandles, |id| self.get_segment(id))
+    }
+
+    /// Opens a reader over the value of a blob, without reading the value into memory.
+    ///
+    /// See [`ValueLog::open_value`].
+    ///
+    /// # Errors
+    ///
+    /// Will return `Err` if an IO error occurs,
+    /// or [`crate::Error::CompressedValue`] if the value is compressed.
+    pub fn open_value(&self, vhandle: &ValueHandle) -> crate::Result<Option<ValueReader<C>>> {
+        self.vlog
+            .open_value_with(vhandle, |id| self.get_segment(id))
+    }
+
+    /// Reads `len` bytes of a value, starting at `offset` into the value.
+    ///
+    /// See [`ValueLog::get_range`].
+    ///
+    /// # Errors
+    ///
+    /// Will return `Err` if an IO error occurs.
+    pub fn get_range(
+        &self,
+        vhandle: &ValueHandle,
+        offset: u64,
+        len: u64,
+    ) -> crate::Result<Option<UserValue>> {
+        self.vlog
+            .get_range_with(vhandle, offset, len, |id| self.get_segment(id))
+    }
+
+    /// Returns the metadata of a blob, reading only its header.
+    ///
+    /// See [`ValueLog::stat`].
+    ///
+    /// # Errors
+    ///
+    /// Will return `Err` if an IO error occurs.
+    pub fn stat(&self, vhandle: &ValueHandle) -> crate::Result<Option<BlobStat>> {
+        self.vlog.stat_with(vhandle, |id| self.get_segment(id))
+    }
+
+    fn get_segment(&self, id: SegmentId) -> Option<Arc<Segment<C>>> {
+        self.segments.get(&id).cloned()
+    }
+
+    /// Returns the amount of segments in the snapshot.
+    #[must_use]
+    pub fn segment_count(&self) -> usize {
+        self.segments.len()
+    }
+}
@@ -0,0 +1,26 @@
+// Copyright (c) 2024-present, fjall-rs
+// This source code is licensed under both the Apache 2.0 and MIT License
+// (found in the LICENSE-* files in the repository)
+
+/// Metadata of a blob, read from its header
+///
+/// For large values that are split into chunks, the lengths are summed
+/// over all chunks, and the checksum is the one of the chunk manifest.
+#[derive(Clone, Debug, Eq, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Deserialize, serde::Serialize))]
+pub struct BlobStat {
+    /// Length of the key
+    pub key_len: u16,
+
+    /// Length of the value as stored on disk (possibly compressed)
+    pub disk_len: u64,
+
+    /// Length of the value after decompression
+    ///
+    /// `None` if the blob was written in the V1 format, which does not store it,
+    /// and the value log uses compression.
+    pub uncompressed_len: Option<u64>,
+
+    /// Checksum over the key and the stored value
+    pub checksum: u64,
+}
@@ -3,24 +3,67 @@
 // (found in the LICENSE-* files in the repository)
 
 use crate::{
+    backup::BackupManifest,
     gc::report::GcReport,
     id::{IdGenerator, SegmentId},
     index::Writer as IndexWriter,
-    manifest::{SegmentManifest, SEGMENTS_FOLDER, VLOG_MARKER},
+    integrity::{verify_segment_file, IntegrityReport},
+    lock::DirectoryLock,
+    manifest::{write_marker, SegmentManifest, SegmentMap, SEGMENTS_FOLDER, VLOG_MARKER},
     path::absolute_path,
+    prefetch::{Prefetch, DEFAULT_READAHEAD},
+    repair::RepairReport,
     scanner::{Scanner, SizeMap},
-    segment::merge::MergeReader,
+    segment::{
+        chunked::{broken_chunks, check_chunk, ChunkManifest},
+        header::{compute_checksum, BlobHeader, BlobKind, CompressionType},
+        merge::MergeReader,
+        pread::PositionalReader,
+        reader::{compressor_for, decompress},
+        Segment,
+    },
+    snapshot::Snapshot,
+    stat::BlobStat,
+    value_reader::{ValueChunk, ValueReader},
     version::Version,
     BlobCache, Compressor, Config, FDCache, GcStrategy, IndexReader, SegmentReader, SegmentWriter,
-    UserValue, ValueHandle,
+    Slice, UserKey, UserValue, ValueHandle,
 };
 use std::{
     fs::File,
-    io::{BufReader, Seek},
+    io::{BufReader, Read, Seek},
     path::{Path, PathBuf},
     sync::{atomic::AtomicU64, Arc, Mutex},
 };
 
+/// Location of a blob, whose value has not been read yet
+struct BlobLocation<C: Compressor + Clone> {
+    segment: Arc<Segment<C>>,
+    file: Arc<File>,
+    header: BlobHeader,
+    key: UserKey,
+
+    /// Offset of the blob in its segment
+    offset: u64,
+
+    /// Offset of the value in the segment file
+    value_offset: u64,
+}
+
+/// Returns `len` bytes of the value, starting at `offset`, clamped to the value's end.
+fn slice_range(value: &UserValue, offset: u64, len: u64) -> UserValue {
+    let value_len = value.len() as u64;
+
+    // NOTE: Truncation is OK, because both are clamped to the value's length
+    #[allow(clippy::cast_possible_truncation)]
+    let (start, end) = (
+        offset.min(value_len) as usize,
+        offset.saturating_add(len).min(value_len) as usize,
+    );
+
+    value.slice(start..end)
+}
+
 /// Unique value log ID
 #[allow(clippy::module_name_repetitions)]
 pub type ValueLogId = u64;
@@ -31,12 +74,10 @@ pub fn get_next_vlog_id() -> ValueLogId {
     VLOG_ID_COUNTER.fetch_add(1, std::sync::atomic::Ordering::Relaxed)
 }
 
-fn unlink_blob_files(base_path: &Path, ids: &[SegmentId]) {
-    for id in ids {
-        let path = base_path.join(SEGMENTS_FOLDER).join(id.to_string());
-
-        if let Err(e) = std::fs::remove_file(&path) {
-            log::error!("Could not free blob file at {path:?}: {e:?}");
+fn unlink_blob_files(paths: &[PathBuf]) {
+    for path in paths {
+        if let Err(e) = std::fs::remove_file(path) {
+            log::error!("Could not free blob file at {}: {e:?}", path.display());
         }
     }
 }
@@ -63,6 +104,15 @@ pub struct ValueLogInner<BC: BlobCache, FDC: FDCache, C: Compressor + Clone> {
     /// Base folder
     pub path: PathBuf,
 
+    /// Disk format version
+    version: Version,
+
+    /// If `true`, the value log never modifies its directory
+    read_only: bool,
+
+    /// Exclusive lock on the value log directory, not taken in read-only mode
+    _lock: Option<DirectoryLock>,
+
     /// Value log configuration
     config: Config<BC, FDC, C>,
 
@@ -85,244 +135,1333 @@ pub struct ValueLogInner<BC: BlobCache, FDC: FDCache, C: Compressor + Clone> {
     pub rollover_guard: Mutex<()>,
 }
 
-impl<BC: BlobCache, C: Compressor + Clone, FDC: FDCache> ValueLog<BC, FDC, C> {
-    /// Creates or recovers a value log in the given directory.
-    ///
-    /// # Errors
-    ///
-    /// Will return `Err` if an IO error occurs.
-    pub fn open<P: Into<PathBuf>>(
-        path: P, // TODO: move path into config?
-        config: Config<BC, FDC, C>,
-    ) -> crate::Result<Self> {
-        let path = path.into();
+impl<BC: BlobCache, FDC: FDCache, C: Compressor + Clone> Drop for ValueLogInner<BC, FDC, C> {
+    fn drop(&mut self) {
+        // NOTE: GC stats that could not be persisted after marking
+        // blobs as stale are retried here
+        if !self.read_only && self.manifest.gc_stats_dirty() {
+            let _lock = self
+                .rollover_guard
+                .lock()
+                .unwrap_or_else(std::sync::PoisonError::into_inner);
+
+            if let Err(e) = self.manifest.persist_gc_stats() {
+                log::error!("Could not persist vLog GC stats: {e:?}");
+            }
+        }
+
+        // NOTE: Caches may be shared across value logs, so they would
+        // otherwise hold on to the entries of this value log forever
+        self.blob_cache.invalidate_vlog(self.id);
+        self.fd_cache.invalidate_vlog(self.id);
+    }
+}
+
+impl<BC: BlobCache, C: Compressor + Clone, FDC: FDCache> ValueLog<BC, FDC, C> {
+    /// Creates or recovers a value log in the given directory.
+    ///
+    /// The directory is locked exclusively until the value log is dropped.
+    ///
+    /// # Errors
+    ///
+    /// Will return `Err` if an IO error occurs, or [`crate::Error::Locked`]
+    /// if the directory is already opened by another value log.
+    pub fn open<P: Into<PathBuf>>(
+        path: P, // TODO: move path into config?
+        config: Config<BC, FDC, C>,
+    ) -> crate::Result<Self> {
+        let path = path.into();
+
+        if path.join(VLOG_MARKER).try_exists()? {
+            Self::recover(path, config, false)
+        } else {
+            Self::create_new(path, config)
+        }
+    }
+
+    /// Recovers an existing value log in read-only mode.
+    ///
+    /// The directory is never modified: unfinished segments are not deleted,
+    /// and GC stats are only kept in memory.
+    /// Operations that would modify the value log
+    /// (e.g. [`ValueLog::get_writer`], [`ValueLog::clear`] or garbage collection)
+    /// return [`crate::Error::ReadOnly`].
+    ///
+    /// This can be used to inspect a backup, or a value log that is being
+    /// written to by another process.
+    ///
+    /// # Errors
+    ///
+    /// Will return `Err` if an IO error occurs, or the value log does not exist.
+    pub fn open_read_only<P: Into<PathBuf>>(
+        path: P,
+        config: Config<BC, FDC, C>,
+    ) -> crate::Result<Self> {
+        Self::recover(path, config, true)
+    }
+
+    /// Returns `true` if the value log was opened in read-only mode.
+    #[must_use]
+    pub fn is_read_only(&self) -> bool {
+        self.read_only
+    }
+
+    fn check_writable(&self) -> crate::Result<()> {
+        if self.read_only {
+            Err(crate::Error::ReadOnly)
+        } else {
+            Ok(())
+        }
+    }
+
+    /// Repairs a value log that cannot be opened anymore, e.g. because
+    /// a segment is truncated, its trailer is corrupt, or it is missing.
+    ///
+    /// All live blobs that are still readable are salvaged from damaged segments
+    /// and rewritten into new segments, which are added to the index.
+    /// Damaged segments are then removed, and the manifest is rewritten,
+    /// so the value log can be opened again.
+    ///
+    /// The value log must not be opened while it is being repaired,
+    /// which is enforced by locking its directory.
+    ///
+    /// Returns a report of what was repaired, including the keys that were lost.
+    ///
+    /// # Errors
+    ///
+    /// Will return `Err` if an IO error occurs.
+    pub fn repair<P: AsRef<Path>, R: IndexReader, W: IndexWriter>(
+        path: P,
+        config: &Config<BC, FDC, C>,
+        index_reader: &R,
+        index_writer: W,
+    ) -> crate::Result<RepairReport> {
+        let path = absolute_path(path.as_ref());
+        crate::repair::repair(&path, config, index_reader, index_writer)
+    }
+
+    /* /// Prints fragmentation histogram.
+    pub fn print_fragmentation_histogram(&self) {
+        let lock = self.manifest.segments.read().expect("lock is poisoned");
+
+        for (id, segment) in &*lock {
+            let stale_ratio = segment.stale_ratio();
+
+            let progress = (stale_ratio * 10.0) as usize;
+            let void = 10 - progress;
+
+            let progress = "=".repeat(progress);
+            let void = " ".repeat(void);
+
+            println!(
+                "{id:0>4} [{progress}{void}] {}%",
+                (stale_ratio * 100.0) as usize
+            );
+        }
+    } */
+
+    /// Verifies the integrity of every segment in the value log.
+    ///
+    /// Each segment is walked individually (including blobs that are shadowed
+    /// by newer versions), checking blob headers, checksums, the segment trailer
+    /// and the segment metadata against what was actually read.
+    ///
+    /// Corruptions do not cause an error, instead they are listed in the returned report.
+    ///
+    /// # Errors
+    ///
+    /// Will return `Err` if an IO error occurs.
+    pub fn verify_integrity(&self) -> crate::Result<IntegrityReport> {
+        let _lock = self.rollover_guard.lock().expect("lock is poisoned");
+
+        let mut report = IntegrityReport::default();
+
+        let mut segments = self.manifest.list_segments();
+        segments.sort_by_key(|x| x.id);
+
+        for segment in &segments {
+            verify_segment_file(
+                segment.id,
+                &segment.path,
+                self.config.compression.as_ref(),
+                &mut report,
+                |_| {},
+            );
+            report.segment_count += 1;
+        }
+
+        Ok(report)
+    }
+
+    /// Creates a point-in-time checkpoint of the value log in a new directory.
+    ///
+    /// Every registered segment is hard linked into the target directory,
+    /// so the checkpoint takes up no additional disk space (until the segments are
+    /// dropped from the value log), and a consistent manifest and version marker
+    /// are written next to them. The checkpoint can be opened as a value log right away.
+    ///
+    /// The target directory must not exist yet, and needs to be on the same file system
+    /// as the value log. If creating the checkpoint fails, it is removed again.
+    ///
+    /// Note that the checkpoint is only consistent with an index that was
+    /// checkpointed at the same time.
+    ///
+    /// # Errors
+    ///
+    /// Will return `Err` if an IO error occurs, or the target directory already exists.
+    pub fn checkpoint<P: AsRef<Path>>(&self, target: P) -> crate::Result<()> {
+        let target = absolute_path(target.as_ref());
+
+        // IMPORTANT: No segments may be registered or dropped while linking
+        let _lock = self.rollover_guard.lock().expect("lock is poisoned");
+
+        log::info!(
+            "Creating checkpoint of vLog at {} in {}",
+            self.path.display(),
+            target.display(),
+        );
+
+        if target.try_exists()? {
+            return Err(crate::Error::Io(std::io::Error::new(
+                std::io::ErrorKind::AlreadyExists,
+                format!("checkpoint target {} already exists", target.display()),
+            )));
+        }
+
+        let segments = self.manifest.snapshot();
+
+        if let Err(e) = self.checkpoint_into(&segments, &target) {
+            if let Err(e) = std::fs::remove_dir_all(&target) {
+                log::warn!(
+                    "Could not clean up failed checkpoint at {}: {e:?}",
+                    target.display()
+                );
+            }
+            return Err(e);
+        }
+
+        log::info!("Created checkpoint with {} vLog segments", segments.len());
+
+        Ok(())
+    }
+
+    fn checkpoint_into(&self, segments: &SegmentMap<C>, target: &Path) -> crate::Result<()> {
+        let segments_folder = target.join(SEGMENTS_FOLDER);
+        std::fs::create_dir_all(&segments_folder)?;
+
+        for segment in segments.values() {
+            let link_path = segments_folder.join(segment.id.to_string());
+            log::trace!(
+                "Linking vLog segment {} to {}",
+                segment.id,
+                link_path.display()
+            );
+            std::fs::hard_link(&segment.path, link_path)?;
+        }
+
+        SegmentManifest::write_checkpoint(target, segments, self.version == Version::V2)?;
+
+        // NOTE: Lastly, write .vlog marker, which contains the version
+        // -> the checkpoint is fully initialized
+        write_marker(target, self.version)
+    }
+
+    /// Backs up the value log into a directory.
+    ///
+    /// Because segments are immutable, only segments that are not part of the
+    /// previous backup are copied. Segments that were dropped since the previous
+    /// backup are recorded in the returned manifest, which is also written into
+    /// the backup directory. Use [`BackupManifest::restore`] to restore the backup.
+    ///
+    /// The backup reads from a [`Snapshot`], so it does not block garbage collection.
+    ///
+    /// Note that the backup is only consistent with an index that was
+    /// backed up at the same time.
+    ///
+    /// # Errors
+    ///
+    /// Will return `Err` if an IO error occurs.
+    pub fn backup<P: AsRef<Path>>(
+        &self,
+        target: P,
+        previous: Option<&BackupManifest>,
+    ) -> crate::Result<BackupManifest> {
+        let target = absolute_path(target.as_ref());
+
+        log::info!(
+            "Backing up vLog at {} to {}",
+            self.path.display(),
+            target.display(),
+        );
+
+        let segments = self.manifest.snapshot();
+        let manifest = crate::backup::backup(self.version, &segments, &target, previous)?;
+
+        log::info!(
+            "Backed up {} vLog segments, copied {}",
+            manifest.segments.len(),
+            manifest.added.len(),
+        );
+
+        Ok(manifest)
+    }
+
+    #[doc(hidden)]
+    pub fn verify(&self) -> crate::Result<usize> {
+        let _lock = self.rollover_guard.lock().expect("lock is poisoned");
+
+        let mut sum = 0;
+
+        // NOTE: Checksums are computed over the values as stored on disk
+        let mut reader = self.get_reader()?;
+
+        while let Some(item) = reader.next_raw() {
+            let (header, k, v, _) = item?;
+
+            if compute_checksum(&k, &v) != header.checksum {
+                sum += 1;
+            }
+        }
+
+        Ok(sum)
+    }
+
+    /// Creates a new empty value log in a directory.
+    pub(crate) fn create_new<P: Into<PathBuf>>(
+        path: P,
+        config: Config<BC, FDC, C>,
+    ) -> crate::Result<Self> {
+        let path = absolute_path(path.into());
+        log::trace!("Creating value-log at {}", path.display());
+
+        std::fs::create_dir_all(&path)?;
+
+        let lock = DirectoryLock::acquire(&path)?;
+
+        let marker_path = path.join(VLOG_MARKER);
+        assert!(!marker_path.try_exists()?);
+
+        std::fs::create_dir_all(path.join(SEGMENTS_FOLDER))?;
+
+        // NOTE: Lastly, fsync .vlog marker, which contains the version
+        // -> the V-log is fully initialized
+
+        let version = Version::V2;
+
+        let mut file = std::fs::File::create(marker_path)?;
+        version.write_file_header(&mut file)?;
+        file.sync_all()?;
+
+        #[cfg(not(target_os = "windows"))]
+        {
+            // fsync folders on Unix
+
+            let folder = std::fs::File::open(path.join(SEGMENTS_FOLDER))?;
+            folder.sync_all()?;
+
+            let folder = std::fs::File::open(&path)?;
+            folder.sync_all()?;
+        }
+
+        let blob_cache = config.blob_cache.clone();
+        let fd_cache = config.fd_cache.clone();
+        let manifest = SegmentManifest::create_new(&path, version == Version::V2)?;
+
+        Ok(Self(Arc::new(ValueLogInner {
+            id: get_next_vlog_id(),
+            config,
+            path,
+            version,
+            read_only: false,
+            _lock: Some(lock),
+            blob_cache,
+            fd_cache,
+            manifest,
+            id_generator: IdGenerator::default(),
+            rollover_guard: Mutex::new(()),
+        })))
+    }
+
+    pub(crate) fn recover<P: Into<PathBuf>>(
+        path: P,
+        config: Config<BC, FDC, C>,
+        read_only: bool,
+    ) -> crate::Result<Self> {
+        let path = path.into();
+        log::info!("Recovering vLog at {}", path.display());
+
+        // NOTE: Read-only value logs may be opened while another instance is writing
+        let lock = if read_only {
+            None
+        } else {
+            Some(DirectoryLock::acquire(&path)?)
+        };
+
+        let version = {
+            let bytes = std::fs::read(path.join(VLOG_MARKER))?;
+
+            // NOTE: V1 value logs keep writing V1 blobs, so they stay
+            // readable by older versions
+            match Version::parse_file_header(&bytes) {
+                Some(version @ (Version::V1 | Version::V2)) => version,
+                None => return Err(crate::Error::InvalidVersion(None)),
+            }
+        };
+
+        let blob_cache = config.blob_cache.clone();
+        let fd_cache = config.fd_cache.clone();
+        let manifest = SegmentManifest::recover(&path, read_only, version == Version::V2)?;
+
+        let highest_id = manifest
+            .segments
+            .read()
+            .expect("lock is poisoned")
+            .values()
+            .map(|x| x.id)
+            .max()
+            .unwrap_or_default();
+
+        Ok(Self(Arc::new(ValueLogInner {
+            id: get_next_vlog_id(),
+            config,
+            path,
+            version,
+            read_only,
+            _lock: lock,
+            blob_cache,
+            fd_cache,
+            manifest,
+            id_generator: IdGenerator::new(highest_id + 1),
+            rollover_guard: Mutex::new(()),
+        })))
+    }
+
+    /// Registers a [`SegmentWriter`].
+    ///
+    /// # Errors
+    ///
+    /// Will return `Err` if an IO error occurs, or the value log is read-only.
+    pub fn register_writer(&self, writer: SegmentWriter<C>) -> crate::Result<()> {
+        self.check_writable()?;
+
+        let _lock = self.rollover_guard.lock().expect("lock is poisoned");
+        self.manifest.register(writer)?;
+        Ok(())
+    }
+
+    /// Returns the amount of segments in the value log.
+    #[must_use]
+    pub fn segment_count(&self) -> usize {
+        self.manifest.len()
+    }
+
+    /// Resolves a value handle.
+    ///
+    /// # Errors
+    ///
+    /// Will return `Err` if an IO error occurs.
+    pub fn get(&self, vhandle: &ValueHandle) -> crate::Result<Option<UserValue>> {
+        self.get_with_prefetch(vhandle, Prefetch::None)
+    }
+
+    /// Resolves a value handle, and prefetches some values after it.
+    ///
+    /// Passing a number prefetches up to that amount of following blobs,
+    /// see [`Prefetch`] for other modes.
+    ///
+    /// # Errors
+    ///
+    /// Will return `Err` if an IO error occurs.
+    pub fn get_with_prefetch(
+        &self,
+        vhandle: &ValueHandle,
+        prefetch: impl Into<Prefetch>,
+    ) -> crate::Result<Option<UserValue>> {
+        self.get_with_readahead(vhandle, prefetch, DEFAULT_READAHEAD)
+    }
+
+    /// Resolves a value handle, and prefetches some values after it,
+    /// reading the segment file in chunks of `readahead` bytes.
+    ///
+    /// A large readahead reduces the amount of I/O operations for large
+    /// sequential scans, especially in combination with [`Prefetch::Buffered`].
+    ///
+    /// # Errors
+    ///
+    /// Will return `Err` if an IO error occurs.
+    pub fn get_with_readahead(
+        &self,
+        vhandle: &ValueHandle,
+        prefetch: impl Into<Prefetch>,
+        readahead: usize,
+    ) -> crate::Result<Option<UserValue>> {
+        self.get_with(vhandle, prefetch.into(), readahead, || {
+            self.manifest.get_segment(vhandle.segment_id)
+        })
+    }
+
+    /// Creates a snapshot of the value log's current segments.
+    ///
+    /// As long as the snapshot is alive, the segments it references are kept on disk,
+    /// even if they are dropped using [`ValueLog::drop_stale_segments`] or [`ValueLog::clear`].
+    /// So readers can take a snapshot before reading value handles from the index,
+    /// and resolve them through the snapshot, without racing against garbage collection.
+    #[must_use]
+    pub fn snapshot(&self) -> Snapshot<BC, FDC, C> {
+        Snapshot::new(self.clone(), self.manifest.snapshot())
+    }
+
+    /// Resolves a value handle, looking up its segment using the given function
+    /// if the value is not cached.
+    pub(crate) fn get_with(
+        &self,
+        vhandle: &ValueHandle,
+        prefetch: Prefetch,
+        readahead: usize,
+        segment: impl FnOnce() -> Option<Arc<Segment<C>>>,
+    ) -> crate::Result<Option<UserValue>> {
+        if let Some(value) = self.blob_cache.get(self.id, vhandle) {
+            return Ok(Some(value));
+        }
+
+        let Some(segment) = segment() else {
+            return Ok(None);
+        };
+
+        Ok(self
+            .read_entry(vhandle, prefetch, readahead, &segment)?
+            .map(|(_, value)| value))
+    }
+
+    /// Reads a blob from disk, and inserts it (and prefetched blobs) into the blob cache.
+    fn read_entry(
+        &self,
+        vhandle: &ValueHandle,
+        prefetch: Prefetch,
+        readahead: usize,
+        segment: &Segment<C>,
+    ) -> crate::Result<Option<(UserKey, UserValue)>> {
+        #[cfg(feature = "mmap")]
+        if self.config.use_mmap {
+            return self.get_mapped(vhandle, prefetch, readahead, segment);
+        }
+
+        let mut reader = self.segment_reader(segment, vhandle.offset, readahead)?;
+
+        let Some((key, val)) = self.read_blob(&mut reader, vhandle.offset)? else {
+            return Ok(None);
+        };
+
+        self.blob_cache.insert(self.id, vhandle, val.clone());
+
+        let mut count = 0;
+        let mut bytes = 0;
+
+        while prefetch.allows(count, bytes) {
+            if prefetch == Prefetch::Buffered && !reader.next_is_buffered() {
+                break;
+            }
+
+            let offset = reader.get_offset()?;
+
+            let Some(val) = self.read_prefetched_blob(&mut reader, offset)? else {
+                break;
+            };
+
+            let value_handle = ValueHandle {
+                segment_id: vhandle.segment_id,
+                offset,
+            };
+
+            self.blob_cache.insert(self.id, &value_handle, val);
+
+            count += 1;
+            bytes += reader.get_offset()? - offset;
+        }
+
+        Ok(Some((key, val)))
+    }
+
+    /// Resolves a value handle, returning the blob's key alongside its value.
+    ///
+    /// The blob cache only stores values, so for cached values,
+    /// the key is read from the blob's header.
+    ///
+    /// # Errors
+    ///
+    /// Will return `Err` if an IO error occurs.
+    pub fn get_entry(&self, vhandle: &ValueHandle) -> crate::Result<Option<(UserKey, UserValue)>> {
+        self.get_entry_with(vhandle, |id| self.manifest.get_segment(id))
+    }
+
+    /// Resolves a value handle with its key, looking up segments using the given function.
+    pub(crate) fn get_entry_with(
+        &self,
+        vhandle: &ValueHandle,
+        segment: impl Fn(SegmentId) -> Option<Arc<Segment<C>>>,
+    ) -> crate::Result<Option<(UserKey, UserValue)>> {
+        if let Some(value) = self.blob_cache.get(self.id, vhandle) {
+            return Ok(self
+                .locate_blob(vhandle, &segment)?
+                .map(|blob| (blob.key, value)));
+        }
+
+        let Some(segment) = segment(vhandle.segment_id) else {
+            return Ok(None);
+        };
+
+        self.read_entry(vhandle, Prefetch::None, DEFAULT_READAHEAD, &segment)
+    }
+
+    /// Resolves a value handle, checking that the blob belongs to the expected key.
+    ///
+    /// This protects against reading another key's value through a wrong or stale value handle.
+    ///
+    /// # Errors
+    ///
+    /// Will return `Err` if an IO error occurs,
+    /// or [`crate::Error::KeyMismatch`] if the blob belongs to another key.
+    pub fn get_checked(
+        &self,
+        vhandle: &ValueHandle,
+        expected_key: &[u8],
+    ) -> crate::Result<Option<UserValue>> {
+        self.get_checked_with(vhandle, expected_key, |id| self.manifest.get_segment(id))
+    }
+
+    /// Resolves a value handle checking its key, looking up segments using the given function.
+    pub(crate) fn get_checked_with(
+        &self,
+        vhandle: &ValueHandle,
+        expected_key: &[u8],
+        segment: impl Fn(SegmentId) -> Option<Arc<Segment<C>>>,
+    ) -> crate::Result<Option<UserValue>> {
+        let Some((key, value)) = self.get_entry_with(vhandle, segment)? else {
+            return Ok(None);
+        };
+
+        if &*key != expected_key {
+            log::error!(
+                "Key mismatch for blob in segment {} at offset {}",
+                vhandle.segment_id,
+                vhandle.offset,
+            );
+
+            return Err(crate::Error::KeyMismatch {
+                segment_id: vhandle.segment_id,
+                offset: vhandle.offset,
+            });
+        }
+
+        Ok(Some(value))
+    }
+
+    /// Opens a reader over the value of a blob, without reading the value into memory.
+    ///
+    /// This allows streaming large values, see [`ValueReader`].
+    /// Values that are split into chunks are streamed chunk by chunk.
+    /// The blob cache is not used.
+    ///
+    /// # Errors
+    ///
+    /// Will return `Err` if an IO error occurs,
+    /// or [`crate::Error::CompressedValue`] if the value is compressed.
+    pub fn open_value(&self, vhandle: &ValueHandle) -> crate::Result<Option<ValueReader<C>>> {
+        self.open_value_with(vhandle, |id| self.manifest.get_segment(id))
+    }
+
+    /// Opens a reader over the value of a blob, looking up segments using the given function.
+    pub(crate) fn open_value_with(
+        &self,
+        vhandle: &ValueHandle,
+        segment: impl Fn(SegmentId) -> Option<Arc<Segment<C>>>,
+    ) -> crate::Result<Option<ValueReader<C>>> {
+        let Some(blob) = self.locate_blob(vhandle, segment)? else {
+            return Ok(None);
+        };
+
+        let chunks = self.locate_chunks(&blob)?;
+
+        if chunks.iter().any(|(header, _)| self.is_compressed(header)) {
+            return Err(crate::Error::CompressedValue);
+        }
+
+        let value_reader = ValueReader::new(
+            blob.segment,
+            PositionalReader::new(blob.file, blob.value_offset),
+            chunks.into_iter().map(|(_, chunk)| chunk).collect(),
+        );
+
+        if self.config.verify_checksums {
+            Ok(Some(value_reader.verify_checksum(blob.key)))
+        } else {
+            Ok(Some(value_reader))
+        }
+    }
+
+    /// Reads `len` bytes of a value, starting at `offset` into the value.
+    ///
+    /// The returned value is shorter than `len` if the range exceeds the value's end,
+    /// and empty if `offset` is past the value's end.
+    ///
+    /// For uncompressed values, only the requested range is read from disk; its checksum
+    /// cannot be verified, because that requires reading the entire value.
+    /// Compressed values are read entirely, but only decompressed as far as
+    /// needed, if the compressor supports it (see [`Compressor::decompress_prefix`]).
+    ///
+    /// Partial values are never inserted into the blob cache, but if the entire value
+    /// is cached, the range is taken from the cache.
+    ///
+    /// # Errors
+    ///
+    /// Will return `Err` if an IO error occurs.
+    pub fn get_range(
+        &self,
+        vhandle: &ValueHandle,
+        offset: u64,
+        len: u64,
+    ) -> crate::Result<Option<UserValue>> {
+        self.get_range_with(vhandle, offset, len, |id| self.manifest.get_segment(id))
+    }
+
+    /// Reads a byte range of a value, looking up segments using the given function.
+    pub(crate) fn get_range_with(
+        &self,
+        vhandle: &ValueHandle,
+        offset: u64,
+        len: u64,
+        segment: impl Fn(SegmentId) -> Option<Arc<Segment<C>>>,
+    ) -> crate::Result<Option<UserValue>> {
+        if let Some(value) = self.blob_cache.get(self.id, vhandle) {
+            return Ok(Some(slice_range(&value, offset, len)));
+        }
+
+        let Some(blob) = self.locate_blob(vhandle, segment)? else {
+            return Ok(None);
+        };
+
+        if blob.header.kind == BlobKind::ChunkManifest {
+            return self.get_chunked_range(&blob, offset, len).map(Some);
+        }
+
+        let value_len = u64::from(blob.header.value_len);
+
+        let Some(compressor) = self.compressor_for(&blob.header)? else {
+            let start = offset.min(value_len);
+            let end = offset.saturating_add(len).min(value_len);
+
+            let mut reader = PositionalReader::new(blob.file, blob.value_offset + start);
+
+            // NOTE: Truncation is OK because we know values are u32 max
+            #[allow(clippy::cast_possible_truncation)]
+            let value = Slice::from_reader(&mut reader, (end - start) as usize)?;
+
+            return Ok(Some(value));
+        };
+
+        let mut reader = PositionalReader::new(blob.file, blob.value_offset);
+
+        // NOTE: Truncation is OK because we know values are u32 max
+        #[allow(clippy::cast_possible_truncation)]
+        let value = Slice::from_reader(&mut reader, value_len as usize)?;
+
+        self.verify_blob(
+            vhandle.segment_id,
+            vhandle.offset,
+            &blob.header,
+            &blob.key,
+            &value,
+        )?;
+
+        // NOTE: Truncation is OK, the result is clamped to the value's length anyway
+        #[allow(clippy::cast_possible_truncation)]
+        let prefix_len = offset.saturating_add(len).min(usize::MAX as u64) as usize;
+
+        let value = Slice::from(compressor.decompress_prefix(&value, prefix_len)?);
+
+        Ok(Some(slice_range(&value, offset, len)))
+    }
+
+    /// Reads a byte range of a large value, only reading the chunks that overlap the range.
+    fn get_chunked_range(
+        &self,
+        blob: &BlobLocation<C>,
+        offset: u64,
+        len: u64,
+    ) -> crate::Result<UserValue> {
+        let end = offset.saturating_add(len);
+        let mut value = vec![];
+
+        for (header, chunk) in self.locate_chunks(blob)? {
+            if chunk.start >= end {
+                break;
+            }
+            if chunk.start + chunk.len <= offset {
+                continue;
+            }
+
+            // NOTE: Truncation is OK because chunks are u32 max
+            #[allow(clippy::cast_possible_truncation)]
+            let (start, stop) = (
+                offset.saturating_sub(chunk.start) as usize,
+                (end - chunk.start).min(chunk.len) as usize,
+            );
+
+            if let Some(compressor) = self.compressor_for(&header)? {
+                let mut reader = PositionalReader::new(blob.file.clone(), chunk.value_offset);
+                let stored = Slice::from_reader(&mut reader, header.value_len as usize)?;
+
+                self.verify_blob(blob.segment.id, chunk.offset, &header, &blob.key, &stored)?;
+
+                let data = compressor.decompress_prefix(&stored, stop)?;
+                let stop = stop.min(data.len());
+                value.extend_from_slice(data.get(start..stop).unwrap_or_default());
+            } else {
+                let mut reader =
+                    PositionalReader::new(blob.file.clone(), chunk.value_offset + start as u64);
+                value.extend_from_slice(&Slice::from_reader(&mut reader, stop - start)?);
+            }
+        }
+
+        Ok(Slice::from(value))
+    }
+
+    /// Returns the metadata of a blob, reading only its header.
+    ///
+    /// For large values, the headers of all chunks are read.
+    ///
+    /// # Errors
+    ///
+    /// Will return `Err` if an IO error occurs.
+    pub fn stat(&self, vhandle: &ValueHandle) -> crate::Result<Option<BlobStat>> {
+        self.stat_with(vhandle, |id| self.manifest.get_segment(id))
+    }
+
+    /// Returns the metadata of a blob, looking up segments using the given function.
+    pub(crate) fn stat_with(
+        &self,
+        vhandle: &ValueHandle,
+        segment: impl Fn(SegmentId) -> Option<Arc<Segment<C>>>,
+    ) -> crate::Result<Option<BlobStat>> {
+        let Some(blob) = self.locate_blob(vhandle, segment)? else {
+            return Ok(None);
+        };
+
+        if blob.header.kind == BlobKind::ChunkManifest {
+            let chunks = self.locate_chunks(&blob)?;
+
+            return Ok(Some(BlobStat {
+                key_len: blob.header.key_len,
+                disk_len: chunks
+                    .iter()
+                    .map(|(header, _)| u64::from(header.value_len))
+                    .sum(),
+                uncompressed_len: Some(chunks.iter().map(|(_, chunk)| chunk.len).sum()),
+                checksum: blob.header.checksum,
+            }));
+        }
+
+        let header = blob.header;
+
+        let uncompressed_len = match header.uncompressed_len {
+            Some(len) => Some(len.into()),
+
+            // NOTE: V1 blobs do not store their uncompressed length,
+            // but it is known if they are not compressed
+            None if !self.is_compressed(&header) => Some(header.value_len.into()),
+            None => None,
+        };
+
+        Ok(Some(BlobStat {
+            key_len: header.key_len,
+            disk_len: header.value_len.into(),
+            uncompressed_len,
+            checksum: header.checksum,
+        }))
+    }
+
+    /// Reads the header and key of a blob, without reading its value.
+    fn locate_blob(
+        &self,
+        vhandle: &ValueHandle,
+        segment: impl Fn(SegmentId) -> Option<Arc<Segment<C>>>,
+    ) -> crate::Result<Option<BlobLocation<C>>> {
+        let Some(segment) = segment(vhandle.segment_id) else {
+            return Ok(None);
+        };
+
+        let file = self.segment_file(&segment)?;
+
+        let mut reader = BufReader::new(PositionalReader::new(file.clone(), vhandle.offset));
+        let Some((header, key)) = BlobHeader::decode_with_key(&mut reader)? else {
+            return Ok(None);
+        };
+
+        let value_offset =
+            vhandle.offset + BlobHeader::header_len(header.version) + u64::from(header.key_len);
+
+        Ok(Some(BlobLocation {
+            segment,
+            file,
+            header,
+            key,
+            offset: vhandle.offset,
+            value_offset,
+        }))
+    }
+
+    /// Locates the chunks of a blob's value, reading only their headers.
+    ///
+    /// Values that are not split into chunks consist of a single chunk.
+    fn locate_chunks(
+        &self,
+        blob: &BlobLocation<C>,
+    ) -> crate::Result<Vec<(BlobHeader, ValueChunk)>> {
+        let header = &blob.header;
+
+        if header.kind != BlobKind::ChunkManifest {
+            let chunk = ValueChunk {
+                offset: blob.offset,
+                value_offset: blob.value_offset,
+                start: 0,
+                len: header.uncompressed_len.unwrap_or(header.value_len).into(),
+                checksum: header.checksum,
+            };
+
+            return Ok(vec![(header.clone(), chunk)]);
+        }
+
+        let manifest = self.read_chunk_manifest(blob)?;
+
+        let mut chunks = vec![];
+        let mut offset = blob.value_offset + u64::from(header.value_len);
+        let mut start = 0;
+
+        for _ in 0..manifest.chunk_count {
+            let mut reader = BufReader::new(PositionalReader::new(blob.file.clone(), offset));
+
+            let Some((header, key)) = BlobHeader::decode_with_key(&mut reader)? else {
+                return Err(broken_chunks());
+            };
+            check_chunk(&header, &key, &blob.key)?;
+
+            let chunk = ValueChunk {
+                offset,
+                value_offset: offset
+                    + BlobHeader::header_len(header.version)
+                    + u64::from(header.key_len),
+                start,
+                len: header.uncompressed_len.unwrap_or(header.value_len).into(),
+                checksum: header.checksum,
+            };
+
+            offset += header.blob_len();
+            start += chunk.len;
+
+            chunks.push((header, chunk));
+        }
+
+        Ok(chunks)
+    }
+
+    /// Reads the chunk manifest of a large value.
+    fn read_chunk_manifest(&self, blob: &BlobLocation<C>) -> crate::Result<ChunkManifest> {
+        let header = &blob.header;
+
+        let mut reader = PositionalReader::new(blob.file.clone(), blob.value_offset);
+        let manifest = Slice::from_reader(&mut reader, header.value_len as usize)?;
+
+        self.verify_blob(blob.segment.id, blob.offset, header, &blob.key, &manifest)?;
+
+        ChunkManifest::from_value(&manifest)
+    }
+
+    /// Returns the length of a value, given the size that was stored in the index.
+    ///
+    /// Index sizes saturate at `u32::MAX`, so the real length of larger values
+    /// is read from their chunk manifest.
+    fn value_len(&self, vhandle: &ValueHandle, size: u32) -> crate::Result<u64> {
+        if size < u32::MAX {
+            return Ok(size.into());
+        }
+
+        let Some(blob) = self.locate_blob(vhandle, |id| self.manifest.get_segment(id))? else {
+            return Ok(size.into());
+        };
+
+        if blob.header.kind != BlobKind::ChunkManifest {
+            return Ok(size.into());
+        }
+
+        Ok(self.read_chunk_manifest(&blob)?.len)
+    }
+
+    /// Returns the compressor needed to decompress the blob's value,
+    /// or `None` if the value is stored uncompressed.
+    ///
+    /// # Errors
+    ///
+    /// Returns error if the value is compressed, but no compressor is configured.
+    fn compressor_for(&self, header: &BlobHeader) -> crate::Result<Option<&C>> {
+        compressor_for(self.config.compression.as_ref(), header)
+    }
+
+    /// Returns `true` if the blob's value is stored compressed.
+    fn is_compressed(&self, header: &BlobHeader) -> bool {
+        // NOTE: V1 blobs do not store their compression type,
+        // so we need to trust the configured compression
+        header.compression.map_or_else(
+            || self.config.compression.is_some(),
+            |compression| compression != CompressionType::None,
+        )
+    }
+
+    /// Resolves many value handles at once.
+    ///
+    /// Handles are grouped by segment and read in offset order, so adjacent blobs
+    /// are read using a single buffered reader, instead of one I/O per blob.
+    /// Cached values are not read again.
+    ///
+    /// The values are returned in the same order as the given handles.
+    ///
+    /// # Errors
+    ///
+    /// Will return `Err` if an IO error occurs.
+    pub fn multi_get(&self, vhandles: &[ValueHandle]) -> crate::Result<Vec<Option<UserValue>>> {
+        self.multi_get_with(vhandles, |id| self.manifest.get_segment(id))
+    }
+
+    /// Resolves many value handles at once, looking up segments using the given function.
+    pub(crate) fn multi_get_with(
+        &self,
+        vhandles: &[ValueHandle],
+        segment: impl Fn(SegmentId) -> Option<Arc<Segment<C>>>,
+    ) -> crate::Result<Vec<Option<UserValue>>> {
+        let mut values = Vec::with_capacity(vhandles.len());
+        let mut misses = vec![];
+
+        for (idx, vhandle) in vhandles.iter().enumerate() {
+            let value = self.blob_cache.get(self.id, vhandle);
+
+            if value.is_none() {
+                misses.push((vhandle, idx));
+            }
+
+            values.push(value);
+        }
+
+        misses.sort_by_key(|(vhandle, _)| (vhandle.segment_id, vhandle.offset));
+
+        let mut rest = misses.as_slice();
+
+        while let Some(((first, _), _)) = rest.split_first() {
+            let group_len = rest
+                .iter()
+                .take_while(|(vhandle, _)| vhandle.segment_id == first.segment_id)
+                .count();
+            let (group, tail) = rest.split_at(group_len);
+            rest = tail;
 
-        if path.join(VLOG_MARKER).try_exists()? {
-            Self::recover(path, config)
-        } else {
-            Self::create_new(path, config)
+            let Some(segment) = segment(first.segment_id) else {
+                continue;
+            };
+
+            self.multi_get_segment(&segment, group, &mut values)?;
         }
+
+        Ok(values)
     }
 
-    /* /// Prints fragmentation histogram.
-    pub fn print_fragmentation_histogram(&self) {
-        let lock = self.manifest.segments.read().expect("lock is poisoned");
+    /// Reads the given handles of a single segment, which need to be sorted by offset.
+    fn multi_get_segment(
+        &self,
+        segment: &Segment<C>,
+        vhandles: &[(&ValueHandle, usize)],
+        values: &mut [Option<UserValue>],
+    ) -> crate::Result<()> {
+        let mut reader = None;
+        let mut prev: Option<(&ValueHandle, Option<UserValue>)> = None;
+
+        for &(vhandle, idx) in vhandles {
+            let value = match &prev {
+                // NOTE: The same handle may be requested multiple times
+                Some((prev_handle, value)) if *prev_handle == vhandle => value.clone(),
+                _ => self.multi_get_blob(segment, vhandle, &mut reader)?,
+            };
 
-        for (id, segment) in &*lock {
-            let stale_ratio = segment.stale_ratio();
+            if let Some(slot) = values.get_mut(idx) {
+                slot.clone_from(&value);
+            }
 
-            let progress = (stale_ratio * 10.0) as usize;
-            let void = 10 - progress;
+            prev = Some((vhandle, value));
+        }
 
-            let progress = "=".repeat(progress);
-            let void = " ".repeat(void);
+        Ok(())
+    }
 
-            println!(
-                "{id:0>4} [{progress}{void}] {}%",
-                (stale_ratio * 100.0) as usize
-            );
+    /// Reads a single blob of a batch, reusing the reader if the blob directly
+    /// follows the previously read blob.
+    fn multi_get_blob(
+        &self,
+        segment: &Segment<C>,
+        vhandle: &ValueHandle,
+        reader: &mut Option<SegmentReader<C, BufReader<PositionalReader>>>,
+    ) -> crate::Result<Option<UserValue>> {
+        #[cfg(feature = "mmap")]
+        if self.config.use_mmap {
+            return Ok(self
+                .get_mapped(vhandle, Prefetch::None, DEFAULT_READAHEAD, segment)?
+                .map(|(_, value)| value));
         }
-    } */
 
-    #[doc(hidden)]
-    pub fn verify(&self) -> crate::Result<usize> {
-        let _lock = self.rollover_guard.lock().expect("lock is poisoned");
+        let adjacent = match reader {
+            Some(reader) => reader.get_offset()? == vhandle.offset,
+            None => false,
+        };
 
-        let mut sum = 0;
+        if !adjacent {
+            *reader = Some(self.segment_reader(segment, vhandle.offset, DEFAULT_READAHEAD)?);
+        }
 
-        for item in self.get_reader()? {
-            let (k, v, _, expected_checksum) = item?;
+        let Some(reader) = reader else {
+            return Ok(None);
+        };
 
-            let mut hasher = xxhash_rust::xxh3::Xxh3::new();
-            hasher.update(&k);
-            hasher.update(&v);
+        let value = self
+            .read_blob(reader, vhandle.offset)?
+            .map(|(_, value)| value);
 
-            if hasher.digest() != expected_checksum {
-                sum += 1;
-            }
+        if let Some(value) = &value {
+            self.blob_cache.insert(self.id, vhandle, value.clone());
         }
 
-        Ok(sum)
+        Ok(value)
     }
 
-    /// Creates a new empty value log in a directory.
-    pub(crate) fn create_new<P: Into<PathBuf>>(
-        path: P,
-        config: Config<BC, FDC, C>,
-    ) -> crate::Result<Self> {
-        let path = absolute_path(path.into());
-        log::trace!("Creating value-log at {}", path.display());
+    /// Returns the segment's file, opening it if it is not cached.
+    fn segment_file(&self, segment: &Segment<C>) -> crate::Result<Arc<File>> {
+        if let Some(file) = self.fd_cache.get(self.id, segment.id) {
+            return Ok(file);
+        }
 
-        std::fs::create_dir_all(&path)?;
+        let file = Arc::new(File::open(&segment.path)?);
 
-        let marker_path = path.join(VLOG_MARKER);
-        assert!(!marker_path.try_exists()?);
+        // NOTE: Dropped segments are not cached, so their files can be deleted
+        if !segment.is_marked_for_deletion() {
+            self.fd_cache.insert(self.id, segment.id, file.clone());
 
-        std::fs::create_dir_all(path.join(SEGMENTS_FOLDER))?;
+            // IMPORTANT: The segment may have been dropped right after the check above.
+            // Segments are marked before their files are evicted, so either the eviction
+            // sees our insert, or we see the mark here and evict the file ourselves
+            if segment.is_marked_for_deletion() {
+                self.fd_cache.invalidate_segment(self.id, segment.id);
+            }
+        }
 
-        // NOTE: Lastly, fsync .vlog marker, which contains the version
-        // -> the V-log is fully initialized
+        Ok(file)
+    }
 
-        let mut file = std::fs::File::create(marker_path)?;
-        Version::V1.write_file_header(&mut file)?;
-        file.sync_all()?;
+    /// Creates a reader that starts reading the segment at the given offset,
+    /// using a read buffer of `readahead` bytes.
+    fn segment_reader(
+        &self,
+        segment: &Segment<C>,
+        offset: u64,
+        readahead: usize,
+    ) -> crate::Result<SegmentReader<C, BufReader<PositionalReader>>> {
+        let file = self.segment_file(segment)?;
+
+        // NOTE: The file is read using positional reads, so it can be shared
+        // with other readers without seeking
+        let reader = BufReader::with_capacity(readahead, PositionalReader::new(file, offset));
+
+        Ok(SegmentReader::with_reader(segment.id, reader)
+            .use_compression(self.config.compression.clone()))
+    }
 
-        #[cfg(not(target_os = "windows"))]
-        {
-            // fsync folders on Unix
+    /// Reads the next blob from the segment reader, verifying its checksum if enabled.
+    fn read_blob<R: Read + Seek>(
+        &self,
+        reader: &mut SegmentReader<C, R>,
+        offset: u64,
+    ) -> crate::Result<Option<(UserKey, UserValue)>> {
+        let Some(item) = reader.next_raw() else {
+            return Ok(None);
+        };
+        let (header, key, value) = item?;
 
-            let folder = std::fs::File::open(path.join(SEGMENTS_FOLDER))?;
-            folder.sync_all()?;
+        self.verify_blob(reader.segment_id, offset, &header, &key, &value)?;
 
-            let folder = std::fs::File::open(&path)?;
-            folder.sync_all()?;
+        if header.kind == BlobKind::ChunkManifest {
+            let value = self.read_chunks(reader.segment_id, &key, &value, || {
+                let offset = reader.get_offset()?;
+                let item = reader.next_raw().transpose()?;
+                Ok(item.map(|(header, key, value)| (offset, header, key, value)))
+            })?;
+
+            return Ok(Some((key, value)));
         }
 
-        let blob_cache = config.blob_cache.clone();
-        let fd_cache = config.fd_cache.clone();
-        let manifest = SegmentManifest::create_new(&path)?;
+        let value = reader.decompress(&header, value)?;
 
-        Ok(Self(Arc::new(ValueLogInner {
-            id: get_next_vlog_id(),
-            config,
-            path,
-            blob_cache,
-            fd_cache,
-            manifest,
-            id_generator: IdGenerator::default(),
-            rollover_guard: Mutex::new(()),
-        })))
+        Ok(Some((key, value)))
     }
 
-    pub(crate) fn recover<P: Into<PathBuf>>(
-        path: P,
-        config: Config<BC, FDC, C>,
-    ) -> crate::Result<Self> {
-        let path = path.into();
-        log::info!("Recovering vLog at {}", path.display());
-
-        {
-            let bytes = std::fs::read(path.join(VLOG_MARKER))?;
+    /// Reads the next blob from the segment reader for prefetching.
+    ///
+    /// Returns `None` at the end of the segment, and for large values,
+    /// as prefetching them would read and cache all of their chunks.
+    fn read_prefetched_blob<R: Read + Seek>(
+        &self,
+        reader: &mut SegmentReader<C, R>,
+        offset: u64,
+    ) -> crate::Result<Option<UserValue>> {
+        let Some(item) = reader.next_raw() else {
+            return Ok(None);
+        };
+        let (header, key, value) = item?;
 
-            if let Some(version) = Version::parse_file_header(&bytes) {
-                if version != Version::V1 {
-                    return Err(crate::Error::InvalidVersion(Some(version)));
-                }
-            } else {
-                return Err(crate::Error::InvalidVersion(None));
-            }
+        if header.kind != BlobKind::Value {
+            return Ok(None);
         }
 
-        let blob_cache = config.blob_cache.clone();
-        let fd_cache = config.fd_cache.clone();
-        let manifest = SegmentManifest::recover(&path)?;
-
-        let highest_id = manifest
-            .segments
-            .read()
-            .expect("lock is poisoned")
-            .values()
-            .map(|x| x.id)
-            .max()
-            .unwrap_or_default();
+        self.verify_blob(reader.segment_id, offset, &header, &key, &value)?;
 
-        Ok(Self(Arc::new(ValueLogInner {
-            id: get_next_vlog_id(),
-            config,
-            path,
-            blob_cache,
-            fd_cache,
-            manifest,
-            id_generator: IdGenerator::new(highest_id + 1),
-            rollover_guard: Mutex::new(()),
-        })))
+        reader.decompress(&header, value).map(Some)
     }
 
-    /// Registers a [`SegmentWriter`].
+    /// Reads the chunks following a chunk manifest, and reassembles the large value.
     ///
-    /// # Errors
-    ///
-    /// Will return `Err` if an IO error occurs.
-    pub fn register_writer(&self, writer: SegmentWriter<C>) -> crate::Result<()> {
-        let _lock = self.rollover_guard.lock().expect("lock is poisoned");
-        self.manifest.register(writer)?;
-        Ok(())
-    }
+    /// `next_blob` returns the offset, header, key and stored value of the next blob.
+    fn read_chunks(
+        &self,
+        segment_id: SegmentId,
+        key: &[u8],
+        manifest: &[u8],
+        mut next_blob: impl FnMut() -> crate::Result<Option<(u64, BlobHeader, UserKey, Slice)>>,
+    ) -> crate::Result<UserValue> {
+        let manifest = ChunkManifest::from_value(manifest)?;
+
+        // IMPORTANT: The length is read from disk, so a corrupt manifest must not
+        // make us allocate an arbitrary amount of memory
+        let max_len = manifest.chunk_count.saturating_mul(u32::MAX.into());
+        let mut value = Vec::new();
+        usize::try_from(manifest.len)
+            .ok()
+            .filter(|_| manifest.len <= max_len)
+            .and_then(|len| value.try_reserve_exact(len).ok())
+            .ok_or_else(broken_chunks)?;
+
+        for _ in 0..manifest.chunk_count {
+            let Some((offset, header, chunk_key, chunk)) = next_blob()? else {
+                return Err(broken_chunks());
+            };
 
-    /// Returns the amount of segments in the value log.
-    #[must_use]
-    pub fn segment_count(&self) -> usize {
-        self.manifest.len()
+            check_chunk(&header, &chunk_key, key)?;
+            self.verify_blob(segment_id, offset, &header, &chunk_key, &chunk)?;
+
+            let chunk = decompress(self.config.compression.as_ref(), &header, chunk)?;
+            value.extend_from_slice(&chunk);
+        }
+
+        if value.len() as u64 != manifest.len {
+            return Err(broken_chunks());
+        }
+
+        Ok(Slice::from(value))
     }
 
-    /// Resolves a value handle.
-    ///
-    /// # Errors
-    ///
-    /// Will return `Err` if an IO error occurs.
-    pub fn get(&self, vhandle: &ValueHandle) -> crate::Result<Option<UserValue>> {
-        self.get_with_prefetch(vhandle, 0)
+    /// Verifies the checksum of a blob, if enabled.
+    fn verify_blob(
+        &self,
+        segment_id: SegmentId,
+        offset: u64,
+        header: &BlobHeader,
+        key: &[u8],
+        value: &[u8],
+    ) -> crate::Result<()> {
+        if self.config.verify_checksums && compute_checksum(key, value) != header.checksum {
+            log::error!("Checksum mismatch for blob in segment {segment_id} at offset {offset}");
+
+            return Err(crate::Error::ChecksumMismatch { segment_id, offset });
+        }
+
+        Ok(())
     }
 
-    /// Resolves a value handle, and prefetches some values after it.
-    ///
-    /// # Errors
+    /// Resolves a value handle by parsing the blob directly from the segment's memory mapping.
     ///
-    /// Will return `Err` if an IO error occurs.
-    pub fn get_with_prefetch(
+    /// There is no read buffer, so [`Prefetch::Buffered`] prefetches
+    /// the blobs within `readahead` bytes of the requested blob.
+    #[cfg(feature = "mmap")]
+    fn get_mapped(
         &self,
         vhandle: &ValueHandle,
-        prefetch_size: usize,
-    ) -> crate::Result<Option<UserValue>> {
-        if let Some(value) = self.blob_cache.get(self.id, vhandle) {
-            return Ok(Some(value));
-        }
+        prefetch: Prefetch,
+        readahead: usize,
+        segment: &Segment<C>,
+    ) -> crate::Result<Option<(UserKey, UserValue)>> {
+        let map = segment.mmap()?;
+        let mut offset = vhandle.offset;
+        let mut result = None;
+
+        let readahead_end = vhandle.offset.saturating_add(readahead as u64);
+        let mut count = 0;
+        let mut bytes = 0;
+
+        loop {
+            let Some(blob) = crate::segment::mmap::read_blob(&map, offset)? else {
+                break;
+            };
 
-        let Some(segment) = self.manifest.get_segment(vhandle.segment_id) else {
-            return Ok(None);
-        };
+            let is_prefetched = result.is_some();
 
-        let mut reader = match self.fd_cache.get(self.id, vhandle.segment_id) {
-            Some(fd) => fd,
-            None => BufReader::new(File::open(&segment.path)?),
-        };
+            if is_prefetched {
+                // NOTE: Large values are never prefetched, see `read_prefetched_blob`
+                if !prefetch.allows(count, bytes)
+                    || blob.header.kind != BlobKind::Value
+                    || (prefetch == Prefetch::Buffered && blob.next_offset > readahead_end)
+                {
+                    break;
+                }
 
-        reader.seek(std::io::SeekFrom::Start(vhandle.offset))?;
-        let mut reader = SegmentReader::with_reader(vhandle.segment_id, reader)
-            .use_compression(self.config.compression.clone());
+                count += 1;
+            }
 
-        let Some(item) = reader.next() else {
-            return Ok(None);
-        };
-        let (_key, val, _checksum) = item?;
+            self.verify_blob(segment.id, offset, &blob.header, &blob.key, &blob.value)?;
 
-        self.blob_cache.insert(self.id, vhandle, val.clone());
+            let mut next_offset = blob.next_offset;
 
-        // TODO: maybe we can look at the value size and prefetch some more values
-        // without causing another I/O...
-        // TODO: benchmark range reads for rather small non-inlined blobs (maybe ~512-1000B)
-        // and see how different BufReader capacities and prefetch changes range read performance
-        for _ in 0..prefetch_size {
-            let offset = reader.get_offset()?;
+            let value = if blob.header.kind == BlobKind::ChunkManifest {
+                self.read_chunks(segment.id, &blob.key, &blob.value, || {
+                    let offset = next_offset;
+                    let chunk = crate::segment::mmap::read_blob(&map, offset)?;
 
-            let Some(item) = reader.next() else {
-                break;
+                    Ok(chunk.map(|chunk| {
+                        next_offset = chunk.next_offset;
+                        (offset, chunk.header, chunk.key, chunk.value)
+                    }))
+                })?
+            } else {
+                decompress(self.config.compression.as_ref(), &blob.header, blob.value)?
             };
-            let (_key, val, _checksum) = item?;
 
             let value_handle = ValueHandle {
-                segment_id: vhandle.segment_id,
+                segment_id: segment.id,
                 offset,
             };
+            self.blob_cache
+                .insert(self.id, &value_handle, value.clone());
 
-            self.blob_cache.insert(self.id, &value_handle, val);
-        }
+            if is_prefetched {
+                bytes += next_offset - offset;
+            }
 
-        // cache the BufReader for future use, must ensure to always use SeekFrom::Start when using it from cache
-        self.fd_cache
-            .insert(self.id, vhandle.segment_id, reader.into_inner());
+            result.get_or_insert((blob.key, value));
+            offset = next_offset;
+        }
 
-        Ok(Some(val))
+        Ok(result)
     }
 
     fn get_writer_raw(&self) -> crate::Result<SegmentWriter<C>> {
@@ -331,6 +1470,7 @@ impl<BC: BlobCache, C: Compressor + Clone, FDC: FDCache> ValueLog<BC, FDC, C> {
             self.config.segment_size_bytes,
             self.path.join(SEGMENTS_FOLDER),
         )
+        .map(|x| x.use_version(self.version))
         .map_err(Into::into)
     }
 
@@ -338,20 +1478,34 @@ impl<BC: BlobCache, C: Compressor + Clone, FDC: FDCache> ValueLog<BC, FDC, C> {
     ///
     /// # Errors
     ///
-    /// Will return `Err` if an IO error occurs.
+    /// Will return `Err` if an IO error occurs, or the value log is read-only.
     pub fn get_writer(&self) -> crate::Result<SegmentWriter<C>> {
-        self.get_writer_raw()
-            .map(|x| x.use_compression(self.config.compression.clone()))
+        self.check_writable()?;
+
+        self.get_writer_raw().map(|x| {
+            x.use_compression(self.config.compression.clone())
+                .use_chunk_size(self.config.chunk_size)
+        })
+    }
+
+    /// Evicts a segment from the blob cache and file descriptor cache.
+    fn invalidate_segment(&self, segment_id: SegmentId) {
+        self.blob_cache.invalidate_segment(self.id, segment_id);
+        self.fd_cache.invalidate_segment(self.id, segment_id);
     }
 
     /// Drops stale segments.
     ///
+    /// Segment files are deleted once no [`Snapshot`] references them anymore.
+    ///
     /// Returns the amount of disk space (compressed data) freed.
     ///
     /// # Errors
     ///
-    /// Will return `Err` if an IO error occurs.
+    /// Will return `Err` if an IO error occurs, or the value log is read-only.
     pub fn drop_stale_segments(&self) -> crate::Result<u64> {
+        self.check_writable()?;
+
         // IMPORTANT: Only allow 1 rollover or GC at any given time
         let _guard = self.rollover_guard.lock().expect("lock is poisoned");
 
@@ -375,8 +1529,11 @@ impl<BC: BlobCache, C: Compressor + Clone, FDC: FDCache> ValueLog<BC, FDC, C> {
             log::info!("Dropping stale blob files: {ids:?}");
             self.manifest.drop_segments(&ids)?;
 
+            // NOTE: The files are deleted when the last reference is dropped,
+            // which may be held by a snapshot
             for segment in segments {
-                std::fs::remove_file(&segment.path)?;
+                segment.mark_for_deletion();
+                self.invalidate_segment(segment.id);
             }
         }
 
@@ -402,6 +1559,66 @@ impl<BC: BlobCache, C: Compressor + Clone, FDC: FDCache> ValueLog<BC, FDC, C> {
         }
     }
 
+    /// Marks blobs as stale, without scanning the index.
+    ///
+    /// Each item is a value handle that is no longer referenced by the index
+    /// (because its key was overwritten or deleted), together with the value size
+    /// that was given to [`IndexWriter::insert_indirect`].
+    ///
+    /// This updates the GC stats incrementally, so they stay current without
+    /// calling [`ValueLog::scan_for_stats`].
+    /// Each handle should only be marked once, otherwise the stats will overestimate
+    /// the amount of stale data (but never beyond a fully stale segment).
+    ///
+    /// The stats are persisted before returning. If that fails, the stats are still
+    /// updated in memory, and persisting them is retried on the next rollover,
+    /// scan or when the value log is dropped.
+    ///
+    /// A concurrent [`ValueLog::scan_for_stats`] overwrites the stats with the result of
+    /// the scan, so handles that are marked while the index is scanned may be counted twice.
+    ///
+    /// # Errors
+    ///
+    /// Will return `Err` if an IO error occurs.
+    pub fn mark_handles_stale(&self, handles: &[(ValueHandle, u32)]) -> crate::Result<()> {
+        if handles.is_empty() {
+            return Ok(());
+        }
+
+        let mut stale_map = SizeMap::new();
+
+        for (vhandle, size) in handles {
+            let size = self.value_len(vhandle, *size)?;
+
+            let counter = stale_map.entry(vhandle.segment_id).or_default();
+            counter.item_count += 1;
+            counter.size += size;
+        }
+
+        // IMPORTANT: Scans set the stats to absolute values, so they must not
+        // run at the same time as incremental updates
+        let _lock = self.rollover_guard.lock().expect("lock is poisoned");
+
+        {
+            let segments = self.manifest.segments.read().expect("lock is poisoned");
+
+            for (id, counter) in &stale_map {
+                // NOTE: The segment may have been dropped already
+                let Some(segment) = segments.get(id) else {
+                    continue;
+                };
+
+                segment.mark_blobs_as_stale(counter.item_count, counter.size);
+            }
+        }
+
+        if !self.read_only {
+            self.manifest.persist_gc_stats()?;
+        }
+
+        Ok(())
+    }
+
     // TODO: remove?
     /// Returns the approximate space amplification.
     ///
@@ -480,10 +1697,17 @@ impl<BC: BlobCache, C: Compressor + Clone, FDC: FDCache> ValueLog<BC, FDC, C> {
         let ids = self.manifest.list_segment_ids();
 
         let mut scanner = Scanner::new(iter, lock_guard, &ids);
-        scanner.scan()?;
-        let size_map = scanner.finish();
+        scanner.scan_with(|vhandle, size| self.value_len(vhandle, size))?;
+
+        // IMPORTANT: Keep holding the rollover lock until the stats are persisted,
+        // so blobs marked as stale in the meantime are not overwritten
+        let (size_map, _lock) = scanner.finish_locked();
         let report = self.consume_scan_result(&size_map);
 
+        if !self.read_only {
+            self.manifest.persist_gc_stats()?;
+        }
+
         Ok(report)
     }
 
@@ -495,7 +1719,10 @@ impl<BC: BlobCache, C: Compressor + Clone, FDC: FDCache> ValueLog<BC, FDC, C> {
             .read()
             .expect("lock is poisoned")
             .values()
-            .map(|x| x.scan())
+            .map(|x| {
+                x.scan()
+                    .map(|reader| reader.use_compression(self.config.compression.clone()))
+            })
             .collect::<crate::Result<Vec<_>>>()?;
 
         Ok(MergeReader::new(readers))
@@ -530,23 +1757,42 @@ impl<BC: BlobCache, C: Compressor + Clone, FDC: FDCache> ValueLog<BC, FDC, C> {
     /// Atomically removes all data from the value log.
     ///
     /// If `prune_async` is set to `true`, the blob files will be removed from disk in a thread to avoid blocking.
+    ///
+    /// Blob files that are still referenced by a [`Snapshot`] are removed once the snapshot is dropped.
     pub fn clear(&self, prune_async: bool) -> crate::Result<()> {
+        self.check_writable()?;
+
         let guard = self.rollover_guard.lock().expect("lock is poisoned");
-        let ids = self.manifest.list_segment_ids();
+        let segments = self.manifest.snapshot();
         self.manifest.clear()?;
         drop(guard);
 
-        if prune_async {
-            let path = self.path.clone();
+        let segments = Arc::try_unwrap(segments).unwrap_or_else(|x| (*x).clone());
+        let mut paths = Vec::with_capacity(segments.len());
+
+        for segment in segments.into_values() {
+            match Arc::try_unwrap(segment) {
+                Ok(segment) => paths.push(segment.path.clone()),
+
+                // NOTE: The segment is still referenced by a snapshot,
+                // so it is deleted when the last reference is dropped
+                Err(segment) => segment.mark_for_deletion(),
+            }
+        }
 
+        // IMPORTANT: Evict only after marking the segments, see `segment_file`
+        self.blob_cache.invalidate_vlog(self.id);
+        self.fd_cache.invalidate_vlog(self.id);
+
+        if prune_async {
             std::thread::spawn(move || {
-                log::trace!("Pruning dropped blob files in thread: {ids:?}");
-                unlink_blob_files(&path, &ids);
+                log::trace!("Pruning dropped blob files in thread: {paths:?}");
+                unlink_blob_files(&paths);
                 log::trace!("Successfully pruned all blob files");
             });
         } else {
-            log::trace!("Pruning dropped blob files: {ids:?}");
-            unlink_blob_files(&self.path, &ids);
+            log::trace!("Pruning dropped blob files: {paths:?}");
+            unlink_blob_files(&paths);
             log::trace!("Successfully pruned all blob files");
         }
 
@@ -568,6 +1814,8 @@ impl<BC: BlobCache, C: Compressor + Clone, FDC: FDCache> ValueLog<BC, FDC, C> {
         index_reader: &R,
         mut index_writer: W,
     ) -> crate::Result<u64> {
+        self.check_writable()?;
+
         if ids.is_empty() {
             return Ok(0);
         }
@@ -593,23 +1841,25 @@ impl<BC: BlobCache, C: Compressor + Clone, FDC: FDCache> ValueLog<BC, FDC, C> {
             .map(|x| x.scan())
             .collect::<crate::Result<Vec<_>>>()?;
 
-        // TODO: 2.0.0: Store uncompressed size per blob
-        // so we can avoid recompression costs during GC
-        // but have stats be correct
+        // NOTE: Blobs are copied as they are stored on disk, so values
+        // never need to be decompressed & compressed again
+        let mut reader = MergeReader::new(readers);
 
-        let reader = MergeReader::new(
-            readers
-                .into_iter()
-                .map(|x| x.use_compression(self.config.compression.clone()))
-                .collect(),
-        );
+        let mut writer = self.get_writer_raw()?;
 
-        let mut writer = self
-            .get_writer_raw()?
-            .use_compression(self.config.compression.clone());
+        // NOTE: The chunks of a large value are kept or discarded together with their manifest
+        let mut keep_chunks = false;
 
-        for item in reader {
-            let (k, v, segment_id, _) = item?;
+        while let Some(item) = reader.next_raw() {
+            let (mut header, k, v, segment_id) = item?;
+
+            if header.kind == BlobKind::Chunk {
+                if keep_chunks {
+                    writer.write_raw(&k, &header, &v)?;
+                }
+                continue;
+            }
+            keep_chunks = false;
 
             match index_reader.get(&k)? {
                 // If this value is in an older segment, we can discard it
@@ -618,13 +1868,34 @@ impl<BC: BlobCache, C: Compressor + Clone, FDC: FDCache> ValueLog<BC, FDC, C> {
                 _ => {}
             }
 
-            let vhandle = writer.get_next_value_handle();
+            // NOTE: V1 blobs do not store their uncompressed size, so we need
+            // to decompress them once to keep the stats correct
+            let uncompressed_len = match (header.uncompressed_len, &self.config.compression) {
+                (Some(len), _) => len,
+                (None, Some(compressor)) => {
+                    // NOTE: Truncation is OK because we know values are u32 max
+                    #[allow(clippy::cast_possible_truncation)]
+                    let len = compressor.decompress(&v)?.len() as u32;
+                    len
+                }
+                (None, None) => header.value_len,
+            };
+            header.uncompressed_len = Some(uncompressed_len);
+            header
+                .compression
+                .get_or_insert_with(|| CompressionType::of(self.config.compression.as_ref()));
+
+            let size = if header.kind == BlobKind::ChunkManifest {
+                keep_chunks = true;
+                ChunkManifest::from_value(&v)?.index_size()
+            } else {
+                uncompressed_len
+            };
 
-            // NOTE: Truncation is OK because we know values are u32 max
-            #[allow(clippy::cast_possible_truncation)]
-            index_writer.insert_indirect(&k, vhandle, v.len() as u32)?;
+            let vhandle = writer.get_next_value_handle();
+            index_writer.insert_indirect(&k, vhandle, size)?;
 
-            writer.write(&k, &v)?;
+            writer.write_raw(&k, &header, &v)?;
         }
 
         // IMPORTANT: New segments need to be persisted before adding to index
@@ -638,7 +1909,15 @@ impl<BC: BlobCache, C: Compressor + Clone, FDC: FDCache> ValueLog<BC, FDC, C> {
         // IMPORTANT: We only mark the segments as definitely stale
         // The external index needs to decide when it is safe to drop
         // the old segments, as some reads may still be performed
+        // without a snapshot
         self.mark_as_stale(ids);
+        self.manifest.persist_gc_stats()?;
+
+        // NOTE: The blobs of the old segments are only reachable through
+        // outdated value handles now
+        for &id in ids {
+            self.blob_cache.invalidate_segment(self.id, id);
+        }
 
         let size_after = self.manifest.disk_space_used();
 
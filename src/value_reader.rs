@@ -0,0 +1,247 @@
+// Copyright (c) 2024-present, fjall-rs
+// This source code is licensed under both the Apache 2.0 and MIT License
+// (found in the LICENSE-* files in the repository)
+
+use crate::{
+    id::SegmentId,
+    segment::{pread::PositionalReader, Segment},
+    Compressor, UserKey,
+};
+use std::{
+    io::{Read, Seek, SeekFrom},
+    sync::Arc,
+};
+
+/// Part of a value, stored as the uncompressed value of a single blob
+///
+/// Values are stored in a single chunk, unless they were split into chunks
+/// because they are too large.
+pub struct ValueChunk {
+    /// Offset of the chunk's blob in its segment
+    pub offset: u64,
+
+    /// Offset of the chunk's data in the segment file
+    pub value_offset: u64,
+
+    /// Position of the chunk in the value
+    pub start: u64,
+
+    /// Length of the chunk
+    pub len: u64,
+
+    /// Stored checksum of the chunk's blob
+    pub checksum: u64,
+}
+
+impl ValueChunk {
+    fn end(&self) -> u64 {
+        self.start + self.len
+    }
+}
+
+/// Verifies the checksums of a value's chunks while the value is read sequentially
+struct ChecksumVerifier {
+    key: UserKey,
+    hasher: xxhash_rust::xxh3::Xxh3,
+    segment_id: SegmentId,
+}
+
+impl ChecksumVerifier {
+    fn new(key: UserKey, segment_id: SegmentId) -> Self {
+        let mut verifier = Self {
+            key,
+            hasher: xxhash_rust::xxh3::Xxh3::new(),
+            segment_id,
+        };
+        verifier.reset();
+        verifier
+    }
+
+    fn reset(&mut self) {
+        self.hasher.reset();
+        self.hasher.update(&self.key);
+    }
+
+    fn finish(&mut self, chunk: &ValueChunk) -> std::io::Result<()> {
+        let checksum = self.hasher.digest();
+        self.reset();
+
+        if checksum == chunk.checksum {
+            return Ok(());
+        }
+
+        log::error!(
+            "Checksum mismatch for blob in segment {} at offset {}",
+            self.segment_id,
+            chunk.offset,
+        );
+
+        Err(std::io::Error::new(
+            std::io::ErrorKind::InvalidData,
+            crate::Error::ChecksumMismatch {
+                segment_id: self.segment_id,
+                offset: chunk.offset,
+            },
+        ))
+    }
+}
+
+/// Streams the value of a single uncompressed blob from disk
+///
+/// The value is never fully loaded into memory, so large values can be
+/// read (and seeked) in small chunks. Large values that are split into
+/// several chunk blobs are streamed transparently.
+///
+/// If checksums are verified, a blob's checksum is checked once its value has been read
+/// to the end, and a mismatch is returned as an [`std::io::ErrorKind::InvalidData`] error
+/// wrapping [`crate::Error::ChecksumMismatch`]. Seeking disables verification,
+/// because the checksum can only be computed over sequential reads.
+///
+/// The reader keeps its segment alive, so it can still be read after the segment has been
+/// dropped by garbage collection.
+#[allow(clippy::module_name_repetitions)]
+pub struct ValueReader<C: Compressor + Clone> {
+    inner: PositionalReader,
+
+    chunks: Vec<ValueChunk>,
+
+    /// Index of the chunk at the current position
+    chunk_idx: usize,
+
+    /// Length of the value
+    len: u64,
+
+    /// Position in the value
+    pos: u64,
+
+    checksum: Option<ChecksumVerifier>,
+
+    segment: Arc<Segment<C>>,
+}
+
+impl<C: Compressor + Clone> ValueReader<C> {
+    pub(crate) fn new(
+        segment: Arc<Segment<C>>,
+        inner: PositionalReader,
+        chunks: Vec<ValueChunk>,
+    ) -> Self {
+        let len = chunks.last().map(ValueChunk::end).unwrap_or_default();
+
+        Self {
+            inner,
+            chunks,
+            chunk_idx: 0,
+            len,
+            pos: 0,
+            checksum: None,
+            segment,
+        }
+    }
+
+    /// Enables checksum verification, using the blob's key.
+    pub(crate) fn verify_checksum(mut self, key: UserKey) -> Self {
+        self.checksum = Some(ChecksumVerifier::new(key, self.segment.id));
+        self
+    }
+
+    /// Moves to the chunk at the current position, checking the checksums
+    /// of all chunks that were read to the end.
+    ///
+    /// Returns `None` if the end of the value was reached.
+    fn advance(&mut self) -> std::io::Result<Option<usize>> {
+        while let Some(chunk) = self.chunks.get(self.chunk_idx) {
+            if self.pos < chunk.end() {
+                return Ok(Some(self.chunk_idx));
+            }
+
+            self.chunk_idx += 1;
+
+            if let Some(checksum) = &mut self.checksum {
+                if let Err(e) = checksum.finish(chunk) {
+                    self.checksum = None;
+                    return Err(e);
+                }
+            }
+        }
+
+        Ok(None)
+    }
+
+    /// Returns the length of the value.
+    #[must_use]
+    pub fn len(&self) -> u64 {
+        self.len
+    }
+
+    /// Returns `true` if the value is empty.
+    #[must_use]
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+}
+
+impl<C: Compressor + Clone> Read for ValueReader<C> {
+    fn read(&mut self, buf: &mut [u8]) -> std::io::Result<usize> {
+        let Some(chunk) = self.advance()?.and_then(|idx| self.chunks.get(idx)) else {
+            return Ok(0);
+        };
+
+        if buf.is_empty() {
+            return Ok(0);
+        }
+
+        let chunk_pos = self.pos - chunk.start;
+        let chunk_end = chunk.end();
+
+        // NOTE: Truncation is OK, because the result is at most buf.len()
+        #[allow(clippy::cast_possible_truncation)]
+        let max = (chunk.len - chunk_pos).min(buf.len() as u64) as usize;
+
+        let buf = buf.get_mut(..max).unwrap_or_default();
+
+        self.inner
+            .seek(SeekFrom::Start(chunk.value_offset + chunk_pos))?;
+        let n = self.inner.read(buf)?;
+
+        if n == 0 {
+            return Err(std::io::Error::from(std::io::ErrorKind::UnexpectedEof));
+        }
+
+        self.pos += n as u64;
+
+        if let Some(checksum) = &mut self.checksum {
+            checksum.hasher.update(buf.get(..n).unwrap_or_default());
+        }
+
+        if self.pos == chunk_end {
+            self.advance()?;
+        }
+
+        Ok(n)
+    }
+}
+
+impl<C: Compressor + Clone> Seek for ValueReader<C> {
+    fn seek(&mut self, pos: SeekFrom) -> std::io::Result<u64> {
+        let pos = match pos {
+            SeekFrom::Start(pos) => Some(pos),
+            SeekFrom::Current(delta) => self.pos.checked_add_signed(delta),
+            SeekFrom::End(delta) => self.len.checked_add_signed(delta),
+        };
+
+        let Some(pos) = pos else {
+            return Err(std::io::Error::new(
+                std::io::ErrorKind::InvalidInput,
+                "invalid seek to a negative or overflowing position",
+            ));
+        };
+
+        if pos != self.pos {
+            self.checksum = None;
+            self.pos = pos;
+            self.chunk_idx = self.chunks.partition_point(|chunk| chunk.end() <= pos);
+        }
+
+        Ok(pos)
+    }
+}
@@ -9,6 +9,9 @@ use byteorder::WriteBytesExt;
 pub enum Version {
     /// Version for 1.x.x releases
     V1,
+
+    /// Version 2, which stores the compression type and uncompressed size of each blob
+    V2,
 }
 
 impl std::fmt::Display for Version {
@@ -21,6 +24,7 @@ impl From<Version> for u8 {
     fn from(value: Version) -> Self {
         match value {
             Version::V1 => 1,
+            Version::V2 => 2,
         }
     }
 }
@@ -30,6 +34,7 @@ impl TryFrom<u8> for Version {
     fn try_from(value: u8) -> Result<Self, Self::Error> {
         match value {
             1 => Ok(Self::V1),
+            2 => Ok(Self::V2),
             _ => Err(()),
         }
     }
@@ -88,6 +93,22 @@ mod tests {
         assert_eq!(version, Some(Version::V1));
     }
 
+    #[test]
+    #[allow(clippy::expect_used)]
+    pub fn version_serialize_v2() -> crate::Result<()> {
+        let mut bytes = vec![];
+        Version::V2.write_file_header(&mut bytes)?;
+        assert_eq!(bytes, &[b'V', b'L', b'G', 2]);
+        Ok(())
+    }
+
+    #[test]
+    #[allow(clippy::expect_used)]
+    pub fn version_deserialize_success_v2() {
+        let version = Version::parse_file_header(&[b'V', b'L', b'G', 2]);
+        assert_eq!(version, Some(Version::V2));
+    }
+
     #[test]
     #[allow(clippy::expect_used)]
     pub fn version_deserialize_fail() {
@@ -0,0 +1,243 @@
+mod common;
+
+use common::{copy_dir, MockIndex, MockIndexWriter, NoCacher, NoCompressor};
+use std::path::Path;
+use test_log::test;
+use value_log::{Compressor, Config, IndexWriter, ValueLog};
+
+#[derive(Clone, Debug, Default)]
+struct Lz4Compressor;
+
+impl Compressor for Lz4Compressor {
+    fn compress(&self, bytes: &[u8]) -> value_log::Result<Vec<u8>> {
+        Ok(lz4_flex::compress_prepend_size(bytes))
+    }
+
+    fn decompress(&self, bytes: &[u8]) -> value_log::Result<Vec<u8>> {
+        lz4_flex::decompress_size_prepended(bytes).map_err(|_| value_log::Error::Decompress)
+    }
+}
+
+/// Same codec as [`Lz4Compressor`], but registered under a different ID
+#[derive(Clone, Debug, Default)]
+struct OtherCompressor;
+
+impl Compressor for OtherCompressor {
+    fn compress(&self, bytes: &[u8]) -> value_log::Result<Vec<u8>> {
+        Lz4Compressor.compress(bytes)
+    }
+
+    fn decompress(&self, bytes: &[u8]) -> value_log::Result<Vec<u8>> {
+        Lz4Compressor.decompress(bytes)
+    }
+
+    fn id(&self) -> u8 {
+        2
+    }
+}
+
+fn blob_magic(path: &Path) -> std::io::Result<Vec<u8>> {
+    let bytes = std::fs::read(path)?;
+    Ok(bytes[0..8].to_vec())
+}
+
+#[test]
+fn blob_format_v2() -> value_log::Result<()> {
+    let folder = tempfile::tempdir()?;
+    let vl_path = folder.path();
+
+    let index = MockIndex::default();
+
+    let value_log = ValueLog::open(
+        vl_path,
+        Config::<_, _, Lz4Compressor>::new(NoCacher, NoCacher).compression(Some(Lz4Compressor)),
+    )?;
+
+    assert_eq!(b"VLG\x02", &*std::fs::read(vl_path.join(".vlog"))?);
+
+    let key = "abc";
+    let value = "verycompressable".repeat(10);
+
+    let vhandle = {
+        let mut index_writer = MockIndexWriter(index.clone());
+        let mut writer = value_log.get_writer()?;
+
+        let vhandle = writer.get_next_value_handle();
+        index_writer.insert_indirect(key.as_bytes(), vhandle.clone(), value.len() as u32)?;
+        writer.write(key, &value)?;
+
+        value_log.register_writer(writer)?;
+
+        vhandle
+    };
+
+    assert_eq!(
+        b"VLGBLOB\x02",
+        &*blob_magic(&vl_path.join("segments").join("0"))?
+    );
+
+    assert_eq!(
+        &*value_log.get(&vhandle)?.expect("value should exist"),
+        value.as_bytes(),
+    );
+
+    {
+        let segments = value_log.manifest.list_segments();
+        let segment = segments.first().unwrap();
+        assert_eq!(segment.meta.total_uncompressed_bytes, value.len() as u64);
+        assert!(segment.meta.compressed_bytes < value.len() as u64);
+    }
+
+    Ok(())
+}
+
+#[test]
+fn blob_format_v2_compressed_without_compressor() -> value_log::Result<()> {
+    let folder = tempfile::tempdir()?;
+    let vl_path = folder.path();
+
+    let index = MockIndex::default();
+
+    let vhandle = {
+        let value_log = ValueLog::open(
+            vl_path,
+            Config::<_, _, Lz4Compressor>::new(NoCacher, NoCacher).compression(Some(Lz4Compressor)),
+        )?;
+
+        let mut index_writer = MockIndexWriter(index.clone());
+        let mut writer = value_log.get_writer()?;
+
+        let value = "verycompressable".repeat(10);
+
+        let vhandle = writer.get_next_value_handle();
+        index_writer.insert_indirect(b"abc", vhandle.clone(), value.len() as u32)?;
+        writer.write("abc", &value)?;
+
+        value_log.register_writer(writer)?;
+
+        vhandle
+    };
+
+    let value_log = ValueLog::open(
+        vl_path,
+        Config::<_, _, NoCompressor>::new(NoCacher, NoCacher),
+    )?;
+
+    assert!(matches!(
+        value_log.get(&vhandle),
+        Err(value_log::Error::Decompress)
+    ));
+    assert!(matches!(
+        value_log.get_range(&vhandle, 0, 10),
+        Err(value_log::Error::Decompress)
+    ));
+
+    Ok(())
+}
+
+#[test]
+fn blob_format_v2_codec_mismatch() -> value_log::Result<()> {
+    let folder = tempfile::tempdir()?;
+    let vl_path = folder.path();
+
+    let index = MockIndex::default();
+
+    let vhandle = {
+        let value_log = ValueLog::open(
+            vl_path,
+            Config::<_, _, OtherCompressor>::new(NoCacher, NoCacher)
+                .compression(Some(OtherCompressor)),
+        )?;
+
+        let mut index_writer = MockIndexWriter(index.clone());
+        let mut writer = value_log.get_writer()?;
+
+        let value = "verycompressable".repeat(10);
+
+        let vhandle = writer.get_next_value_handle();
+        index_writer.insert_indirect(b"abc", vhandle.clone(), value.len() as u32)?;
+        writer.write("abc", &value)?;
+
+        value_log.register_writer(writer)?;
+
+        assert_eq!(value.as_bytes(), &*value_log.get(&vhandle)?.unwrap());
+
+        vhandle
+    };
+
+    // NOTE: The low nibble of the tag after the magic and checksum stores the codec ID
+    let segment_path = vl_path
+        .join("segments")
+        .join(vhandle.segment_id.to_string());
+    assert_eq!(2, std::fs::read(segment_path)?[8 + 8] & 0x0F);
+
+    let value_log = ValueLog::open(
+        vl_path,
+        Config::<_, _, Lz4Compressor>::new(NoCacher, NoCacher).compression(Some(Lz4Compressor)),
+    )?;
+
+    assert!(matches!(
+        value_log.get(&vhandle),
+        Err(value_log::Error::Decompress)
+    ));
+    assert!(matches!(
+        value_log.get_range(&vhandle, 0, 10),
+        Err(value_log::Error::Decompress)
+    ));
+
+    Ok(())
+}
+
+#[test]
+fn blob_format_v1_stays_v1() -> value_log::Result<()> {
+    let folder = tempfile::tempdir()?;
+    let vl_path = folder.path();
+
+    copy_dir(Path::new("test_fixture/v1_vlog"), vl_path)?;
+
+    let index = MockIndex::default();
+
+    let vhandle = {
+        let value_log = ValueLog::open(
+            vl_path,
+            Config::<_, _, NoCompressor>::new(NoCacher, NoCacher),
+        )?;
+        assert_eq!(2, value_log.segment_count());
+
+        let mut index_writer = MockIndexWriter(index.clone());
+        let mut writer = value_log.get_writer()?;
+
+        let vhandle = writer.get_next_value_handle();
+        index_writer.insert_indirect(b"new", vhandle.clone(), 3)?;
+        writer.write("new", "new")?;
+
+        value_log.register_writer(writer)?;
+
+        assert_eq!(
+            b"VLGBLOB\x01",
+            &*blob_magic(
+                &vl_path
+                    .join("segments")
+                    .join(vhandle.segment_id.to_string())
+            )?
+        );
+
+        vhandle
+    };
+
+    assert_eq!(b"VLG\x01", &*std::fs::read(vl_path.join(".vlog"))?);
+
+    let value_log = ValueLog::open(
+        vl_path,
+        Config::<_, _, NoCompressor>::new(NoCacher, NoCacher),
+    )?;
+    assert_eq!(3, value_log.segment_count());
+    assert_eq!(0, value_log.verify()?);
+
+    assert_eq!(
+        &*value_log.get(&vhandle)?.expect("value should exist"),
+        b"new",
+    );
+
+    Ok(())
+}
@@ -0,0 +1,103 @@
+mod common;
+
+use common::{write_items, MockIndex, MockIndexWriter, NoCompressor};
+use test_log::test;
+use value_log::{Config, IndexReader, LruBlobCache, LruFDCache, ValueLog};
+
+type TestValueLog = ValueLog<LruBlobCache, LruFDCache, NoCompressor>;
+
+fn read_items(
+    value_log: &TestValueLog,
+    index: &MockIndex,
+    items: &[&str],
+) -> value_log::Result<()> {
+    for key in items {
+        let vhandle = index.get(key.as_bytes())?.unwrap();
+        let value = value_log.get(&vhandle)?.unwrap();
+        assert_eq!(&*value, key.repeat(1_000).as_bytes());
+    }
+
+    Ok(())
+}
+
+#[test]
+fn cache_invalidation_drop_stale_segments() -> value_log::Result<()> {
+    let folder = tempfile::tempdir()?;
+
+    let blob_cache = LruBlobCache::with_capacity_bytes(1_000_000);
+    let fd_cache = LruFDCache::with_capacity(10);
+
+    let index = MockIndex::default();
+
+    let value_log = ValueLog::open(
+        folder.path(),
+        Config::<_, _, NoCompressor>::new(blob_cache.clone(), fd_cache.clone()),
+    )?;
+
+    let items = ["a", "b", "c"];
+    write_items(&value_log, &index, &items, 1_000)?;
+    read_items(&value_log, &index, &items)?;
+
+    assert_eq!(3, blob_cache.len());
+    assert_eq!(1, fd_cache.len());
+
+    // NOTE: Rolled over blobs are only reachable through outdated handles
+    value_log.major_compact(&index, MockIndexWriter(index.clone()))?;
+    assert!(blob_cache.is_empty());
+    assert_eq!(1, fd_cache.len());
+
+    value_log.drop_stale_segments()?;
+    assert!(fd_cache.is_empty());
+
+    read_items(&value_log, &index, &items)?;
+    assert_eq!(3, blob_cache.len());
+    assert_eq!(1, fd_cache.len());
+
+    value_log.clear(false)?;
+    assert!(blob_cache.is_empty());
+    assert!(fd_cache.is_empty());
+
+    Ok(())
+}
+
+#[test]
+fn cache_invalidation_drop_vlog() -> value_log::Result<()> {
+    let folder = tempfile::tempdir()?;
+
+    let blob_cache = LruBlobCache::with_capacity_bytes(1_000_000);
+    let fd_cache = LruFDCache::with_capacity(10);
+
+    let index_a = MockIndex::default();
+    let index_b = MockIndex::default();
+
+    let value_log_a = ValueLog::open(
+        folder.path().join("a"),
+        Config::<_, _, NoCompressor>::new(blob_cache.clone(), fd_cache.clone()),
+    )?;
+    let value_log_b = ValueLog::open(
+        folder.path().join("b"),
+        Config::<_, _, NoCompressor>::new(blob_cache.clone(), fd_cache.clone()),
+    )?;
+
+    write_items(&value_log_a, &index_a, &["a", "b"], 1_000)?;
+    write_items(&value_log_b, &index_b, &["c"], 1_000)?;
+
+    read_items(&value_log_a, &index_a, &["a", "b"])?;
+    read_items(&value_log_b, &index_b, &["c"])?;
+    assert_eq!(3, blob_cache.len());
+    assert_eq!(2, fd_cache.len());
+
+    // NOTE: Dropping a value log evicts its entries from shared caches
+    drop(value_log_a);
+    assert_eq!(1, blob_cache.len());
+    assert_eq!(1, fd_cache.len());
+
+    read_items(&value_log_b, &index_b, &["c"])?;
+    assert_eq!(1, blob_cache.hits());
+
+    drop(value_log_b);
+    assert!(blob_cache.is_empty());
+    assert!(fd_cache.is_empty());
+
+    Ok(())
+}
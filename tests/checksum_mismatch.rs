@@ -0,0 +1,92 @@
+mod common;
+
+use common::{MockIndex, MockIndexWriter, NoCacher, NoCompressor};
+use std::io::{Seek, SeekFrom, Write};
+use test_log::test;
+use value_log::{Config, IndexWriter, ValueLog};
+
+#[test]
+fn checksum_mismatch() -> value_log::Result<()> {
+    let folder = tempfile::tempdir()?;
+    let vl_path = folder.path();
+
+    let index = MockIndex::default();
+
+    let items = ["a", "b", "c", "d", "e"];
+
+    {
+        let value_log = ValueLog::open(
+            vl_path,
+            Config::<_, _, NoCompressor>::new(NoCacher, NoCacher),
+        )?;
+
+        let mut index_writer = MockIndexWriter(index.clone());
+        let mut writer = value_log.get_writer()?;
+
+        for key in &items {
+            let value = key.repeat(1_000);
+            let value = value.as_bytes();
+
+            let key = key.as_bytes();
+
+            let vhandle = writer.get_next_value_handle();
+            index_writer.insert_indirect(key, vhandle, value.len() as u32)?;
+
+            writer.write(key, value)?;
+        }
+
+        value_log.register_writer(writer)?;
+    }
+
+    let vhandle = index
+        .read()
+        .unwrap()
+        .get(b"c".as_slice())
+        .unwrap()
+        .0
+        .clone();
+
+    // Flip the last byte of the value of "c"
+    {
+        let path = vl_path
+            .join("segments")
+            .join(vhandle.segment_id.to_string());
+        let mut file = std::fs::OpenOptions::new().write(true).open(path)?;
+        let value_end = vhandle.offset + 27 + 1 + 1_000;
+        file.seek(SeekFrom::Start(value_end - 1))?;
+        file.write_all(b"x")?;
+        file.sync_all()?;
+    }
+
+    {
+        let value_log = ValueLog::open(
+            vl_path,
+            Config::<_, _, NoCompressor>::new(NoCacher, NoCacher),
+        )?;
+
+        assert!(matches!(
+            value_log.get(&vhandle),
+            Err(value_log::Error::ChecksumMismatch { segment_id, offset })
+            if segment_id == vhandle.segment_id && offset == vhandle.offset,
+        ));
+
+        for key in ["a", "b", "d", "e"] {
+            let vhandle = index.read().unwrap().get(key.as_bytes()).unwrap().0.clone();
+            let item = value_log.get(&vhandle)?.unwrap();
+            assert_eq!(&*item, key.repeat(1_000).as_bytes());
+        }
+    }
+
+    {
+        let value_log = ValueLog::open(
+            vl_path,
+            Config::<_, _, NoCompressor>::new(NoCacher, NoCacher).verify_checksums(false),
+        )?;
+
+        let item = value_log.get(&vhandle)?.unwrap();
+        assert_eq!(1_000, item.len());
+        assert_eq!(b'x', *item.last().unwrap());
+    }
+
+    Ok(())
+}
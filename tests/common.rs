@@ -6,13 +6,13 @@ use std::{
     cell::RefCell,
     collections::{BTreeMap, HashMap},
     fs::File,
-    io::BufReader,
     ops::Add,
+    path::Path,
     sync::{Arc, Mutex, RwLock},
 };
 use value_log::{
     BlobCache, BlobFileId, Compressor, FDCache, IndexReader, IndexWriter, UserKey, UserValue,
-    ValueHandle, ValueLogId,
+    ValueHandle, ValueLog, ValueLogId,
 };
 
 type MockIndexInner = RwLock<BTreeMap<UserKey, (ValueHandle, u32)>>;
@@ -97,15 +97,15 @@ impl BlobCache for NoCacher {
 }
 
 impl FDCache for NoCacher {
-    fn get(&self, _: ValueLogId, _: BlobFileId) -> Option<BufReader<File>> {
+    fn get(&self, _: ValueLogId, _: BlobFileId) -> Option<Arc<File>> {
         None
     }
-    fn insert(&self, _: ValueLogId, _: BlobFileId, _: BufReader<File>) {}
+    fn insert(&self, _: ValueLogId, _: BlobFileId, _: Arc<File>) {}
 }
 
 #[derive(Clone, Default)]
 pub struct InMemCacher {
-    fd_cache: Arc<Mutex<HashMap<(ValueLogId, BlobFileId), File>>>,
+    fd_cache: Arc<Mutex<HashMap<(ValueLogId, BlobFileId), Arc<File>>>>,
     fd_hit_count: Arc<RefCell<u32>>,
     fd_miss_count: Arc<RefCell<u32>>,
 }
@@ -120,7 +120,7 @@ impl InMemCacher {
 }
 
 impl FDCache for InMemCacher {
-    fn get(&self, vlog_id: ValueLogId, blob_file_id: BlobFileId) -> Option<BufReader<File>> {
+    fn get(&self, vlog_id: ValueLogId, blob_file_id: BlobFileId) -> Option<Arc<File>> {
         let lock = self.fd_cache.lock().unwrap();
         let fd = match lock.get(&(vlog_id, blob_file_id)) {
             Some(fd) => fd,
@@ -130,15 +130,77 @@ impl FDCache for InMemCacher {
             }
         };
 
-        let fd_clone = fd.try_clone().unwrap();
         *self.fd_hit_count.borrow_mut() += 1;
-        Some(BufReader::new(fd_clone))
+        Some(fd.clone())
     }
 
-    fn insert(&self, vlog_id: ValueLogId, blob_file_id: BlobFileId, fd: BufReader<File>) {
+    fn insert(&self, vlog_id: ValueLogId, blob_file_id: BlobFileId, fd: Arc<File>) {
         self.fd_cache
             .lock()
             .unwrap()
-            .insert((vlog_id, blob_file_id), fd.into_inner());
+            .insert((vlog_id, blob_file_id), fd);
     }
 }
+
+/// Writes the given key-value pairs into a new segment, and inserts them into the index
+#[allow(unused)]
+pub fn write_kvs<BC: BlobCache, FDC: FDCache, C: Compressor + Clone>(
+    value_log: &ValueLog<BC, FDC, C>,
+    index: &MockIndex,
+    items: &[(&[u8], &[u8])],
+) -> value_log::Result<()> {
+    let mut index_writer = MockIndexWriter(index.clone());
+    let mut writer = value_log.get_writer()?;
+
+    for (key, value) in items {
+        let vhandle = writer.get_next_value_handle();
+        // NOTE: Sizes of values of 4 GiB or more saturate
+        let size = u32::try_from(value.len()).unwrap_or(u32::MAX);
+        index_writer.insert_indirect(key, vhandle, size)?;
+
+        writer.write(key, value)?;
+    }
+
+    value_log.register_writer(writer)
+}
+
+/// Writes the given keys into a new segment, using the key repeated `repeat` times as value
+#[allow(unused)]
+pub fn write_items<BC: BlobCache, FDC: FDCache, C: Compressor + Clone>(
+    value_log: &ValueLog<BC, FDC, C>,
+    index: &MockIndex,
+    items: &[&str],
+    repeat: usize,
+) -> value_log::Result<()> {
+    let values = items
+        .iter()
+        .map(|key| key.repeat(repeat))
+        .collect::<Vec<_>>();
+
+    let items = items
+        .iter()
+        .zip(&values)
+        .map(|(key, value)| (key.as_bytes(), value.as_bytes()))
+        .collect::<Vec<_>>();
+
+    write_kvs(value_log, index, &items)
+}
+
+/// Recursively copies a directory
+#[allow(unused)]
+pub fn copy_dir(src: &Path, dst: &Path) -> std::io::Result<()> {
+    std::fs::create_dir_all(dst)?;
+
+    for dirent in std::fs::read_dir(src)? {
+        let dirent = dirent?;
+        let path = dst.join(dirent.file_name());
+
+        if dirent.file_type()?.is_dir() {
+            copy_dir(&dirent.path(), &path)?;
+        } else {
+            std::fs::copy(dirent.path(), path)?;
+        }
+    }
+
+    Ok(())
+}
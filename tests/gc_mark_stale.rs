@@ -0,0 +1,166 @@
+mod common;
+
+use common::{copy_dir, MockIndex, MockIndexWriter, NoCacher, NoCompressor};
+use test_log::test;
+use value_log::{Config, IndexWriter, StaleThresholdStrategy, ValueLog};
+
+#[test]
+fn gc_mark_handles_stale() -> value_log::Result<()> {
+    let folder = tempfile::tempdir()?;
+    let vl_path = folder.path();
+
+    let index = MockIndex::default();
+
+    let value_log = ValueLog::open(
+        vl_path,
+        Config::<_, _, NoCompressor>::new(NoCacher, NoCacher),
+    )?;
+
+    let items = ["a", "b", "c", "d", "e"];
+
+    {
+        let mut index_writer = MockIndexWriter(index.clone());
+        let mut writer = value_log.get_writer()?;
+
+        for key in &items {
+            let value = key.repeat(10_000);
+            let value = value.as_bytes();
+
+            let key = key.as_bytes();
+
+            let vhandle = writer.get_next_value_handle();
+            index_writer.insert_indirect(key, vhandle, value.len() as u32)?;
+
+            writer.write(key, value)?;
+        }
+
+        value_log.register_writer(writer)?;
+    }
+
+    // NOTE: Overwrite some keys, and tell the value log about the old versions
+    {
+        let mut stale_handles = vec![];
+
+        let mut index_writer = MockIndexWriter(index.clone());
+        let mut writer = value_log.get_writer()?;
+
+        for key in ["a", "b"] {
+            let value = key.repeat(10_000);
+
+            let old = index.read().unwrap().get(key.as_bytes()).cloned().unwrap();
+            stale_handles.push(old);
+
+            let vhandle = writer.get_next_value_handle();
+            index_writer.insert_indirect(key.as_bytes(), vhandle, value.len() as u32)?;
+
+            writer.write(key, value)?;
+        }
+
+        value_log.register_writer(writer)?;
+        value_log.mark_handles_stale(&stale_handles)?;
+    }
+
+    let space_amp = value_log.space_amp();
+    let stale_bytes = value_log.manifest.stale_bytes();
+    assert_eq!(20_000, stale_bytes);
+
+    {
+        let segment = value_log.manifest.get_segment(0).unwrap();
+        assert_eq!(2, segment.gc_stats.stale_items());
+        assert_eq!(0.4, segment.stale_ratio());
+    }
+
+    // NOTE: A full scan should come to the same conclusion
+    value_log.scan_for_stats(index.read().unwrap().values().cloned().map(Ok))?;
+    assert_eq!(space_amp, value_log.space_amp());
+    assert_eq!(stale_bytes, value_log.manifest.stale_bytes());
+
+    // NOTE: Delete the remaining keys of the first segment
+    {
+        let stale_handles = ["c", "d", "e"]
+            .into_iter()
+            .map(|key| {
+                let item = index.read().unwrap().get(key.as_bytes()).cloned().unwrap();
+                index.remove(key.as_bytes());
+                item
+            })
+            .collect::<Vec<_>>();
+
+        value_log.mark_handles_stale(&stale_handles)?;
+
+        // NOTE: Marking handles twice never exceeds the segment size
+        value_log.mark_handles_stale(&stale_handles)?;
+    }
+
+    {
+        let segment = value_log.manifest.get_segment(0).unwrap();
+        assert!(segment.is_stale());
+        assert_eq!(
+            segment.meta.total_uncompressed_bytes,
+            segment.gc_stats.stale_bytes()
+        );
+    }
+
+    let strategy = StaleThresholdStrategy::new(0.5);
+    value_log.apply_gc_strategy(&strategy, &index, MockIndexWriter(index.clone()))?;
+    value_log.drop_stale_segments()?;
+    assert_eq!(1, value_log.segment_count());
+
+    for (key, (vhandle, _)) in index.read().unwrap().iter() {
+        let item = value_log.get(vhandle)?.unwrap();
+        assert_eq!(&*item, &*key.repeat(10_000));
+    }
+
+    Ok(())
+}
+
+#[test]
+fn gc_mark_handles_stale_persisted() -> value_log::Result<()> {
+    let folder = tempfile::tempdir()?;
+    let vl_path = folder.path().join("vlog");
+    let crash_path = folder.path().join("crash");
+
+    let index = MockIndex::default();
+
+    {
+        let value_log = ValueLog::open(
+            &vl_path,
+            Config::<_, _, NoCompressor>::new(NoCacher, NoCacher),
+        )?;
+
+        let mut index_writer = MockIndexWriter(index.clone());
+        let mut writer = value_log.get_writer()?;
+
+        for key in ["a", "b", "c", "d"] {
+            let value = key.repeat(10_000);
+
+            let vhandle = writer.get_next_value_handle();
+            index_writer.insert_indirect(key.as_bytes(), vhandle, value.len() as u32)?;
+
+            writer.write(key, value)?;
+        }
+
+        value_log.register_writer(writer)?;
+
+        let stale_handles = ["a", "b"]
+            .into_iter()
+            .map(|key| index.read().unwrap().get(key.as_bytes()).cloned().unwrap())
+            .collect::<Vec<_>>();
+
+        value_log.mark_handles_stale(&stale_handles)?;
+
+        // NOTE: Simulate a crash by copying the folder while the value log is still open
+        copy_dir(&vl_path, &crash_path)?;
+    }
+
+    for path in [&crash_path, &vl_path] {
+        let value_log =
+            ValueLog::open(path, Config::<_, _, NoCompressor>::new(NoCacher, NoCacher))?;
+
+        let segment = value_log.manifest.get_segment(0).unwrap();
+        assert_eq!(2, segment.gc_stats.stale_items());
+        assert_eq!(20_000, segment.gc_stats.stale_bytes());
+    }
+
+    Ok(())
+}
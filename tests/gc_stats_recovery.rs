@@ -0,0 +1,112 @@
+mod common;
+
+use common::{MockIndex, MockIndexWriter, NoCacher, NoCompressor};
+use test_log::test;
+use value_log::{Config, IndexWriter, StaleThresholdStrategy, ValueLog};
+
+#[test]
+fn gc_stats_recovery() -> value_log::Result<()> {
+    let folder = tempfile::tempdir()?;
+    let vl_path = folder.path();
+
+    let index = MockIndex::default();
+
+    let items = ["a", "b", "c", "d", "e"];
+
+    let (space_amp, stale_bytes) = {
+        let value_log = ValueLog::open(
+            vl_path,
+            Config::<_, _, NoCompressor>::new(NoCacher, NoCacher),
+        )?;
+
+        for _ in 0..2 {
+            let mut index_writer = MockIndexWriter(index.clone());
+            let mut writer = value_log.get_writer()?;
+
+            for key in &items {
+                let value = key.repeat(10_000);
+                let value = value.as_bytes();
+
+                let key = key.as_bytes();
+
+                let vhandle = writer.get_next_value_handle();
+                index_writer.insert_indirect(key, vhandle, value.len() as u32)?;
+
+                writer.write(key, value)?;
+            }
+
+            value_log.register_writer(writer)?;
+        }
+
+        {
+            let mut index_writer = MockIndexWriter(index.clone());
+            let mut writer = value_log.get_writer()?;
+
+            let key = "a";
+            let value = key.repeat(10_000);
+
+            let vhandle = writer.get_next_value_handle();
+            index_writer.insert_indirect(key.as_bytes(), vhandle, value.len() as u32)?;
+
+            writer.write(key, value)?;
+
+            value_log.register_writer(writer)?;
+        }
+
+        value_log.scan_for_stats(index.read().unwrap().values().cloned().map(Ok))?;
+
+        assert!(value_log.space_amp() > 1.0);
+        assert_eq!(vec![(0, 5), (1, 1), (2, 0)], sorted_stale_items(&value_log));
+
+        (value_log.space_amp(), value_log.manifest.stale_bytes())
+    };
+
+    {
+        let value_log = ValueLog::open(
+            vl_path,
+            Config::<_, _, NoCompressor>::new(NoCacher, NoCacher),
+        )?;
+
+        // NOTE: No scan needed, the stats are restored from disk
+        assert_eq!(space_amp, value_log.space_amp());
+        assert_eq!(stale_bytes, value_log.manifest.stale_bytes());
+        assert_eq!(vec![(0, 5), (1, 1), (2, 0)], sorted_stale_items(&value_log));
+
+        value_log.drop_stale_segments()?;
+        assert_eq!(2, value_log.segment_count());
+
+        let strategy = StaleThresholdStrategy::new(0.1);
+        value_log.apply_gc_strategy(&strategy, &index, MockIndexWriter(index.clone()))?;
+    }
+
+    {
+        let value_log = ValueLog::open(
+            vl_path,
+            Config::<_, _, NoCompressor>::new(NoCacher, NoCacher),
+        )?;
+
+        // NOTE: The rolled over segment is still marked stale after recovery
+        value_log.drop_stale_segments()?;
+        assert_eq!(2, value_log.segment_count());
+        assert_eq!(1.0, value_log.space_amp());
+
+        for (key, (vhandle, _)) in index.read().unwrap().iter() {
+            let item = value_log.get(vhandle)?.unwrap();
+            assert_eq!(&*item, &*key.repeat(10_000));
+        }
+    }
+
+    Ok(())
+}
+
+fn sorted_stale_items(value_log: &ValueLog<NoCacher, NoCacher, NoCompressor>) -> Vec<(u64, u64)> {
+    let mut stats = value_log
+        .manifest
+        .list_segments()
+        .into_iter()
+        .map(|x| (x.id, x.gc_stats.stale_items()))
+        .collect::<Vec<_>>();
+
+    stats.sort_unstable();
+    stats
+}
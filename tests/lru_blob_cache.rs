@@ -0,0 +1,106 @@
+mod common;
+
+use common::{write_items, MockIndex, NoCacher, NoCompressor};
+use test_log::test;
+use value_log::{BlobCache, Config, IndexReader, LruBlobCache, Slice, ValueHandle, ValueLog};
+
+#[test]
+fn lru_blob_cache_shared() -> value_log::Result<()> {
+    let folder = tempfile::tempdir()?;
+
+    let blob_cache = LruBlobCache::with_capacity_bytes(1_000_000);
+
+    let items = ["a", "b", "c", "d", "e"];
+
+    let mut logs = vec![];
+
+    for (idx, item) in items.iter().enumerate() {
+        let index = MockIndex::default();
+
+        let value_log = ValueLog::open(
+            folder.path().join(idx.to_string()),
+            Config::<_, _, NoCompressor>::new(blob_cache.clone(), NoCacher),
+        )?;
+
+        // NOTE: Every value log stores the same handles, but a different value
+        write_items(&value_log, &index, &[item], 1_000)?;
+
+        logs.push((value_log, index, item));
+    }
+
+    for _ in 0..2 {
+        for (value_log, index, item) in &logs {
+            let vhandle = index.get(item.as_bytes())?.unwrap();
+            let value = value_log.get(&vhandle)?.unwrap();
+            assert_eq!(&*value, item.repeat(1_000).as_bytes());
+        }
+    }
+
+    assert_eq!(5, blob_cache.len());
+    assert_eq!(5 * (1_000 + 64), blob_cache.size());
+    assert_eq!(5, blob_cache.misses());
+    assert_eq!(5, blob_cache.hits());
+
+    Ok(())
+}
+
+#[test]
+fn lru_blob_cache_capacity() -> value_log::Result<()> {
+    let folder = tempfile::tempdir()?;
+
+    let blob_cache = LruBlobCache::with_shard_count(3_200, 1);
+    assert_eq!(3_200, blob_cache.capacity());
+
+    let index = MockIndex::default();
+
+    let value_log = ValueLog::open(
+        folder.path(),
+        Config::<_, _, NoCompressor>::new(blob_cache.clone(), NoCacher),
+    )?;
+
+    let items = ["a", "b", "c", "d", "e"];
+    write_items(&value_log, &index, &items, 1_000)?;
+
+    for item in items {
+        let vhandle = index.get(item.as_bytes())?.unwrap();
+        let value = value_log.get(&vhandle)?.unwrap();
+        assert_eq!(&*value, item.repeat(1_000).as_bytes());
+    }
+
+    assert_eq!(3, blob_cache.len());
+    assert_eq!(3 * (1_000 + 64), blob_cache.size());
+
+    // NOTE: The least recently used values were evicted
+    for item in ["c", "d", "e"] {
+        let vhandle = index.get(item.as_bytes())?.unwrap();
+        value_log.get(&vhandle)?.unwrap();
+    }
+    assert_eq!(3, blob_cache.hits());
+
+    let vhandle = index.get(b"a")?.unwrap();
+    value_log.get(&vhandle)?.unwrap();
+    assert_eq!(3, blob_cache.hits());
+    assert_eq!(6, blob_cache.misses());
+
+    blob_cache.clear();
+    assert!(blob_cache.is_empty());
+
+    Ok(())
+}
+
+#[test]
+fn lru_blob_cache_empty_values() {
+    let blob_cache = LruBlobCache::with_shard_count(640, 1);
+
+    for offset in 0..100 {
+        let vhandle = ValueHandle {
+            segment_id: 0,
+            offset,
+        };
+        blob_cache.insert(0, &vhandle, Slice::empty());
+    }
+
+    // NOTE: Empty values still take up space, so the amount of cached values is bounded
+    assert_eq!(10, blob_cache.len());
+    assert_eq!(640, blob_cache.size());
+}
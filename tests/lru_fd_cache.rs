@@ -0,0 +1,192 @@
+mod common;
+
+use common::{MockIndex, MockIndexWriter, NoCacher, NoCompressor};
+use std::{fs::File, sync::Arc};
+use test_log::test;
+use value_log::{Config, FDCache, IndexReader, IndexWriter, LruFDCache, ValueLog};
+
+#[test]
+fn lru_fd_cache_capacity() -> value_log::Result<()> {
+    let folder = tempfile::tempdir()?;
+    let vl_path = folder.path();
+
+    let fd_cache = LruFDCache::with_capacity(2);
+    assert_eq!(2, fd_cache.capacity());
+
+    let index = MockIndex::default();
+
+    let value_log = ValueLog::open(
+        vl_path,
+        Config::<_, _, NoCompressor>::new(NoCacher, fd_cache.clone()),
+    )?;
+
+    let items = ["a", "b", "c", "d", "e"];
+
+    // NOTE: Write every item into its own segment
+    for key in &items {
+        let mut index_writer = MockIndexWriter(index.clone());
+        let mut writer = value_log.get_writer()?;
+
+        let value = key.repeat(1_000);
+        let vhandle = writer.get_next_value_handle();
+        index_writer.insert_indirect(key.as_bytes(), vhandle, value.len() as u32)?;
+        writer.write(key.as_bytes(), value.as_bytes())?;
+
+        value_log.register_writer(writer)?;
+    }
+
+    for _ in 0..2 {
+        for key in &items {
+            let vhandle = index.get(key.as_bytes())?.unwrap();
+            let value = value_log.get(&vhandle)?.unwrap();
+            assert_eq!(&*value, key.repeat(1_000).as_bytes());
+        }
+    }
+
+    // NOTE: Only the two most recently used descriptors are kept open
+    assert_eq!(2, fd_cache.len());
+    assert_eq!(0, fd_cache.hits());
+    assert_eq!(10, fd_cache.misses());
+
+    let vhandle = index.get(b"e")?.unwrap();
+    value_log.get(&vhandle)?.unwrap();
+    assert_eq!(1, fd_cache.hits());
+
+    value_log.major_compact(&index, MockIndexWriter(index.clone()))?;
+    value_log.drop_stale_segments()?;
+
+    // NOTE: Descriptors of dropped segments are evicted
+    assert!(fd_cache.is_empty());
+
+    for key in &items {
+        let vhandle = index.get(key.as_bytes())?.unwrap();
+        let value = value_log.get(&vhandle)?.unwrap();
+        assert_eq!(&*value, key.repeat(1_000).as_bytes());
+    }
+    assert_eq!(1, fd_cache.len());
+
+    value_log.clear(false)?;
+    assert!(fd_cache.is_empty());
+
+    Ok(())
+}
+
+#[test]
+fn lru_fd_cache_shared_descriptors() -> value_log::Result<()> {
+    let folder = tempfile::tempdir()?;
+    let path = folder.path().join("file");
+    std::fs::write(&path, b"abc")?;
+
+    let fd_cache = LruFDCache::with_capacity(3);
+
+    fd_cache.insert(0, 1, Arc::new(File::open(&path)?));
+    fd_cache.insert(1, 1, Arc::new(File::open(&path)?));
+    assert_eq!(2, fd_cache.len());
+
+    // NOTE: Readers of the same file share its descriptor
+    let a = fd_cache.get(0, 1).unwrap();
+    let b = fd_cache.get(0, 1).unwrap();
+    assert!(Arc::ptr_eq(&a, &b));
+    assert_eq!(2, fd_cache.len());
+
+    fd_cache.insert(0, 2, Arc::new(File::open(&path)?));
+    fd_cache.insert(0, 3, Arc::new(File::open(&path)?));
+
+    // NOTE: The descriptor of value log 1 was the least recently used
+    assert_eq!(3, fd_cache.len());
+    assert!(fd_cache.get(1, 1).is_none());
+
+    fd_cache.invalidate_segment(0, 2);
+    assert_eq!(2, fd_cache.len());
+    assert!(fd_cache.get(0, 2).is_none());
+
+    assert_eq!(2, fd_cache.hits());
+    assert_eq!(2, fd_cache.misses());
+
+    Ok(())
+}
+
+#[test]
+fn lru_fd_cache_sharded() -> value_log::Result<()> {
+    let folder = tempfile::tempdir()?;
+    let path = folder.path().join("file");
+    std::fs::write(&path, b"abc")?;
+
+    let fd_cache = LruFDCache::with_shard_count(16, 4);
+    assert_eq!(16, fd_cache.capacity());
+
+    for vlog_id in 0..2 {
+        for blob_file_id in 0..4 {
+            fd_cache.insert(vlog_id, blob_file_id, Arc::new(File::open(&path)?));
+        }
+    }
+
+    for blob_file_id in 0..4 {
+        assert!(fd_cache.get(0, blob_file_id).is_some());
+    }
+
+    // NOTE: Descriptors of a value log are evicted from all shards
+    fd_cache.invalidate_vlog(0);
+    assert_eq!(4, fd_cache.len());
+    assert!(fd_cache.get(0, 0).is_none());
+    assert!(fd_cache.get(1, 0).is_some());
+
+    Ok(())
+}
+
+#[test]
+fn lru_fd_cache_concurrent_readers() -> value_log::Result<()> {
+    let folder = tempfile::tempdir()?;
+    let vl_path = folder.path();
+
+    let fd_cache = LruFDCache::with_capacity(1);
+
+    let index = MockIndex::default();
+
+    let value_log = ValueLog::open(
+        vl_path,
+        Config::<_, _, NoCompressor>::new(NoCacher, fd_cache.clone()),
+    )?;
+
+    let items = (0..100u32)
+        .map(|x| (x.to_be_bytes(), x.to_string().repeat(100)))
+        .collect::<Vec<_>>();
+
+    {
+        let mut index_writer = MockIndexWriter(index.clone());
+        let mut writer = value_log.get_writer()?;
+
+        for (key, value) in &items {
+            let vhandle = writer.get_next_value_handle();
+            index_writer.insert_indirect(key, vhandle, value.len() as u32)?;
+            writer.write(key, value.as_bytes())?;
+        }
+
+        value_log.register_writer(writer)?;
+    }
+
+    // NOTE: Open the file descriptor once, all threads then read through it
+    let vhandle = index.get(&0u32.to_be_bytes())?.unwrap();
+    assert!(value_log.get(&vhandle)?.is_some());
+
+    std::thread::scope(|s| {
+        for t in 0..4 {
+            let value_log = &value_log;
+            let index = &index;
+            let items = &items;
+
+            s.spawn(move || {
+                for (key, value) in items.iter().skip(t).step_by(2) {
+                    let vhandle = index.get(key).unwrap().unwrap();
+                    let item = value_log.get(&vhandle).unwrap().unwrap();
+                    assert_eq!(&*item, value.as_bytes());
+                }
+            });
+        }
+    });
+
+    assert_eq!(1, fd_cache.len());
+    assert_eq!(1, fd_cache.misses());
+
+    Ok(())
+}
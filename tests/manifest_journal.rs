@@ -0,0 +1,197 @@
+mod common;
+
+use common::{copy_dir, write_items, MockIndex, MockIndexWriter, NoCacher, NoCompressor};
+use std::path::Path;
+use test_log::test;
+use value_log::{Config, ValueLog};
+
+const JOURNAL_MAGIC: &[u8] = b"VLOGMFJ\x01";
+
+#[test]
+fn manifest_journal() -> value_log::Result<()> {
+    let folder = tempfile::tempdir()?;
+    let vl_path = folder.path();
+    let manifest_path = vl_path.join("vlog_manifest");
+
+    let index = MockIndex::default();
+
+    {
+        let value_log = ValueLog::open(
+            vl_path,
+            Config::<_, _, NoCompressor>::new(NoCacher, NoCacher),
+        )?;
+
+        assert!(std::fs::read(&manifest_path)?.starts_with(JOURNAL_MAGIC));
+
+        for _ in 0..5 {
+            write_items(&value_log, &index, &["a", "b", "c"], 1_000)?;
+        }
+        assert_eq!(5, value_log.segment_count());
+
+        value_log.major_compact(&index, MockIndexWriter(index.clone()))?;
+        value_log.drop_stale_segments()?;
+        assert_eq!(1, value_log.segment_count());
+    }
+
+    {
+        let value_log = ValueLog::open(
+            vl_path,
+            Config::<_, _, NoCompressor>::new(NoCacher, NoCacher),
+        )?;
+        assert_eq!(1, value_log.segment_count());
+
+        write_items(&value_log, &index, &["d"], 1_000)?;
+        assert_eq!(2, value_log.segment_count());
+    }
+
+    // NOTE: Simulate a crash in the middle of appending an edit
+    {
+        use std::io::Write;
+
+        let mut file = std::fs::OpenOptions::new()
+            .append(true)
+            .open(&manifest_path)?;
+        file.write_all(&[1, 0, 0, 0, 100, 0, 0])?;
+        file.sync_all()?;
+    }
+
+    {
+        let value_log = ValueLog::open(
+            vl_path,
+            Config::<_, _, NoCompressor>::new(NoCacher, NoCacher),
+        )?;
+        assert_eq!(2, value_log.segment_count());
+
+        write_items(&value_log, &index, &["e"], 1_000)?;
+        assert_eq!(3, value_log.segment_count());
+    }
+
+    {
+        let value_log = ValueLog::open(
+            vl_path,
+            Config::<_, _, NoCompressor>::new(NoCacher, NoCacher),
+        )?;
+        assert_eq!(3, value_log.segment_count());
+
+        for key in ["a", "b", "c", "d", "e"] {
+            let (vhandle, _) = index.read().unwrap().get(key.as_bytes()).cloned().unwrap();
+            let item = value_log.get(&vhandle)?.unwrap();
+            assert_eq!(&*item, key.repeat(1_000).as_bytes());
+        }
+    }
+
+    Ok(())
+}
+
+#[test]
+fn manifest_journal_v1_stays_flat() -> value_log::Result<()> {
+    let folder = tempfile::tempdir()?;
+    let vl_path = folder.path();
+    let manifest_path = vl_path.join("vlog_manifest");
+
+    copy_dir(Path::new("test_fixture/v1_vlog"), vl_path)?;
+
+    let index = MockIndex::default();
+
+    {
+        let value_log = ValueLog::open(
+            vl_path,
+            Config::<_, _, NoCompressor>::new(NoCacher, NoCacher),
+        )?;
+        assert_eq!(2, value_log.segment_count());
+
+        write_items(&value_log, &index, &["z"], 1_000)?;
+        assert_eq!(3, value_log.segment_count());
+    }
+
+    assert!(!std::fs::read(&manifest_path)?.starts_with(JOURNAL_MAGIC));
+
+    {
+        let value_log = ValueLog::open(
+            vl_path,
+            Config::<_, _, NoCompressor>::new(NoCacher, NoCacher),
+        )?;
+        assert_eq!(3, value_log.segment_count());
+    }
+
+    Ok(())
+}
+
+#[test]
+fn manifest_journal_corrupt_edit() -> value_log::Result<()> {
+    let folder = tempfile::tempdir()?;
+    let vl_path = folder.path();
+    let manifest_path = vl_path.join("vlog_manifest");
+
+    let index = MockIndex::default();
+
+    {
+        let value_log = ValueLog::open(
+            vl_path,
+            Config::<_, _, NoCompressor>::new(NoCacher, NoCacher),
+        )?;
+
+        for _ in 0..3 {
+            write_items(&value_log, &index, &["a"], 1_000)?;
+        }
+        assert_eq!(3, value_log.segment_count());
+    }
+
+    // NOTE: Flip a byte in the payload of the first edit, which comes after
+    // the magic and the initial (empty) snapshot
+    {
+        let mut bytes = std::fs::read(&manifest_path)?;
+        let first_edit = JOURNAL_MAGIC.len() + 1 + 4 + 8 + 8;
+        *bytes.get_mut(first_edit + 1 + 4).unwrap() ^= 0xFF;
+        std::fs::write(&manifest_path, bytes)?;
+    }
+
+    assert!(ValueLog::open(
+        vl_path,
+        Config::<_, _, NoCompressor>::new(NoCacher, NoCacher),
+    )
+    .is_err());
+
+    Ok(())
+}
+
+#[test]
+fn manifest_journal_corrupt_edit_length() -> value_log::Result<()> {
+    let folder = tempfile::tempdir()?;
+    let vl_path = folder.path();
+    let manifest_path = vl_path.join("vlog_manifest");
+
+    let index = MockIndex::default();
+
+    {
+        let value_log = ValueLog::open(
+            vl_path,
+            Config::<_, _, NoCompressor>::new(NoCacher, NoCacher),
+        )?;
+
+        for _ in 0..3 {
+            write_items(&value_log, &index, &["a"], 1_000)?;
+        }
+        assert_eq!(3, value_log.segment_count());
+    }
+
+    // NOTE: Make the length of the first edit reach past the end of the journal,
+    // so it looks like a torn append
+    {
+        let mut bytes = std::fs::read(&manifest_path)?;
+        let first_edit = JOURNAL_MAGIC.len() + 1 + 4 + 8 + 8;
+        *bytes.get_mut(first_edit + 1).unwrap() = 0xFF;
+        std::fs::write(&manifest_path, bytes)?;
+    }
+
+    assert!(ValueLog::open(
+        vl_path,
+        Config::<_, _, NoCompressor>::new(NoCacher, NoCacher),
+    )
+    .is_err());
+
+    // Segments must not have been treated as unfinished and deleted
+    assert_eq!(3, std::fs::read_dir(vl_path.join("segments"))?.count());
+
+    Ok(())
+}
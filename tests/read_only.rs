@@ -0,0 +1,131 @@
+mod common;
+
+use common::{MockIndex, MockIndexWriter, NoCacher, NoCompressor};
+use test_log::test;
+use value_log::{Config, IndexReader, IndexWriter, ValueLog};
+
+fn list_dir(path: &std::path::Path) -> std::io::Result<Vec<(std::path::PathBuf, u64)>> {
+    let mut entries = vec![];
+
+    for dirent in walkdir(path)? {
+        let len = std::fs::metadata(&dirent)?.len();
+        entries.push((dirent, len));
+    }
+
+    entries.sort();
+    Ok(entries)
+}
+
+fn walkdir(path: &std::path::Path) -> std::io::Result<Vec<std::path::PathBuf>> {
+    let mut paths = vec![];
+
+    for dirent in std::fs::read_dir(path)? {
+        let dirent = dirent?;
+
+        if dirent.file_type()?.is_dir() {
+            paths.extend(walkdir(&dirent.path())?);
+        } else {
+            paths.push(dirent.path());
+        }
+    }
+
+    Ok(paths)
+}
+
+#[test]
+fn read_only() -> value_log::Result<()> {
+    let folder = tempfile::tempdir()?;
+    let vl_path = folder.path();
+
+    let index = MockIndex::default();
+
+    let items = ["a", "b", "c", "d", "e"];
+
+    {
+        let value_log = ValueLog::open(
+            vl_path,
+            Config::<_, _, NoCompressor>::new(NoCacher, NoCacher),
+        )?;
+
+        let mut index_writer = MockIndexWriter(index.clone());
+        let mut writer = value_log.get_writer()?;
+
+        for key in &items {
+            let value = key.repeat(10_000);
+            let value = value.as_bytes();
+
+            let key = key.as_bytes();
+
+            let vhandle = writer.get_next_value_handle();
+            index_writer.insert_indirect(key, vhandle, value.len() as u32)?;
+
+            writer.write(key, value)?;
+        }
+
+        value_log.register_writer(writer)?;
+
+        // NOTE: Simulate an unfinished segment, e.g. of another process still writing
+        let mut writer = value_log.get_writer()?;
+        writer.write(b"f", b"unfinished")?;
+        drop(writer);
+    }
+
+    let files_before = list_dir(vl_path)?;
+
+    {
+        let value_log = ValueLog::open_read_only(
+            vl_path,
+            Config::<_, _, NoCompressor>::new(NoCacher, NoCacher),
+        )?;
+        assert!(value_log.is_read_only());
+        assert_eq!(1, value_log.segment_count());
+
+        for key in &items {
+            let vhandle = index.get(key.as_bytes())?.unwrap();
+            let item = value_log.get(&vhandle)?.unwrap();
+            assert_eq!(&*item, key.repeat(10_000).as_bytes());
+        }
+
+        let report = value_log.scan_for_stats(index.read().unwrap().values().cloned().map(Ok))?;
+        assert_eq!(0, report.stale_bytes);
+
+        assert!(value_log.verify_integrity()?.is_ok());
+
+        assert!(matches!(
+            value_log.get_writer(),
+            Err(value_log::Error::ReadOnly)
+        ));
+        assert!(matches!(
+            value_log.drop_stale_segments(),
+            Err(value_log::Error::ReadOnly)
+        ));
+        assert!(matches!(
+            value_log.clear(false),
+            Err(value_log::Error::ReadOnly)
+        ));
+        assert!(matches!(
+            value_log.major_compact(&index, MockIndexWriter(index.clone())),
+            Err(value_log::Error::ReadOnly)
+        ));
+    }
+
+    assert_eq!(files_before, list_dir(vl_path)?);
+
+    Ok(())
+}
+
+#[test]
+fn read_only_missing() -> value_log::Result<()> {
+    let folder = tempfile::tempdir()?;
+    let vl_path = folder.path().join("missing");
+
+    assert!(ValueLog::open_read_only(
+        &vl_path,
+        Config::<_, _, NoCompressor>::new(NoCacher, NoCacher),
+    )
+    .is_err());
+
+    assert!(!vl_path.try_exists()?);
+
+    Ok(())
+}
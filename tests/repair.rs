@@ -0,0 +1,222 @@
+mod common;
+
+use common::{MockIndex, MockIndexWriter, NoCacher, NoCompressor};
+use test_log::test;
+use value_log::{Config, IndexWriter, Slice, ValueHandle, ValueLog};
+
+#[test]
+fn repair() -> value_log::Result<()> {
+    let folder = tempfile::tempdir()?;
+    let vl_path = folder.path();
+
+    let index = MockIndex::default();
+
+    let items = ["a", "b", "c", "d", "e"];
+
+    {
+        let value_log = ValueLog::open(
+            vl_path,
+            Config::<_, _, NoCompressor>::new(NoCacher, NoCacher),
+        )?;
+
+        for segment_idx in 0..3 {
+            let mut index_writer = MockIndexWriter(index.clone());
+            let mut writer = value_log.get_writer()?;
+
+            for key in &items {
+                let key = format!("{key}{segment_idx}");
+
+                let value = key.repeat(500);
+                let value = value.as_bytes();
+
+                let key = key.as_bytes();
+
+                let vhandle = writer.get_next_value_handle();
+                index_writer.insert_indirect(key, vhandle, value.len() as u32)?;
+
+                writer.write(key, value)?;
+            }
+
+            value_log.register_writer(writer)?;
+        }
+
+        assert_eq!(3, value_log.segment_count());
+    }
+
+    // NOTE: e1 is stale, so it does not need to be salvaged
+    index.remove(b"e1");
+
+    let segments_folder = vl_path.join("segments");
+    let blob_len = 27 + 2 + 1_000;
+
+    // Corrupt the value of b1
+    {
+        use std::io::{Seek, SeekFrom, Write};
+
+        let mut file = std::fs::OpenOptions::new()
+            .write(true)
+            .open(segments_folder.join("1"))?;
+        file.seek(SeekFrom::Start(blob_len + 100))?;
+        file.write_all(b"x")?;
+        file.sync_all()?;
+    }
+
+    // Cut segment 1 in the middle of d1
+    {
+        let file = std::fs::OpenOptions::new()
+            .write(true)
+            .open(segments_folder.join("1"))?;
+        file.set_len(3 * blob_len + 500)?;
+        file.sync_all()?;
+    }
+
+    // Delete segment 2
+    std::fs::remove_file(segments_folder.join("2"))?;
+
+    assert!(ValueLog::open(
+        vl_path,
+        Config::<_, _, NoCompressor>::new(NoCacher, NoCacher),
+    )
+    .is_err());
+
+    let report = ValueLog::repair(
+        vl_path,
+        &Config::<_, _, NoCompressor>::new(NoCacher, NoCacher),
+        &index,
+        MockIndexWriter(index.clone()),
+    )?;
+
+    assert!(!report.is_clean());
+    assert_eq!(3, report.segment_count);
+    assert_eq!(vec![1], report.damaged_segments);
+    assert_eq!(vec![2], report.missing_segments);
+    assert_eq!(vec![3], report.new_segments);
+    assert_eq!(2, report.salvaged_blobs);
+    assert_eq!(vec![Slice::from(*b"b1")], report.lost_keys);
+    assert!(!report.corruptions.is_empty());
+
+    assert!(!segments_folder.join("1").try_exists()?);
+
+    {
+        let value_log = ValueLog::open(
+            vl_path,
+            Config::<_, _, NoCompressor>::new(NoCacher, NoCacher),
+        )?;
+
+        assert_eq!(2, value_log.segment_count());
+        assert!(value_log.verify_integrity()?.is_ok());
+
+        for key in ["a0", "b0", "c0", "d0", "e0", "a1", "c1"] {
+            let (vhandle, _) = index.read().unwrap().get(key.as_bytes()).cloned().unwrap();
+            let item = value_log.get(&vhandle)?.unwrap();
+            assert_eq!(&*item, key.repeat(500).as_bytes());
+        }
+
+        let (vhandle, _) = index
+            .read()
+            .unwrap()
+            .get(b"a1".as_slice())
+            .cloned()
+            .unwrap();
+        assert_eq!(3, vhandle.segment_id);
+        assert_eq!(0, vhandle.offset);
+    }
+
+    let report = ValueLog::repair(
+        vl_path,
+        &Config::<_, _, NoCompressor>::new(NoCacher, NoCacher),
+        &index,
+        MockIndexWriter(index.clone()),
+    )?;
+    assert!(report.is_clean());
+
+    Ok(())
+}
+
+struct FailingIndexWriter;
+
+impl IndexWriter for FailingIndexWriter {
+    fn insert_indirect(&mut self, _: &[u8], _: ValueHandle, _: u32) -> std::io::Result<()> {
+        Ok(())
+    }
+
+    fn finish(&mut self) -> std::io::Result<()> {
+        Err(std::io::Error::new(std::io::ErrorKind::Other, "Oh no"))
+    }
+}
+
+#[test]
+fn repair_index_fail_finish() -> value_log::Result<()> {
+    let folder = tempfile::tempdir()?;
+    let vl_path = folder.path();
+
+    let index = MockIndex::default();
+
+    {
+        let value_log = ValueLog::open(
+            vl_path,
+            Config::<_, _, NoCompressor>::new(NoCacher, NoCacher),
+        )?;
+
+        let mut index_writer = MockIndexWriter(index.clone());
+        let mut writer = value_log.get_writer()?;
+
+        for key in ["a", "b"] {
+            let value = key.repeat(1_000);
+
+            let vhandle = writer.get_next_value_handle();
+            index_writer.insert_indirect(key.as_bytes(), vhandle, value.len() as u32)?;
+
+            writer.write(key, value)?;
+        }
+
+        value_log.register_writer(writer)?;
+    }
+
+    // Corrupt the value of a
+    {
+        use std::io::{Seek, SeekFrom, Write};
+
+        let mut file = std::fs::OpenOptions::new()
+            .write(true)
+            .open(vl_path.join("segments").join("0"))?;
+        file.seek(SeekFrom::Start(100))?;
+        file.write_all(b"x")?;
+        file.sync_all()?;
+    }
+
+    assert!(ValueLog::repair(
+        vl_path,
+        &Config::<_, _, NoCompressor>::new(NoCacher, NoCacher),
+        &index,
+        FailingIndexWriter,
+    )
+    .is_err());
+
+    // NOTE: The index still points into the damaged segment, so it must not be dropped
+    assert!(vl_path.join("segments").join("0").try_exists()?);
+
+    {
+        let value_log = ValueLog::open(
+            vl_path,
+            Config::<_, _, NoCompressor>::new(NoCacher, NoCacher),
+        )?;
+        assert_eq!(2, value_log.segment_count());
+
+        let (vhandle, _) = index.read().unwrap().get(b"b".as_slice()).cloned().unwrap();
+        assert_eq!(0, vhandle.segment_id);
+        let item = value_log.get(&vhandle)?.unwrap();
+        assert_eq!(&*item, "b".repeat(1_000).as_bytes());
+    }
+
+    let report = ValueLog::repair(
+        vl_path,
+        &Config::<_, _, NoCompressor>::new(NoCacher, NoCacher),
+        &index,
+        MockIndexWriter(index.clone()),
+    )?;
+    assert_eq!(vec![0], report.damaged_segments);
+    assert!(!vl_path.join("segments").join("0").try_exists()?);
+
+    Ok(())
+}
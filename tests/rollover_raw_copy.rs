@@ -0,0 +1,103 @@
+mod common;
+
+use common::{MockIndex, MockIndexWriter, NoCacher};
+use std::sync::atomic::{AtomicUsize, Ordering::Relaxed};
+use test_log::test;
+use value_log::{Compressor, Config, IndexReader, IndexWriter, ValueLog};
+
+static COMPRESS_CALLS: AtomicUsize = AtomicUsize::new(0);
+static DECOMPRESS_CALLS: AtomicUsize = AtomicUsize::new(0);
+
+#[derive(Clone, Debug, Default)]
+struct CountingLz4Compressor;
+
+impl Compressor for CountingLz4Compressor {
+    fn compress(&self, bytes: &[u8]) -> value_log::Result<Vec<u8>> {
+        COMPRESS_CALLS.fetch_add(1, Relaxed);
+        Ok(lz4_flex::compress_prepend_size(bytes))
+    }
+
+    fn decompress(&self, bytes: &[u8]) -> value_log::Result<Vec<u8>> {
+        DECOMPRESS_CALLS.fetch_add(1, Relaxed);
+        lz4_flex::decompress_size_prepended(bytes).map_err(|_| value_log::Error::Decompress)
+    }
+}
+
+#[test]
+fn rollover_raw_copy() -> value_log::Result<()> {
+    let folder = tempfile::tempdir()?;
+    let vl_path = folder.path();
+
+    let index = MockIndex::default();
+
+    let value_log = ValueLog::open(
+        vl_path,
+        Config::<_, _, CountingLz4Compressor>::new(NoCacher, NoCacher)
+            .compression(Some(CountingLz4Compressor)),
+    )?;
+
+    let items = ["a", "b", "c", "d", "e"];
+
+    for _ in 0..2 {
+        let mut index_writer = MockIndexWriter(index.clone());
+        let mut writer = value_log.get_writer()?;
+
+        for key in &items {
+            let value = key.repeat(10_000);
+            let value = value.as_bytes();
+
+            let key = key.as_bytes();
+
+            let vhandle = writer.get_next_value_handle();
+            index_writer.insert_indirect(key, vhandle, value.len() as u32)?;
+
+            writer.write(key, value)?;
+        }
+
+        value_log.register_writer(writer)?;
+    }
+
+    let compressed_bytes = value_log
+        .manifest
+        .get_segment(1)
+        .unwrap()
+        .meta
+        .compressed_bytes;
+
+    COMPRESS_CALLS.store(0, Relaxed);
+    DECOMPRESS_CALLS.store(0, Relaxed);
+
+    value_log.major_compact(&index, MockIndexWriter(index.clone()))?;
+    value_log.drop_stale_segments()?;
+
+    assert_eq!(0, COMPRESS_CALLS.load(Relaxed));
+    assert_eq!(0, DECOMPRESS_CALLS.load(Relaxed));
+
+    assert_eq!(1, value_log.segment_count());
+
+    {
+        let segments = value_log.manifest.list_segments();
+        let segment = segments.first().unwrap();
+        assert_eq!(items.len() as u64, segment.meta.item_count);
+        assert_eq!(compressed_bytes, segment.meta.compressed_bytes);
+        assert_eq!(
+            items.len() as u64 * 10_000,
+            segment.meta.total_uncompressed_bytes
+        );
+    }
+
+    for key in &items {
+        let (vhandle, size) = index.read().unwrap().get(key.as_bytes()).cloned().unwrap();
+        assert_eq!(10_000, size);
+        assert_eq!(Some(vhandle.clone()), index.get(key.as_bytes())?);
+
+        let item = value_log.get(&vhandle)?.unwrap();
+        assert_eq!(&*item, key.repeat(10_000).as_bytes());
+    }
+
+    let report = value_log.scan_for_stats(index.read().unwrap().values().cloned().map(Ok))?;
+    assert_eq!(0, report.stale_bytes);
+    assert_eq!(items.len() as u64 * 10_000, report.total_bytes);
+
+    Ok(())
+}
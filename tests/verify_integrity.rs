@@ -0,0 +1,96 @@
+mod common;
+
+use common::{MockIndex, MockIndexWriter, NoCacher, NoCompressor};
+use std::io::{Seek, SeekFrom, Write};
+use test_log::test;
+use value_log::{Config, CorruptionKind, IndexWriter, ValueLog};
+
+#[test]
+fn verify_integrity() -> value_log::Result<()> {
+    let folder = tempfile::tempdir()?;
+    let vl_path = folder.path();
+
+    let index = MockIndex::default();
+
+    let value_log = ValueLog::open(
+        vl_path,
+        Config::<_, _, NoCompressor>::new(NoCacher, NoCacher),
+    )?;
+
+    let items = ["a", "b", "c", "d", "e"];
+
+    for _ in 0..3 {
+        let mut index_writer = MockIndexWriter(index.clone());
+        let mut writer = value_log.get_writer()?;
+
+        for key in &items {
+            let value = key.repeat(1_000);
+            let value = value.as_bytes();
+
+            let key = key.as_bytes();
+
+            let vhandle = writer.get_next_value_handle();
+            index_writer.insert_indirect(key, vhandle, value.len() as u32)?;
+
+            writer.write(key, value)?;
+        }
+
+        value_log.register_writer(writer)?;
+    }
+
+    {
+        let report = value_log.verify_integrity()?;
+        assert!(report.is_ok());
+        assert_eq!(3, report.segment_count);
+        assert_eq!(15, report.blob_count);
+        assert_eq!(15_000, report.blob_bytes);
+    }
+
+    let segments_folder = vl_path.join("segments");
+
+    // Flip a byte in the value of the second blob of segment 0
+    let blob_len = 27 + 1 + 1_000;
+    {
+        let mut file = std::fs::OpenOptions::new()
+            .write(true)
+            .open(segments_folder.join("0"))?;
+        file.seek(SeekFrom::Start(blob_len + 100))?;
+        file.write_all(b"x")?;
+        file.sync_all()?;
+    }
+
+    // Cut segment 1 in the middle of its third blob
+    {
+        let file = std::fs::OpenOptions::new()
+            .write(true)
+            .open(segments_folder.join("1"))?;
+        file.set_len(2 * blob_len + 500)?;
+        file.sync_all()?;
+    }
+
+    let report = value_log.verify_integrity()?;
+    assert_eq!(3, report.segment_count);
+    assert_eq!(vec![0, 1], report.corrupt_segments());
+
+    assert_eq!(3, report.corruptions.len());
+
+    let corruption = report.corruptions.first().unwrap();
+    assert_eq!(0, corruption.segment_id);
+    assert_eq!(Some(blob_len), corruption.offset);
+    assert!(matches!(
+        corruption.kind,
+        CorruptionKind::ChecksumMismatch { .. }
+    ));
+
+    let corruption = report.corruptions.get(1).unwrap();
+    assert_eq!(1, corruption.segment_id);
+    assert_eq!(None, corruption.offset);
+    assert_eq!(CorruptionKind::InvalidTrailer, corruption.kind);
+
+    let corruption = report.corruptions.get(2).unwrap();
+    assert_eq!(1, corruption.segment_id);
+    assert_eq!(Some(2 * blob_len), corruption.offset);
+    assert_eq!(CorruptionKind::Truncated, corruption.kind);
+
+    Ok(())
+}
@@ -0,0 +1,161 @@
+mod common;
+
+use common::{write_items, MockIndex, MockIndexWriter, NoCacher, NoCompressor};
+use test_log::test;
+use value_log::{BackupManifest, Config, IndexReader, ValueLog};
+
+#[test]
+fn vlog_backup_incremental() -> value_log::Result<()> {
+    let folder = tempfile::tempdir()?;
+    let vl_path = folder.path().join("vlog");
+    let backup_path = folder.path().join("backup");
+    let restore_path = folder.path().join("restore");
+
+    let index = MockIndex::default();
+
+    let value_log = ValueLog::open(
+        &vl_path,
+        Config::<_, _, NoCompressor>::new(NoCacher, NoCacher),
+    )?;
+
+    write_items(&value_log, &index, &["a", "b"], 10_000)?;
+    write_items(&value_log, &index, &["c", "d"], 10_000)?;
+
+    let backup = value_log.backup(&backup_path, None)?;
+    assert_eq!(backup.added, [0, 1]);
+    assert!(backup.removed.is_empty());
+    assert_eq!(backup, BackupManifest::from_dir(&backup_path)?);
+
+    write_items(&value_log, &index, &["e"], 10_000)?;
+
+    let backup = value_log.backup(&backup_path, Some(&backup))?;
+    assert_eq!(backup.added, [2]);
+    assert!(backup.removed.is_empty());
+
+    value_log.major_compact(&index, MockIndexWriter(index.clone()))?;
+    value_log.drop_stale_segments()?;
+    assert_eq!(value_log.manifest.list_segment_ids(), [3]);
+
+    let backup = value_log.backup(&backup_path, Some(&backup))?;
+    assert_eq!(backup.added, [3]);
+    assert_eq!(backup.removed, [0, 1, 2]);
+    assert_eq!(1, backup.segments.len());
+
+    let backup = BackupManifest::from_dir(&backup_path)?;
+    backup.restore(&backup_path, &restore_path)?;
+    assert!(backup.restore(&backup_path, &restore_path).is_err());
+
+    let restored = ValueLog::open(
+        &restore_path,
+        Config::<_, _, NoCompressor>::new(NoCacher, NoCacher),
+    )?;
+    assert_eq!(restored.manifest.list_segment_ids(), [3]);
+    assert!(restored.verify_integrity()?.is_ok());
+
+    for key in ["a", "b", "c", "d", "e"] {
+        let vhandle = index.get(key.as_bytes())?.unwrap();
+        let value = restored.get(&vhandle)?.unwrap();
+        assert_eq!(&*value, key.repeat(10_000).as_bytes());
+    }
+
+    Ok(())
+}
+
+#[test]
+fn vlog_backup_restore_damaged() -> value_log::Result<()> {
+    let folder = tempfile::tempdir()?;
+    let vl_path = folder.path().join("vlog");
+    let backup_path = folder.path().join("backup");
+    let restore_path = folder.path().join("restore");
+
+    let index = MockIndex::default();
+
+    let value_log = ValueLog::open(
+        &vl_path,
+        Config::<_, _, NoCompressor>::new(NoCacher, NoCacher),
+    )?;
+
+    write_items(&value_log, &index, &["a", "b"], 10_000)?;
+    write_items(&value_log, &index, &["c", "d"], 10_000)?;
+
+    let backup = value_log.backup(&backup_path, None)?;
+
+    // NOTE: Truncate the trailer of segment 1
+    {
+        let segment_path = backup_path.join("segments").join("1");
+        let len = std::fs::metadata(&segment_path)?.len();
+
+        let file = std::fs::OpenOptions::new()
+            .write(true)
+            .open(&segment_path)?;
+        file.set_len(len - 100)?;
+        file.sync_all()?;
+    }
+
+    assert!(matches!(
+        backup.restore(&backup_path, &restore_path),
+        Err(value_log::Error::InvalidBackup(1)),
+    ));
+    assert!(!restore_path.try_exists()?);
+
+    std::fs::remove_file(backup_path.join("segments").join("1"))?;
+
+    assert!(matches!(
+        backup.restore(&backup_path, &restore_path),
+        Err(value_log::Error::InvalidBackup(1)),
+    ));
+    assert!(!restore_path.try_exists()?);
+
+    Ok(())
+}
+
+#[test]
+fn vlog_backup_incremental_after_clear() -> value_log::Result<()> {
+    let folder = tempfile::tempdir()?;
+    let vl_path = folder.path().join("vlog");
+    let backup_path = folder.path().join("backup");
+    let restore_path = folder.path().join("restore");
+
+    let index = MockIndex::default();
+
+    let value_log = ValueLog::open(
+        &vl_path,
+        Config::<_, _, NoCompressor>::new(NoCacher, NoCacher),
+    )?;
+
+    write_items(&value_log, &index, &["a"], 10_000)?;
+    write_items(&value_log, &index, &["b"], 10_000)?;
+
+    let backup = value_log.backup(&backup_path, None)?;
+    assert_eq!(backup.added, [0, 1]);
+
+    value_log.clear(false)?;
+    drop(value_log);
+
+    // NOTE: After reopening, segment IDs are handed out again
+    let value_log = ValueLog::open(
+        &vl_path,
+        Config::<_, _, NoCompressor>::new(NoCacher, NoCacher),
+    )?;
+
+    write_items(&value_log, &index, &["c"], 5_000)?;
+    assert_eq!(value_log.manifest.list_segment_ids(), [1]);
+
+    let backup = value_log.backup(&backup_path, Some(&backup))?;
+    assert_eq!(backup.added, [1]);
+    assert_eq!(backup.removed, [0]);
+
+    backup.restore(&backup_path, &restore_path)?;
+
+    let restored = ValueLog::open(
+        &restore_path,
+        Config::<_, _, NoCompressor>::new(NoCacher, NoCacher),
+    )?;
+    assert!(restored.verify_integrity()?.is_ok());
+
+    let vhandle = index.get(b"c")?.unwrap();
+    let value = restored.get(&vhandle)?.unwrap();
+    assert_eq!(&*value, "c".repeat(5_000).as_bytes());
+
+    Ok(())
+}
@@ -0,0 +1,115 @@
+mod common;
+
+use common::{MockIndex, MockIndexWriter, NoCacher, NoCompressor};
+use test_log::test;
+use value_log::{Config, IndexReader, IndexWriter, ValueLog};
+
+#[test]
+fn vlog_checkpoint() -> value_log::Result<()> {
+    let folder = tempfile::tempdir()?;
+    let vl_path = folder.path().join("vlog");
+    let checkpoint_path = folder.path().join("checkpoint");
+
+    let index = MockIndex::default();
+
+    let value_log = ValueLog::open(
+        &vl_path,
+        Config::<_, _, NoCompressor>::new(NoCacher, NoCacher),
+    )?;
+
+    let items = ["a", "b", "c", "d", "e"];
+
+    for _ in 0..3 {
+        let mut index_writer = MockIndexWriter(index.clone());
+        let mut writer = value_log.get_writer()?;
+
+        for key in &items {
+            let value = key.repeat(10_000);
+            let value = value.as_bytes();
+
+            let key = key.as_bytes();
+
+            let vhandle = writer.get_next_value_handle();
+            index_writer.insert_indirect(key, vhandle, value.len() as u32)?;
+
+            writer.write(key, value)?;
+        }
+
+        value_log.register_writer(writer)?;
+    }
+
+    assert_eq!(3, value_log.segment_count());
+
+    value_log.checkpoint(&checkpoint_path)?;
+    assert!(value_log.checkpoint(&checkpoint_path).is_err());
+
+    // NOTE: Checkpoint the index at the same time
+    let checkpoint_index = MockIndex::default();
+    *checkpoint_index.write().unwrap() = index.read().unwrap().clone();
+
+    // NOTE: Modifying the value log does not affect the checkpoint
+    value_log.major_compact(&index, MockIndexWriter(index.clone()))?;
+    value_log.drop_stale_segments()?;
+    assert_eq!(value_log.manifest.list_segment_ids(), [3]);
+
+    {
+        let checkpoint = ValueLog::open(
+            &checkpoint_path,
+            Config::<_, _, NoCompressor>::new(NoCacher, NoCacher),
+        )?;
+
+        assert_eq!(3, checkpoint.segment_count());
+        assert!(checkpoint.verify_integrity()?.is_ok());
+
+        for key in &items {
+            let vhandle = checkpoint_index.get(key.as_bytes())?.unwrap();
+            assert_eq!(2, vhandle.segment_id);
+
+            let value = checkpoint.get(&vhandle)?.unwrap();
+            assert_eq!(&*value, key.repeat(10_000).as_bytes());
+        }
+    }
+
+    for key in &items {
+        let vhandle = index.get(key.as_bytes())?.unwrap();
+        let value = value_log.get(&vhandle)?.unwrap();
+        assert_eq!(&*value, key.repeat(10_000).as_bytes());
+    }
+
+    Ok(())
+}
+
+#[test]
+fn vlog_checkpoint_fail_cleanup() -> value_log::Result<()> {
+    let folder = tempfile::tempdir()?;
+    let vl_path = folder.path().join("vlog");
+    let checkpoint_path = folder.path().join("checkpoint");
+
+    let index = MockIndex::default();
+
+    let value_log = ValueLog::open(
+        &vl_path,
+        Config::<_, _, NoCompressor>::new(NoCacher, NoCacher),
+    )?;
+
+    for key in ["a", "b"] {
+        let mut index_writer = MockIndexWriter(index.clone());
+        let mut writer = value_log.get_writer()?;
+
+        let value = key.repeat(10_000);
+
+        let vhandle = writer.get_next_value_handle();
+        index_writer.insert_indirect(key.as_bytes(), vhandle, value.len() as u32)?;
+        writer.write(key, value)?;
+
+        value_log.register_writer(writer)?;
+    }
+
+    // NOTE: Linking the second segment fails
+    std::fs::remove_file(vl_path.join("segments").join("1"))?;
+
+    assert!(value_log.checkpoint(&checkpoint_path).is_err());
+    assert!(!checkpoint_path.try_exists()?);
+
+    Ok(())
+}
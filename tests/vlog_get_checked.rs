@@ -0,0 +1,65 @@
+mod common;
+
+use common::{MockIndex, MockIndexWriter, NoCacher, NoCompressor};
+use test_log::test;
+use value_log::{Config, IndexReader, IndexWriter, LruBlobCache, ValueHandle, ValueLog};
+
+#[test]
+fn vlog_get_checked() -> value_log::Result<()> {
+    let folder = tempfile::tempdir()?;
+    let vl_path = folder.path();
+
+    let blob_cache = LruBlobCache::with_capacity_bytes(1_000_000);
+
+    let index = MockIndex::default();
+
+    let value_log = ValueLog::open(
+        vl_path,
+        Config::<_, _, NoCompressor>::new(blob_cache.clone(), NoCacher),
+    )?;
+
+    {
+        let mut index_writer = MockIndexWriter(index.clone());
+        let mut writer = value_log.get_writer()?;
+
+        for key in [b"a", b"b"] {
+            let vhandle = writer.get_next_value_handle();
+            index_writer.insert_indirect(key, vhandle, 1)?;
+            writer.write(key, key)?;
+        }
+
+        value_log.register_writer(writer)?;
+    }
+
+    let a = index.get(b"a")?.unwrap();
+    let b = index.get(b"b")?.unwrap();
+
+    assert_eq!(b"a", &*value_log.get_checked(&a, b"a")?.unwrap());
+    assert_eq!(b"b", &*value_log.get_checked(&b, b"b")?.unwrap());
+
+    assert!(matches!(
+        value_log.get_checked(&a, b"b"),
+        Err(value_log::Error::KeyMismatch { segment_id, offset })
+            if segment_id == a.segment_id && offset == a.offset,
+    ));
+
+    // NOTE: The key is also returned for cached values
+    assert_eq!(2, blob_cache.len());
+    let (key, value) = value_log.get_entry(&b)?.unwrap();
+    assert_eq!(b"b", &*key);
+    assert_eq!(b"b", &*value);
+
+    blob_cache.clear();
+    let (key, value) = value_log.get_entry(&a)?.unwrap();
+    assert_eq!(b"a", &*key);
+    assert_eq!(b"a", &*value);
+
+    let unknown = ValueHandle {
+        segment_id: 1_000,
+        offset: 0,
+    };
+    assert!(value_log.get_entry(&unknown)?.is_none());
+    assert!(value_log.get_checked(&unknown, b"a")?.is_none());
+
+    Ok(())
+}
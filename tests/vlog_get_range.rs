@@ -0,0 +1,136 @@
+mod common;
+
+use common::{MockIndex, MockIndexWriter, NoCacher, NoCompressor};
+use std::sync::{
+    atomic::{AtomicUsize, Ordering},
+    Arc,
+};
+use test_log::test;
+use value_log::{
+    Compressor, Config, IndexReader, IndexWriter, LruBlobCache, ValueHandle, ValueLog,
+};
+
+/// Records the prefix length of the last partial decompression
+#[derive(Clone, Debug, Default)]
+struct Lz4Compressor(Arc<AtomicUsize>);
+impl Compressor for Lz4Compressor {
+    fn compress(&self, bytes: &[u8]) -> value_log::Result<Vec<u8>> {
+        Ok(lz4_flex::compress_prepend_size(bytes))
+    }
+
+    fn decompress(&self, bytes: &[u8]) -> value_log::Result<Vec<u8>> {
+        lz4_flex::decompress_size_prepended(bytes).map_err(|_| value_log::Error::Decompress)
+    }
+
+    fn decompress_prefix(&self, bytes: &[u8], len: usize) -> value_log::Result<Vec<u8>> {
+        self.0.store(len, Ordering::Relaxed);
+
+        let mut value = self.decompress(bytes)?;
+        value.truncate(len);
+        Ok(value)
+    }
+}
+
+fn value() -> Vec<u8> {
+    (0..1_000_000u32).map(|x| (x % 251) as u8).collect()
+}
+
+#[test]
+fn vlog_get_range() -> value_log::Result<()> {
+    let folder = tempfile::tempdir()?;
+    let vl_path = folder.path();
+
+    let blob_cache = LruBlobCache::with_shard_count(10_000_000, 1);
+
+    let index = MockIndex::default();
+
+    let value_log = ValueLog::open(
+        vl_path,
+        Config::<_, _, NoCompressor>::new(blob_cache.clone(), NoCacher),
+    )?;
+
+    let value = value();
+
+    let mut index_writer = MockIndexWriter(index.clone());
+    let mut writer = value_log.get_writer()?;
+
+    let vhandle = writer.get_next_value_handle();
+    index_writer.insert_indirect(b"a", vhandle.clone(), value.len() as u32)?;
+    writer.write(b"a", &value)?;
+
+    value_log.register_writer(writer)?;
+
+    let range = value_log.get_range(&vhandle, 500_000, 1_000)?.unwrap();
+    assert_eq!(value.get(500_000..501_000).unwrap(), &*range);
+
+    // NOTE: Ranges are clamped to the value's end
+    let range = value_log.get_range(&vhandle, 999_990, 1_000)?.unwrap();
+    assert_eq!(value.get(999_990..).unwrap(), &*range);
+
+    let range = value_log.get_range(&vhandle, 2_000_000, 1_000)?.unwrap();
+    assert!(range.is_empty());
+
+    let range = value_log.get_range(&vhandle, 0, u64::MAX)?.unwrap();
+    assert_eq!(value, &*range);
+
+    // NOTE: Partial values are not cached
+    assert_eq!(0, blob_cache.len());
+
+    // NOTE: Cached values are sliced
+    value_log.get(&vhandle)?.unwrap();
+    let range = value_log.get_range(&vhandle, 10, 20)?.unwrap();
+    assert_eq!(value.get(10..30).unwrap(), &*range);
+    assert_eq!(1, blob_cache.len());
+
+    assert!(value_log
+        .get_range(
+            &ValueHandle {
+                segment_id: 1_000,
+                offset: 0,
+            },
+            0,
+            10,
+        )?
+        .is_none());
+
+    Ok(())
+}
+
+#[test]
+fn vlog_get_range_compressed() -> value_log::Result<()> {
+    let folder = tempfile::tempdir()?;
+    let vl_path = folder.path();
+
+    let index = MockIndex::default();
+    let compressor = Lz4Compressor::default();
+
+    let value_log = ValueLog::open(
+        vl_path,
+        Config::<_, _, Lz4Compressor>::new(NoCacher, NoCacher)
+            .compression(Some(compressor.clone())),
+    )?;
+
+    let value = value();
+
+    let mut index_writer = MockIndexWriter(index.clone());
+    let mut writer = value_log.get_writer()?;
+
+    let vhandle = writer.get_next_value_handle();
+    index_writer.insert_indirect(b"a", vhandle.clone(), value.len() as u32)?;
+    writer.write(b"a", &value)?;
+
+    value_log.register_writer(writer)?;
+
+    let vhandle = index.get(b"a")?.unwrap();
+
+    let range = value_log.get_range(&vhandle, 1_000, 500)?.unwrap();
+    assert_eq!(value.get(1_000..1_500).unwrap(), &*range);
+
+    // NOTE: Only the prefix up to the end of the range needs to be decompressed
+    assert_eq!(1_500, compressor.0.load(Ordering::Relaxed));
+
+    let range = value_log.get_range(&vhandle, 999_000, 5_000)?.unwrap();
+    assert_eq!(value.get(999_000..).unwrap(), &*range);
+
+    Ok(())
+}
@@ -0,0 +1,420 @@
+mod common;
+
+use common::{copy_dir, write_kvs, MockIndex, MockIndexWriter, NoCacher, NoCompressor};
+use std::io::{Read, Seek, SeekFrom, Write};
+use test_log::test;
+use value_log::{Compressor, Config, IndexReader, Slice, ValueLog};
+
+#[derive(Clone, Debug, Default)]
+struct Lz4Compressor;
+impl Compressor for Lz4Compressor {
+    fn compress(&self, bytes: &[u8]) -> value_log::Result<Vec<u8>> {
+        Ok(lz4_flex::compress_prepend_size(bytes))
+    }
+
+    fn decompress(&self, bytes: &[u8]) -> value_log::Result<Vec<u8>> {
+        lz4_flex::decompress_size_prepended(bytes).map_err(|_| value_log::Error::Decompress)
+    }
+}
+
+const CHUNK_SIZE: u32 = 1_000;
+
+fn large_value() -> Vec<u8> {
+    (0..10_500u32).map(|x| (x % 251) as u8).collect()
+}
+
+/// Offset of the first chunk's value, relative to the chunk manifest of a value with a 1-byte key
+const FIRST_CHUNK_VALUE: u64 = (27 + 1 + 16) + (27 + 1);
+
+#[test]
+fn vlog_large_value() -> value_log::Result<()> {
+    let folder = tempfile::tempdir()?;
+    let vl_path = folder.path();
+
+    let index = MockIndex::default();
+
+    let value_log = ValueLog::open(
+        vl_path,
+        Config::<_, _, NoCompressor>::new(NoCacher, NoCacher).large_value_chunk_size(CHUNK_SIZE),
+    )?;
+
+    let value = large_value();
+
+    write_kvs(
+        &value_log,
+        &index,
+        &[(b"a", b"small"), (b"b", &value), (b"c", b"small")],
+    )?;
+
+    // NOTE: The large value is stored as its manifest, followed by 11 chunks,
+    // but counts as a single item
+    let vhandle = index.get(b"b")?.unwrap();
+    let segment = value_log.manifest.get_segment(vhandle.segment_id).unwrap();
+    assert_eq!(3, segment.meta.item_count);
+    assert_eq!(10_510, segment.meta.total_uncompressed_bytes);
+    assert_eq!(1, value_log.segment_count());
+
+    assert_eq!(value, &*value_log.get(&vhandle)?.unwrap());
+    assert_eq!(
+        b"small",
+        &*value_log.get(&index.get(b"c")?.unwrap())?.unwrap()
+    );
+
+    let values = value_log.multi_get(&[
+        index.get(b"a")?.unwrap(),
+        vhandle.clone(),
+        index.get(b"c")?.unwrap(),
+    ])?;
+    assert_eq!(value, &*values.get(1).cloned().flatten().unwrap());
+    assert_eq!(b"small", &*values.get(2).cloned().flatten().unwrap());
+
+    // NOTE: Ranges may span multiple chunks
+    let range = value_log.get_range(&vhandle, 900, 2_200)?.unwrap();
+    assert_eq!(value.get(900..3_100).unwrap(), &*range);
+
+    let range = value_log.get_range(&vhandle, 10_400, 1_000)?.unwrap();
+    assert_eq!(value.get(10_400..).unwrap(), &*range);
+
+    let stat = value_log.stat(&vhandle)?.unwrap();
+    assert_eq!(10_500, stat.disk_len);
+    assert_eq!(Some(10_500), stat.uncompressed_len);
+
+    let mut reader = value_log.open_value(&vhandle)?.unwrap();
+    assert_eq!(10_500, reader.len());
+
+    let mut read = vec![];
+    reader.read_to_end(&mut read)?;
+    assert_eq!(value, read);
+
+    reader.seek(SeekFrom::Start(5_500))?;
+    let mut buf = [0; 1_000];
+    reader.read_exact(&mut buf)?;
+    assert_eq!(value.get(5_500..6_500).unwrap(), buf);
+
+    assert!(value_log.verify_integrity()?.is_ok());
+
+    // NOTE: Index sizes of values larger than 4 GiB saturate,
+    // so the real length is taken from the chunk manifest
+    value_log.mark_handles_stale(&[(vhandle, u32::MAX)])?;
+    assert_eq!(1, segment.gc_stats.stale_items());
+    assert_eq!(10_500, segment.gc_stats.stale_bytes());
+
+    Ok(())
+}
+
+#[test]
+fn vlog_large_value_compressed() -> value_log::Result<()> {
+    let folder = tempfile::tempdir()?;
+    let vl_path = folder.path();
+
+    let index = MockIndex::default();
+
+    let value_log = ValueLog::open(
+        vl_path,
+        Config::<_, _, Lz4Compressor>::new(NoCacher, NoCacher)
+            .compression(Some(Lz4Compressor))
+            .large_value_chunk_size(CHUNK_SIZE),
+    )?;
+
+    let value = "verycompressable".repeat(1_000);
+
+    write_kvs(&value_log, &index, &[(b"a", value.as_bytes())])?;
+
+    let vhandle = index.get(b"a")?.unwrap();
+
+    assert_eq!(value.as_bytes(), &*value_log.get(&vhandle)?.unwrap());
+
+    let range = value_log.get_range(&vhandle, 4_990, 20)?.unwrap();
+    assert_eq!(value.as_bytes().get(4_990..5_010).unwrap(), &*range);
+
+    let stat = value_log.stat(&vhandle)?.unwrap();
+    assert_eq!(Some(value.len() as u64), stat.uncompressed_len);
+    assert!(stat.disk_len < value.len() as u64);
+
+    assert!(matches!(
+        value_log.open_value(&vhandle),
+        Err(value_log::Error::CompressedValue),
+    ));
+
+    Ok(())
+}
+
+#[test]
+fn vlog_large_value_compressed_without_compressor() -> value_log::Result<()> {
+    let folder = tempfile::tempdir()?;
+    let vl_path = folder.path();
+
+    let index = MockIndex::default();
+
+    {
+        let value_log = ValueLog::open(
+            vl_path,
+            Config::<_, _, Lz4Compressor>::new(NoCacher, NoCacher)
+                .compression(Some(Lz4Compressor))
+                .large_value_chunk_size(CHUNK_SIZE),
+        )?;
+
+        let value = "verycompressable".repeat(1_000);
+        write_kvs(&value_log, &index, &[(b"a", value.as_bytes())])?;
+    }
+
+    let value_log = ValueLog::open(
+        vl_path,
+        Config::<_, _, NoCompressor>::new(NoCacher, NoCacher).large_value_chunk_size(CHUNK_SIZE),
+    )?;
+
+    let vhandle = index.get(b"a")?.unwrap();
+
+    assert!(matches!(
+        value_log.get(&vhandle),
+        Err(value_log::Error::Decompress)
+    ));
+    assert!(matches!(
+        value_log.get_range(&vhandle, 4_990, 20),
+        Err(value_log::Error::Decompress)
+    ));
+
+    Ok(())
+}
+
+#[test]
+fn vlog_large_value_gc() -> value_log::Result<()> {
+    let folder = tempfile::tempdir()?;
+    let vl_path = folder.path();
+
+    let index = MockIndex::default();
+
+    // NOTE: Segments are rotated once they exceed 100 bytes, but never in between chunks
+    let value_log = ValueLog::open(
+        vl_path,
+        Config::<_, _, NoCompressor>::new(NoCacher, NoCacher)
+            .segment_size_bytes(100)
+            .large_value_chunk_size(CHUNK_SIZE),
+    )?;
+
+    let value = large_value();
+    let new_value = value.iter().rev().copied().collect::<Vec<_>>();
+
+    write_kvs(&value_log, &index, &[(b"a", b"small"), (b"b", &value)])?;
+    write_kvs(&value_log, &index, &[(b"b", &new_value), (b"c", b"small")])?;
+    assert_eq!(3, value_log.segment_count());
+
+    // NOTE: Only the old version of b is stale
+    let report = value_log.scan_for_stats(index.read().unwrap().values().cloned().map(Ok))?;
+    assert_eq!(4, report.total_blobs);
+    assert_eq!(1, report.stale_blobs);
+    assert_eq!(10_500, report.stale_bytes);
+
+    value_log.major_compact(&index, MockIndexWriter(index.clone()))?;
+    value_log.drop_stale_segments()?;
+    assert_eq!(2, value_log.segment_count());
+
+    // NOTE: a, followed by the manifest and chunks of b
+    let vhandle = index.get(b"b")?.unwrap();
+    let segment = value_log.manifest.get_segment(vhandle.segment_id).unwrap();
+    assert_eq!(2, segment.meta.item_count);
+    assert_eq!(10_505, segment.meta.total_uncompressed_bytes);
+
+    assert_eq!(new_value, &*value_log.get(&vhandle)?.unwrap());
+
+    for key in [b"a", b"c"] {
+        let vhandle = index.get(key)?.unwrap();
+        assert_eq!(b"small", &*value_log.get(&vhandle)?.unwrap());
+    }
+
+    assert!(value_log.verify_integrity()?.is_ok());
+
+    Ok(())
+}
+
+#[test]
+fn vlog_large_value_checksum_mismatch() -> value_log::Result<()> {
+    let folder = tempfile::tempdir()?;
+    let vl_path = folder.path();
+
+    let index = MockIndex::default();
+
+    let value_log = ValueLog::open(
+        vl_path,
+        Config::<_, _, NoCompressor>::new(NoCacher, NoCacher).large_value_chunk_size(CHUNK_SIZE),
+    )?;
+
+    let value = large_value();
+
+    write_kvs(&value_log, &index, &[(b"a", &value)])?;
+
+    let vhandle = index.get(b"a")?.unwrap();
+
+    // Flip a byte in the third chunk
+    {
+        let path = vl_path
+            .join("segments")
+            .join(vhandle.segment_id.to_string());
+        let mut file = std::fs::OpenOptions::new().write(true).open(path)?;
+        file.seek(SeekFrom::Start(
+            vhandle.offset + FIRST_CHUNK_VALUE + 2 * (27 + 1 + 1_000) + 10,
+        ))?;
+        file.write_all(b"x")?;
+        file.sync_all()?;
+    }
+
+    assert!(matches!(
+        value_log.get(&vhandle),
+        Err(value_log::Error::ChecksumMismatch { segment_id, offset })
+            if segment_id == vhandle.segment_id
+                && offset == vhandle.offset + (27 + 1 + 16) + 2 * (27 + 1 + 1_000),
+    ));
+
+    let mut reader = value_log.open_value(&vhandle)?.unwrap();
+    let err = reader.read_to_end(&mut vec![]).unwrap_err();
+    assert_eq!(std::io::ErrorKind::InvalidData, err.kind());
+
+    // NOTE: Only the chunks overlapping the range are read
+    let range = value_log.get_range(&vhandle, 0, 1_000)?.unwrap();
+    assert_eq!(value.get(..1_000).unwrap(), &*range);
+
+    Ok(())
+}
+
+#[test]
+fn vlog_large_value_corrupt_manifest_len() -> value_log::Result<()> {
+    let folder = tempfile::tempdir()?;
+    let vl_path = folder.path();
+
+    let index = MockIndex::default();
+
+    // NOTE: Disable checksums, so the corrupt manifest is actually parsed
+    let value_log = ValueLog::open(
+        vl_path,
+        Config::<_, _, NoCompressor>::new(NoCacher, NoCacher)
+            .large_value_chunk_size(CHUNK_SIZE)
+            .verify_checksums(false),
+    )?;
+
+    write_kvs(&value_log, &index, &[(b"a", &large_value())])?;
+
+    let vhandle = index.get(b"a")?.unwrap();
+
+    let path = vl_path
+        .join("segments")
+        .join(vhandle.segment_id.to_string());
+
+    for len in [1u64 << 40, 20_000] {
+        {
+            let mut file = std::fs::OpenOptions::new().write(true).open(&path)?;
+            file.seek(SeekFrom::Start(vhandle.offset + 27 + 1))?;
+            file.write_all(&len.to_be_bytes())?;
+            file.sync_all()?;
+        }
+
+        assert!(matches!(
+            value_log.get(&vhandle),
+            Err(value_log::Error::Decode(_)),
+        ));
+    }
+
+    Ok(())
+}
+
+#[test]
+fn vlog_large_value_repair() -> value_log::Result<()> {
+    let folder = tempfile::tempdir()?;
+    let vl_path = folder.path();
+
+    let index = MockIndex::default();
+
+    let config =
+        || Config::<_, _, NoCompressor>::new(NoCacher, NoCacher).large_value_chunk_size(CHUNK_SIZE);
+
+    let value = large_value();
+
+    {
+        let value_log = ValueLog::open(vl_path, config())?;
+
+        write_kvs(
+            &value_log,
+            &index,
+            &[(b"a", b"small"), (b"b", &value), (b"c", &value)],
+        )?;
+    }
+
+    // Corrupt the first chunk of c
+    {
+        let vhandle = index.get(b"c")?.unwrap();
+
+        let path = vl_path
+            .join("segments")
+            .join(vhandle.segment_id.to_string());
+        let mut file = std::fs::OpenOptions::new().write(true).open(path)?;
+        file.seek(SeekFrom::Start(vhandle.offset + FIRST_CHUNK_VALUE + 10))?;
+        file.write_all(b"x")?;
+        file.sync_all()?;
+    }
+
+    let report = ValueLog::repair(vl_path, &config(), &index, MockIndexWriter(index.clone()))?;
+
+    assert_eq!(2, report.salvaged_blobs);
+    assert_eq!(vec![Slice::from(*b"c")], report.lost_keys);
+
+    let value_log = ValueLog::open(vl_path, config())?;
+    assert!(value_log.verify_integrity()?.is_ok());
+
+    let vhandle = index.get(b"b")?.unwrap();
+    assert_eq!(value, &*value_log.get(&vhandle)?.unwrap());
+
+    Ok(())
+}
+
+#[test]
+#[cfg(feature = "mmap")]
+fn vlog_large_value_mmap() -> value_log::Result<()> {
+    let folder = tempfile::tempdir()?;
+    let vl_path = folder.path();
+
+    let index = MockIndex::default();
+
+    let value_log = ValueLog::open(
+        vl_path,
+        Config::<_, _, NoCompressor>::new(NoCacher, NoCacher)
+            .use_mmap(true)
+            .large_value_chunk_size(CHUNK_SIZE),
+    )?;
+
+    let value = large_value();
+
+    write_kvs(&value_log, &index, &[(b"a", &value), (b"b", b"small")])?;
+
+    let vhandle = index.get(b"a")?.unwrap();
+    assert_eq!(value, &*value_log.get(&vhandle)?.unwrap());
+
+    let values = value_log.multi_get(&[vhandle, index.get(b"b")?.unwrap()])?;
+    assert_eq!(b"small", &*values.get(1).cloned().flatten().unwrap());
+
+    Ok(())
+}
+
+#[test]
+fn vlog_large_value_v1_not_chunked() -> value_log::Result<()> {
+    let folder = tempfile::tempdir()?;
+    let vl_path = folder.path();
+
+    copy_dir(std::path::Path::new("test_fixture/v1_vlog"), vl_path)?;
+
+    let index = MockIndex::default();
+
+    let value_log = ValueLog::open(
+        vl_path,
+        Config::<_, _, NoCompressor>::new(NoCacher, NoCacher).large_value_chunk_size(CHUNK_SIZE),
+    )?;
+
+    let value = large_value();
+    write_kvs(&value_log, &index, &[(b"a", &value)])?;
+
+    let vhandle = index.get(b"a")?.unwrap();
+    assert_eq!(&*value_log.get(&vhandle)?.unwrap(), &*value);
+
+    let segment = value_log.manifest.get_segment(vhandle.segment_id).unwrap();
+    assert_eq!(1, segment.meta.item_count);
+
+    Ok(())
+}
@@ -0,0 +1,55 @@
+mod common;
+
+use common::{NoCacher, NoCompressor};
+use test_log::test;
+use value_log::{Config, ValueLog};
+
+#[test]
+fn vlog_lock() -> value_log::Result<()> {
+    let folder = tempfile::tempdir()?;
+    let vl_path = folder.path();
+
+    {
+        let value_log = ValueLog::open(
+            vl_path,
+            Config::<_, _, NoCompressor>::new(NoCacher, NoCacher),
+        )?;
+
+        assert!(matches!(
+            ValueLog::open(
+                vl_path,
+                Config::<_, _, NoCompressor>::new(NoCacher, NoCacher),
+            ),
+            Err(value_log::Error::Locked),
+        ));
+
+        // NOTE: Read-only value logs do not need the lock
+        let read_only = ValueLog::open_read_only(
+            vl_path,
+            Config::<_, _, NoCompressor>::new(NoCacher, NoCacher),
+        )?;
+        assert_eq!(0, read_only.segment_count());
+
+        // NOTE: Clones share the same lock
+        let clone = value_log.clone();
+        drop(value_log);
+
+        assert!(matches!(
+            ValueLog::open(
+                vl_path,
+                Config::<_, _, NoCompressor>::new(NoCacher, NoCacher),
+            ),
+            Err(value_log::Error::Locked),
+        ));
+
+        drop(clone);
+    }
+
+    // NOTE: The lock is released once the value log is dropped
+    let _value_log = ValueLog::open(
+        vl_path,
+        Config::<_, _, NoCompressor>::new(NoCacher, NoCacher),
+    )?;
+
+    Ok(())
+}
@@ -0,0 +1,169 @@
+#![cfg(feature = "mmap")]
+
+mod common;
+
+use common::{MockIndex, MockIndexWriter, NoCacher, NoCompressor};
+use std::io::{Seek, SeekFrom, Write};
+use test_log::test;
+use value_log::{Compressor, Config, IndexReader, IndexWriter, LruBlobCache, ValueLog};
+
+#[derive(Clone, Debug, Default)]
+struct Lz4Compressor;
+impl Compressor for Lz4Compressor {
+    fn compress(&self, bytes: &[u8]) -> value_log::Result<Vec<u8>> {
+        Ok(lz4_flex::compress_prepend_size(bytes))
+    }
+
+    fn decompress(&self, bytes: &[u8]) -> value_log::Result<Vec<u8>> {
+        lz4_flex::decompress_size_prepended(bytes).map_err(|_| value_log::Error::Decompress)
+    }
+}
+
+#[test]
+fn vlog_mmap_read() -> value_log::Result<()> {
+    let folder = tempfile::tempdir()?;
+    let vl_path = folder.path();
+
+    let index = MockIndex::default();
+    let blob_cache = LruBlobCache::with_capacity_bytes(1_000_000);
+
+    let value_log = ValueLog::open(
+        vl_path,
+        Config::<_, _, NoCompressor>::new(blob_cache.clone(), NoCacher).use_mmap(true),
+    )?;
+
+    let items = ["a", "b", "c", "d", "e"];
+
+    {
+        let mut index_writer = MockIndexWriter(index.clone());
+        let mut writer = value_log.get_writer()?;
+
+        for key in &items {
+            let value = key.repeat(1_000);
+
+            let vhandle = writer.get_next_value_handle();
+            index_writer.insert_indirect(key.as_bytes(), vhandle, value.len() as u32)?;
+            writer.write(key.as_bytes(), value.as_bytes())?;
+        }
+
+        value_log.register_writer(writer)?;
+    }
+
+    for key in &items {
+        let vhandle = index.get(key.as_bytes())?.unwrap();
+        let value = value_log.get(&vhandle)?.unwrap();
+        assert_eq!(&*value, key.repeat(1_000).as_bytes());
+    }
+
+    // NOTE: Prefetching stops at the end of the segment
+    blob_cache.clear();
+    let vhandle = index.get(b"c")?.unwrap();
+    let value = value_log.get_with_prefetch(&vhandle, 10)?.unwrap();
+    assert_eq!(&*value, "c".repeat(1_000).as_bytes());
+    assert_eq!(3, blob_cache.len());
+
+    // NOTE: Segments that are dropped are unmapped and deleted
+    value_log.major_compact(&index, MockIndexWriter(index.clone()))?;
+    value_log.drop_stale_segments()?;
+    assert_eq!(1, value_log.segment_count());
+    assert_eq!(1, std::fs::read_dir(vl_path.join("segments"))?.count());
+
+    for key in &items {
+        let vhandle = index.get(key.as_bytes())?.unwrap();
+        let value = value_log.get(&vhandle)?.unwrap();
+        assert_eq!(&*value, key.repeat(1_000).as_bytes());
+    }
+
+    Ok(())
+}
+
+#[test]
+fn vlog_mmap_compression() -> value_log::Result<()> {
+    let folder = tempfile::tempdir()?;
+    let vl_path = folder.path();
+
+    let index = MockIndex::default();
+
+    let value_log = ValueLog::open(
+        vl_path,
+        Config::<_, _, Lz4Compressor>::new(NoCacher, NoCacher)
+            .compression(Some(Lz4Compressor))
+            .use_mmap(true),
+    )?;
+
+    let value = "verycompressable".repeat(10);
+
+    let mut index_writer = MockIndexWriter(index.clone());
+    let mut writer = value_log.get_writer()?;
+
+    let vhandle = writer.get_next_value_handle();
+    index_writer.insert_indirect(b"abc", vhandle.clone(), value.len() as u32)?;
+    writer.write(b"abc", &value)?;
+
+    value_log.register_writer(writer)?;
+
+    assert_eq!(&*value_log.get(&vhandle)?.unwrap(), value.as_bytes());
+
+    Ok(())
+}
+
+#[test]
+fn vlog_mmap_checksum_mismatch() -> value_log::Result<()> {
+    let folder = tempfile::tempdir()?;
+    let vl_path = folder.path();
+
+    let index = MockIndex::default();
+
+    {
+        let value_log = ValueLog::open(
+            vl_path,
+            Config::<_, _, NoCompressor>::new(NoCacher, NoCacher),
+        )?;
+
+        let mut index_writer = MockIndexWriter(index.clone());
+        let mut writer = value_log.get_writer()?;
+
+        for key in ["a", "b"] {
+            let value = key.repeat(1_000);
+
+            let vhandle = writer.get_next_value_handle();
+            index_writer.insert_indirect(key.as_bytes(), vhandle, value.len() as u32)?;
+            writer.write(key.as_bytes(), value.as_bytes())?;
+        }
+
+        value_log.register_writer(writer)?;
+    }
+
+    let vhandle = index.get(b"b")?.unwrap();
+
+    // Flip the last byte of the value of "b"
+    {
+        let path = vl_path
+            .join("segments")
+            .join(vhandle.segment_id.to_string());
+        let mut file = std::fs::OpenOptions::new().write(true).open(path)?;
+        let value_end = vhandle.offset + 27 + 1 + 1_000;
+        file.seek(SeekFrom::Start(value_end - 1))?;
+        file.write_all(b"x")?;
+        file.sync_all()?;
+    }
+
+    let value_log = ValueLog::open(
+        vl_path,
+        Config::<_, _, NoCompressor>::new(NoCacher, NoCacher).use_mmap(true),
+    )?;
+
+    assert!(matches!(
+        value_log.get(&vhandle),
+        Err(value_log::Error::ChecksumMismatch { segment_id, offset })
+        if segment_id == vhandle.segment_id && offset == vhandle.offset,
+    ));
+
+    let vhandle = index.get(b"a")?.unwrap();
+    assert_eq!(
+        &*value_log.get(&vhandle)?.unwrap(),
+        "a".repeat(1_000).as_bytes()
+    );
+
+    Ok(())
+}
@@ -0,0 +1,79 @@
+mod common;
+
+use common::{MockIndex, MockIndexWriter, NoCompressor};
+use test_log::test;
+use value_log::{
+    Config, IndexReader, IndexWriter, LruBlobCache, LruFDCache, ValueHandle, ValueLog,
+};
+
+#[test]
+fn vlog_multi_get() -> value_log::Result<()> {
+    let folder = tempfile::tempdir()?;
+    let vl_path = folder.path();
+
+    let blob_cache = LruBlobCache::with_capacity_bytes(1_000_000);
+    let fd_cache = LruFDCache::with_capacity(10);
+
+    let index = MockIndex::default();
+
+    let value_log = ValueLog::open(
+        vl_path,
+        Config::<_, _, NoCompressor>::new(blob_cache.clone(), fd_cache.clone()),
+    )?;
+
+    let segments = [["a", "b", "c", "d"], ["e", "f", "g", "h"]];
+
+    for keys in &segments {
+        let mut index_writer = MockIndexWriter(index.clone());
+        let mut writer = value_log.get_writer()?;
+
+        for key in keys {
+            let value = key.repeat(1_000);
+
+            let vhandle = writer.get_next_value_handle();
+            index_writer.insert_indirect(key.as_bytes(), vhandle, value.len() as u32)?;
+            writer.write(key.as_bytes(), value.as_bytes())?;
+        }
+
+        value_log.register_writer(writer)?;
+    }
+
+    // NOTE: Warm up the cache for one of the keys
+    let vhandle = index.get(b"g")?.unwrap();
+    value_log.get(&vhandle)?.unwrap();
+    let cache_hits = blob_cache.hits();
+
+    let keys = ["h", "a", "g", "c", "e", "b", "a", "f"];
+
+    let mut vhandles = keys
+        .iter()
+        .map(|key| index.get(key.as_bytes()).map(Option::unwrap))
+        .collect::<std::io::Result<Vec<_>>>()?;
+
+    vhandles.push(ValueHandle {
+        segment_id: 1_000,
+        offset: 0,
+    });
+
+    let values = value_log.multi_get(&vhandles)?;
+    assert_eq!(vhandles.len(), values.len());
+
+    // NOTE: Values are returned in input order
+    for (key, value) in keys.iter().zip(&values) {
+        assert_eq!(value.as_deref(), Some(key.repeat(1_000).as_bytes()));
+    }
+
+    // NOTE: Handles of unknown segments resolve to nothing
+    assert!(values.last().unwrap().is_none());
+
+    // NOTE: Each segment file is opened once
+    assert_eq!(2, fd_cache.misses());
+    assert_eq!(cache_hits + 1, blob_cache.hits());
+
+    // NOTE: All values are cached afterwards
+    assert_eq!(7, blob_cache.len());
+
+    assert!(value_log.multi_get(&[])?.is_empty());
+
+    Ok(())
+}
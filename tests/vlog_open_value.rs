@@ -0,0 +1,212 @@
+mod common;
+
+use common::{MockIndex, MockIndexWriter, NoCacher, NoCompressor};
+use std::io::{Read, Seek, SeekFrom, Write};
+use test_log::test;
+use value_log::{Compressor, Config, IndexReader, IndexWriter, ValueHandle, ValueLog};
+
+#[derive(Clone, Debug, Default)]
+struct Lz4Compressor;
+impl Compressor for Lz4Compressor {
+    fn compress(&self, bytes: &[u8]) -> value_log::Result<Vec<u8>> {
+        Ok(lz4_flex::compress_prepend_size(bytes))
+    }
+
+    fn decompress(&self, bytes: &[u8]) -> value_log::Result<Vec<u8>> {
+        lz4_flex::decompress_size_prepended(bytes).map_err(|_| value_log::Error::Decompress)
+    }
+}
+
+fn large_value() -> Vec<u8> {
+    (0..4_000_000u32).map(|x| (x % 251) as u8).collect()
+}
+
+#[test]
+fn vlog_open_value() -> value_log::Result<()> {
+    let folder = tempfile::tempdir()?;
+    let vl_path = folder.path();
+
+    let index = MockIndex::default();
+
+    let value_log = ValueLog::open(
+        vl_path,
+        Config::<_, _, NoCompressor>::new(NoCacher, NoCacher),
+    )?;
+
+    let value = large_value();
+
+    {
+        let mut index_writer = MockIndexWriter(index.clone());
+        let mut writer = value_log.get_writer()?;
+
+        for (key, value) in [(b"a", b"small".as_slice()), (b"b", &value), (b"c", b"")] {
+            let vhandle = writer.get_next_value_handle();
+            index_writer.insert_indirect(key, vhandle, value.len() as u32)?;
+            writer.write(key, value)?;
+        }
+
+        value_log.register_writer(writer)?;
+    }
+
+    let vhandle = index.get(b"b")?.unwrap();
+    let mut reader = value_log.open_value(&vhandle)?.unwrap();
+    assert_eq!(value.len() as u64, reader.len());
+
+    // NOTE: Read the value in small chunks
+    let mut buf = [0; 4_096];
+    let mut read = vec![];
+
+    loop {
+        let n = reader.read(&mut buf)?;
+        if n == 0 {
+            break;
+        }
+        read.extend_from_slice(buf.get(..n).unwrap());
+    }
+    assert_eq!(value, read);
+
+    reader.seek(SeekFrom::Start(1_000_000))?;
+    let mut buf = [0; 10];
+    reader.read_exact(&mut buf)?;
+    assert_eq!(value.get(1_000_000..1_000_010).unwrap(), buf);
+
+    reader.seek(SeekFrom::End(-5))?;
+    let mut rest = vec![];
+    reader.read_to_end(&mut rest)?;
+    assert_eq!(value.get(value.len() - 5..).unwrap(), rest);
+
+    let vhandle = index.get(b"a")?.unwrap();
+    let mut reader = value_log.open_value(&vhandle)?.unwrap();
+    let mut read = vec![];
+    reader.read_to_end(&mut read)?;
+    assert_eq!(b"small", &*read);
+
+    let vhandle = index.get(b"c")?.unwrap();
+    let mut reader = value_log.open_value(&vhandle)?.unwrap();
+    assert!(reader.is_empty());
+    let mut read = vec![];
+    reader.read_to_end(&mut read)?;
+    assert!(read.is_empty());
+
+    assert!(value_log
+        .open_value(&ValueHandle {
+            segment_id: 1_000,
+            offset: 0,
+        })?
+        .is_none());
+
+    // NOTE: The reader keeps its segment alive, even if the segment is dropped
+    let vhandle = index.get(b"b")?.unwrap();
+    let mut reader = value_log.open_value(&vhandle)?.unwrap();
+
+    value_log.major_compact(&index, MockIndexWriter(index.clone()))?;
+    value_log.drop_stale_segments()?;
+    assert_ne!(vhandle, index.get(b"b")?.unwrap());
+
+    let mut read = vec![];
+    reader.read_to_end(&mut read)?;
+    assert_eq!(value, read);
+
+    Ok(())
+}
+
+#[test]
+fn vlog_open_value_compressed() -> value_log::Result<()> {
+    let folder = tempfile::tempdir()?;
+    let vl_path = folder.path();
+
+    let index = MockIndex::default();
+
+    let value_log = ValueLog::open(
+        vl_path,
+        Config::<_, _, Lz4Compressor>::new(NoCacher, NoCacher).compression(Some(Lz4Compressor)),
+    )?;
+
+    let value = "verycompressable".repeat(10);
+
+    let mut index_writer = MockIndexWriter(index.clone());
+    let mut writer = value_log.get_writer()?;
+
+    let vhandle = writer.get_next_value_handle();
+    index_writer.insert_indirect(b"a", vhandle.clone(), value.len() as u32)?;
+    writer.write(b"a", &value)?;
+
+    value_log.register_writer(writer)?;
+
+    assert!(matches!(
+        value_log.open_value(&vhandle),
+        Err(value_log::Error::CompressedValue),
+    ));
+
+    Ok(())
+}
+
+#[test]
+fn vlog_open_value_checksum_mismatch() -> value_log::Result<()> {
+    let folder = tempfile::tempdir()?;
+    let vl_path = folder.path();
+
+    let index = MockIndex::default();
+
+    let value = large_value();
+
+    {
+        let value_log = ValueLog::open(
+            vl_path,
+            Config::<_, _, NoCompressor>::new(NoCacher, NoCacher),
+        )?;
+
+        let mut index_writer = MockIndexWriter(index.clone());
+        let mut writer = value_log.get_writer()?;
+
+        let vhandle = writer.get_next_value_handle();
+        index_writer.insert_indirect(b"a", vhandle, value.len() as u32)?;
+        writer.write(b"a", &value)?;
+
+        value_log.register_writer(writer)?;
+    }
+
+    let vhandle = index.get(b"a")?.unwrap();
+
+    // Flip the last byte of the value
+    {
+        let path = vl_path
+            .join("segments")
+            .join(vhandle.segment_id.to_string());
+        let mut file = std::fs::OpenOptions::new().write(true).open(path)?;
+        let value_end = vhandle.offset + 27 + 1 + value.len() as u64;
+        file.seek(SeekFrom::Start(value_end - 1))?;
+        file.write_all(b"x")?;
+        file.sync_all()?;
+    }
+
+    {
+        let value_log = ValueLog::open(
+            vl_path,
+            Config::<_, _, NoCompressor>::new(NoCacher, NoCacher),
+        )?;
+
+        let mut reader = value_log.open_value(&vhandle)?.unwrap();
+        let err = reader.read_to_end(&mut vec![]).unwrap_err();
+        assert_eq!(std::io::ErrorKind::InvalidData, err.kind());
+
+        // NOTE: Seeking disables verification
+        let mut reader = value_log.open_value(&vhandle)?.unwrap();
+        reader.seek(SeekFrom::Start(1))?;
+        reader.read_to_end(&mut vec![])?;
+    }
+
+    {
+        let value_log = ValueLog::open(
+            vl_path,
+            Config::<_, _, NoCompressor>::new(NoCacher, NoCacher).verify_checksums(false),
+        )?;
+
+        let mut reader = value_log.open_value(&vhandle)?.unwrap();
+        let mut read = vec![];
+        reader.read_to_end(&mut read)?;
+        assert_eq!(Some(&b'x'), read.last());
+    }
+
+    Ok(())
+}
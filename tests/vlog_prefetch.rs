@@ -0,0 +1,187 @@
+mod common;
+
+use common::{write_kvs, MockIndex, MockIndexWriter, NoCacher, NoCompressor};
+use test_log::test;
+use value_log::{
+    Config, IndexReader, IndexWriter, LruBlobCache, Prefetch, ValueLog, DEFAULT_READAHEAD,
+};
+
+#[test]
+fn vlog_prefetch() -> value_log::Result<()> {
+    let folder = tempfile::tempdir()?;
+    let vl_path = folder.path();
+
+    let blob_cache = LruBlobCache::with_capacity_bytes(1_000_000);
+
+    let index = MockIndex::default();
+
+    let value_log = ValueLog::open(
+        vl_path,
+        Config::<_, _, NoCompressor>::new(blob_cache.clone(), NoCacher),
+    )?;
+
+    let keys = (0..1_000u16).map(u16::to_be_bytes).collect::<Vec<_>>();
+
+    {
+        let mut index_writer = MockIndexWriter(index.clone());
+        let mut writer = value_log.get_writer()?;
+
+        for key in &keys {
+            let vhandle = writer.get_next_value_handle();
+            index_writer.insert_indirect(key, vhandle, 100)?;
+            writer.write(key, [b'x'; 100])?;
+        }
+
+        value_log.register_writer(writer)?;
+    }
+
+    let first = index.get(keys.first().unwrap())?.unwrap();
+    let blob_len = index.get(keys.get(1).unwrap())?.unwrap().offset - first.offset;
+
+    value_log
+        .get_with_prefetch(&first, Prefetch::None)?
+        .unwrap();
+    assert_eq!(1, blob_cache.len());
+
+    blob_cache.clear();
+    value_log.get_with_prefetch(&first, 5)?.unwrap();
+    assert_eq!(6, blob_cache.len());
+
+    // NOTE: The blob crossing the limit is cached as well
+    blob_cache.clear();
+    value_log.get_with_prefetch(&first, Prefetch::Bytes(blob_len * 7 + 1))?;
+    assert_eq!(9, blob_cache.len());
+
+    // NOTE: Only blobs that fit into the read buffer are cached
+    blob_cache.clear();
+    value_log.get_with_prefetch(&first, Prefetch::Buffered)?;
+    assert_eq!(DEFAULT_READAHEAD as u64 / blob_len, blob_cache.len() as u64);
+
+    blob_cache.clear();
+    value_log.get_with_readahead(&first, Prefetch::Buffered, (blob_len * 10) as usize)?;
+    assert_eq!(10, blob_cache.len());
+
+    blob_cache.clear();
+    value_log.get_with_readahead(&first, Prefetch::Buffered, 64 * 1_024)?;
+    assert_eq!(64 * 1_024 / blob_len, blob_cache.len() as u64);
+
+    // NOTE: Prefetching stops at the end of the segment
+    blob_cache.clear();
+    let last = index.get(keys.get(997).unwrap())?.unwrap();
+    value_log.get_with_prefetch(&last, Prefetch::Bytes(u64::MAX))?;
+    assert_eq!(3, blob_cache.len());
+
+    for key in &keys {
+        let vhandle = index.get(key)?.unwrap();
+        assert_eq!(&*value_log.get(&vhandle)?.unwrap(), [b'x'; 100]);
+    }
+
+    Ok(())
+}
+
+#[test]
+fn vlog_prefetch_large_value() -> value_log::Result<()> {
+    let folder = tempfile::tempdir()?;
+    let vl_path = folder.path();
+
+    let blob_cache = LruBlobCache::with_capacity_bytes(1_000_000);
+
+    let index = MockIndex::default();
+
+    let value_log = ValueLog::open(
+        vl_path,
+        Config::<_, _, NoCompressor>::new(blob_cache.clone(), NoCacher)
+            .large_value_chunk_size(1_000),
+    )?;
+
+    let large = vec![b'y'; 10_500];
+    write_kvs(
+        &value_log,
+        &index,
+        &[(b"a", b"small"), (b"b", &large), (b"c", b"small")],
+    )?;
+
+    let first = index.get(b"a")?.unwrap();
+
+    // NOTE: Prefetching stops at the large value, so its chunks are not read
+    for prefetch in [
+        Prefetch::Count(10),
+        Prefetch::Bytes(u64::MAX),
+        Prefetch::Buffered,
+    ] {
+        blob_cache.clear();
+        value_log.get_with_prefetch(&first, prefetch)?.unwrap();
+        assert_eq!(1, blob_cache.len());
+    }
+
+    let vhandle = index.get(b"b")?.unwrap();
+    assert_eq!(&*value_log.get(&vhandle)?.unwrap(), large);
+
+    #[cfg(feature = "mmap")]
+    {
+        drop(value_log);
+
+        let value_log = ValueLog::open(
+            vl_path,
+            Config::<_, _, NoCompressor>::new(blob_cache.clone(), NoCacher)
+                .large_value_chunk_size(1_000)
+                .use_mmap(true),
+        )?;
+
+        for prefetch in [Prefetch::Count(10), Prefetch::Bytes(u64::MAX)] {
+            blob_cache.clear();
+            value_log.get_with_prefetch(&first, prefetch)?.unwrap();
+            assert_eq!(1, blob_cache.len());
+        }
+    }
+
+    Ok(())
+}
+
+#[test]
+#[cfg(feature = "mmap")]
+fn vlog_prefetch_mmap() -> value_log::Result<()> {
+    let folder = tempfile::tempdir()?;
+    let vl_path = folder.path();
+
+    let blob_cache = LruBlobCache::with_capacity_bytes(1_000_000);
+
+    let index = MockIndex::default();
+
+    let value_log = ValueLog::open(
+        vl_path,
+        Config::<_, _, NoCompressor>::new(blob_cache.clone(), NoCacher).use_mmap(true),
+    )?;
+
+    let keys = (0..1_000u16).map(u16::to_be_bytes).collect::<Vec<_>>();
+
+    {
+        let mut index_writer = MockIndexWriter(index.clone());
+        let mut writer = value_log.get_writer()?;
+
+        for key in &keys {
+            let vhandle = writer.get_next_value_handle();
+            index_writer.insert_indirect(key, vhandle, 100)?;
+            writer.write(key, [b'x'; 100])?;
+        }
+
+        value_log.register_writer(writer)?;
+    }
+
+    let first = index.get(keys.first().unwrap())?.unwrap();
+    let blob_len = index.get(keys.get(1).unwrap())?.unwrap().offset - first.offset;
+
+    value_log.get_with_prefetch(&first, 5)?.unwrap();
+    assert_eq!(6, blob_cache.len());
+
+    blob_cache.clear();
+    value_log.get_with_prefetch(&first, Prefetch::Bytes(blob_len * 7 + 1))?;
+    assert_eq!(9, blob_cache.len());
+
+    // NOTE: Without a read buffer, the blobs within the readahead are cached
+    blob_cache.clear();
+    value_log.get_with_readahead(&first, Prefetch::Buffered, (blob_len * 10) as usize)?;
+    assert_eq!(10, blob_cache.len());
+
+    Ok(())
+}
@@ -0,0 +1,150 @@
+mod common;
+
+use common::{write_items, MockIndex, MockIndexWriter, NoCacher, NoCompressor};
+use test_log::test;
+use value_log::{Config, IndexReader, ValueLog};
+
+#[test]
+fn vlog_snapshot_defers_segment_deletion() -> value_log::Result<()> {
+    let folder = tempfile::tempdir()?;
+    let vl_path = folder.path();
+    let segment_path = vl_path.join("segments").join("0");
+
+    let index = MockIndex::default();
+
+    let value_log = ValueLog::open(
+        vl_path,
+        Config::<_, _, NoCompressor>::new(NoCacher, NoCacher),
+    )?;
+
+    let items = ["a", "b", "c", "d", "e"];
+    write_items(&value_log, &index, &items, 10_000)?;
+
+    let snapshot = value_log.snapshot();
+    assert_eq!(1, snapshot.segment_count());
+
+    let mut old_handles = vec![];
+    for key in items {
+        old_handles.push(index.get(key.as_bytes())?.unwrap());
+    }
+
+    value_log.major_compact(&index, MockIndexWriter(index.clone()))?;
+    assert!(value_log.drop_stale_segments()? > 0);
+    assert_eq!(value_log.manifest.list_segment_ids(), [1]);
+
+    // NOTE: The snapshot still references segment 0
+    assert!(segment_path.try_exists()?);
+
+    for (key, vhandle) in items.iter().zip(&old_handles) {
+        assert_eq!(0, vhandle.segment_id);
+        assert!(value_log.get(vhandle)?.is_none());
+
+        let value = snapshot.get(vhandle)?.unwrap();
+        assert_eq!(&*value, key.repeat(10_000).as_bytes());
+    }
+
+    // NOTE: New snapshots do not see the dropped segment
+    let new_snapshot = value_log.snapshot();
+    assert_eq!(1, new_snapshot.segment_count());
+    assert!(new_snapshot.get(&old_handles[0])?.is_none());
+
+    drop(snapshot);
+    assert!(!segment_path.try_exists()?);
+
+    for key in items {
+        let vhandle = index.get(key.as_bytes())?.unwrap();
+        let value = new_snapshot.get(&vhandle)?.unwrap();
+        assert_eq!(&*value, key.repeat(10_000).as_bytes());
+    }
+
+    Ok(())
+}
+
+#[test]
+fn vlog_snapshot_read_apis() -> value_log::Result<()> {
+    let folder = tempfile::tempdir()?;
+    let vl_path = folder.path();
+
+    let index = MockIndex::default();
+
+    let value_log = ValueLog::open(
+        vl_path,
+        Config::<_, _, NoCompressor>::new(NoCacher, NoCacher),
+    )?;
+
+    let items = ["a", "b", "c"];
+    write_items(&value_log, &index, &items, 10_000)?;
+
+    let snapshot = value_log.snapshot();
+
+    let mut old_handles = vec![];
+    for key in items {
+        old_handles.push(index.get(key.as_bytes())?.unwrap());
+    }
+
+    value_log.major_compact(&index, MockIndexWriter(index.clone()))?;
+    value_log.drop_stale_segments()?;
+
+    let vhandle = old_handles.first().unwrap();
+    assert!(value_log.get_entry(vhandle)?.is_none());
+    assert!(value_log.stat(vhandle)?.is_none());
+
+    let values = snapshot.multi_get(&old_handles)?;
+    for (key, value) in items.iter().zip(values) {
+        assert_eq!(&*value.unwrap(), key.repeat(10_000).as_bytes());
+    }
+
+    let (key, value) = snapshot.get_entry(vhandle)?.unwrap();
+    assert_eq!(&*key, b"a");
+    assert_eq!(&*value, "a".repeat(10_000).as_bytes());
+
+    assert!(snapshot.get_checked(vhandle, b"a")?.is_some());
+    assert!(snapshot.get_checked(vhandle, b"b").is_err());
+
+    assert_eq!(&*snapshot.get_range(vhandle, 100, 3)?.unwrap(), b"aaa");
+    assert_eq!(10_000, snapshot.stat(vhandle)?.unwrap().disk_len);
+
+    let mut value = vec![];
+    std::io::Read::read_to_end(&mut snapshot.open_value(vhandle)?.unwrap(), &mut value)?;
+    assert_eq!(value, "a".repeat(10_000).as_bytes());
+
+    Ok(())
+}
+
+#[test]
+fn vlog_snapshot_clear() -> value_log::Result<()> {
+    let folder = tempfile::tempdir()?;
+    let vl_path = folder.path();
+
+    let index = MockIndex::default();
+
+    let value_log = ValueLog::open(
+        vl_path,
+        Config::<_, _, NoCompressor>::new(NoCacher, NoCacher),
+    )?;
+
+    write_items(&value_log, &index, &["a", "b"], 10_000)?;
+
+    let snapshot = value_log.snapshot();
+
+    write_items(&value_log, &index, &["c", "d"], 10_000)?;
+    assert_eq!(2, value_log.segment_count());
+
+    value_log.clear(false)?;
+    assert_eq!(0, value_log.segment_count());
+
+    // NOTE: Segment 1 is not referenced by the snapshot, so it is deleted right away
+    assert!(vl_path.join("segments").join("0").try_exists()?);
+    assert!(!vl_path.join("segments").join("1").try_exists()?);
+
+    let vhandle = index.get(b"a")?.unwrap();
+    assert_eq!(
+        &*snapshot.get(&vhandle)?.unwrap(),
+        "a".repeat(10_000).as_bytes()
+    );
+
+    drop(snapshot);
+    assert!(!vl_path.join("segments").join("0").try_exists()?);
+
+    Ok(())
+}
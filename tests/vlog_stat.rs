@@ -0,0 +1,128 @@
+mod common;
+
+use common::{copy_dir, MockIndex, MockIndexWriter, NoCacher, NoCompressor};
+use test_log::test;
+use value_log::{BlobStat, Compressor, Config, IndexReader, IndexWriter, ValueHandle, ValueLog};
+
+#[derive(Clone, Debug, Default)]
+struct Lz4Compressor;
+impl Compressor for Lz4Compressor {
+    fn compress(&self, bytes: &[u8]) -> value_log::Result<Vec<u8>> {
+        Ok(lz4_flex::compress_prepend_size(bytes))
+    }
+
+    fn decompress(&self, bytes: &[u8]) -> value_log::Result<Vec<u8>> {
+        lz4_flex::decompress_size_prepended(bytes).map_err(|_| value_log::Error::Decompress)
+    }
+}
+
+#[test]
+fn vlog_stat() -> value_log::Result<()> {
+    let folder = tempfile::tempdir()?;
+    let vl_path = folder.path();
+
+    let index = MockIndex::default();
+
+    let value_log = ValueLog::open(
+        vl_path,
+        Config::<_, _, NoCompressor>::new(NoCacher, NoCacher),
+    )?;
+
+    let mut index_writer = MockIndexWriter(index.clone());
+    let mut writer = value_log.get_writer()?;
+
+    let vhandle = writer.get_next_value_handle();
+    index_writer.insert_indirect(b"abc", vhandle.clone(), 10_000)?;
+    writer.write(b"abc", [b'x'; 10_000])?;
+
+    value_log.register_writer(writer)?;
+
+    let stat = value_log.stat(&vhandle)?.unwrap();
+    assert_eq!(3, stat.key_len);
+    assert_eq!(10_000, stat.disk_len);
+    assert_eq!(Some(10_000), stat.uncompressed_len);
+
+    let checksum = value_log
+        .get_reader()?
+        .next()
+        .unwrap()
+        .map(|(_, _, _, checksum)| checksum)?;
+    assert_eq!(checksum, stat.checksum);
+
+    assert!(value_log
+        .stat(&ValueHandle {
+            segment_id: 1_000,
+            offset: 0,
+        })?
+        .is_none());
+
+    Ok(())
+}
+
+#[test]
+fn vlog_stat_compressed() -> value_log::Result<()> {
+    let folder = tempfile::tempdir()?;
+    let vl_path = folder.path();
+
+    let index = MockIndex::default();
+
+    let value_log = ValueLog::open(
+        vl_path,
+        Config::<_, _, Lz4Compressor>::new(NoCacher, NoCacher).compression(Some(Lz4Compressor)),
+    )?;
+
+    let value = "verycompressable".repeat(10);
+
+    let mut index_writer = MockIndexWriter(index.clone());
+    let mut writer = value_log.get_writer()?;
+
+    let vhandle = writer.get_next_value_handle();
+    index_writer.insert_indirect(b"a", vhandle.clone(), value.len() as u32)?;
+    let written_bytes = writer.write(b"a", &value)?;
+
+    value_log.register_writer(writer)?;
+
+    let stat = value_log.stat(&index.get(b"a")?.unwrap())?.unwrap();
+    assert_eq!(1, stat.key_len);
+    assert_eq!(u64::from(written_bytes), stat.disk_len);
+    assert_eq!(Some(value.len() as u64), stat.uncompressed_len);
+
+    Ok(())
+}
+
+#[test]
+fn vlog_stat_v1() -> value_log::Result<()> {
+    let folder = tempfile::tempdir()?;
+    let path = folder.path();
+
+    copy_dir(std::path::Path::new("test_fixture/v1_vlog"), path)?;
+
+    let value_log = ValueLog::open(path, Config::<_, _, NoCompressor>::new(NoCacher, NoCacher))?;
+
+    let segment_id = value_log
+        .manifest
+        .list_segment_ids()
+        .into_iter()
+        .min()
+        .unwrap();
+
+    let vhandle = ValueHandle {
+        segment_id,
+        offset: 0,
+    };
+
+    let value = value_log.get(&vhandle)?.unwrap();
+
+    let BlobStat {
+        disk_len,
+        uncompressed_len,
+        ..
+    } = value_log.stat(&vhandle)?.unwrap();
+
+    assert_eq!(value.len() as u64, disk_len);
+
+    // NOTE: V1 blobs do not store their uncompressed length, but they are not compressed here
+    assert_eq!(Some(disk_len), uncompressed_len);
+
+    Ok(())
+}
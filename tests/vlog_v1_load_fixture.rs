@@ -1,12 +1,16 @@
 mod common;
 
-use common::{NoCacher, NoCompressor};
+use common::{copy_dir, NoCacher, NoCompressor};
 use test_log::test;
 use value_log::{Config, ValueLog};
 
 #[test]
 fn vlog_load_v1() -> value_log::Result<()> {
-    let path = std::path::Path::new("test_fixture/v1_vlog");
+    let folder = tempfile::tempdir()?;
+    let path = folder.path();
+
+    // NOTE: Tests run in parallel, so each one needs its own copy of the fixture
+    copy_dir(std::path::Path::new("test_fixture/v1_vlog"), path)?;
 
     let value_log = ValueLog::open(path, Config::<_, _, NoCompressor>::new(NoCacher, NoCacher))?;
 
@@ -30,7 +34,10 @@ fn vlog_load_v1() -> value_log::Result<()> {
 
 #[test]
 fn vlog_load_v1_corrupt() -> value_log::Result<()> {
-    let path = std::path::Path::new("test_fixture/v1_vlog_corrupt");
+    let folder = tempfile::tempdir()?;
+    let path = folder.path();
+
+    copy_dir(std::path::Path::new("test_fixture/v1_vlog_corrupt"), path)?;
 
     let value_log = ValueLog::open(path, Config::<_, _, NoCompressor>::new(NoCacher, NoCacher))?;
 
@@ -38,3 +45,50 @@ fn vlog_load_v1_corrupt() -> value_log::Result<()> {
 
     Ok(())
 }
+
+#[test]
+fn vlog_load_v1_verify_integrity() -> value_log::Result<()> {
+    let folder = tempfile::tempdir()?;
+    let path = folder.path();
+
+    copy_dir(std::path::Path::new("test_fixture/v1_vlog"), path)?;
+
+    let value_log = ValueLog::open(path, Config::<_, _, NoCompressor>::new(NoCacher, NoCacher))?;
+
+    let report = value_log.verify_integrity()?;
+    assert!(report.is_ok());
+    assert_eq!(2, report.segment_count);
+
+    // NOTE: Also includes shadowed blobs that the merging reader skips
+    assert_eq!(8, report.blob_count);
+
+    Ok(())
+}
+
+#[test]
+fn vlog_load_v1_corrupt_verify_integrity() -> value_log::Result<()> {
+    let folder = tempfile::tempdir()?;
+    let path = folder.path();
+
+    copy_dir(std::path::Path::new("test_fixture/v1_vlog_corrupt"), path)?;
+
+    let value_log = ValueLog::open(path, Config::<_, _, NoCompressor>::new(NoCacher, NoCacher))?;
+
+    let report = value_log.verify_integrity()?;
+    assert_eq!(2, report.corruptions.len());
+    assert_eq!(vec![1], report.corrupt_segments());
+    assert!(report
+        .corruptions
+        .iter()
+        .all(|x| matches!(x.kind, value_log::CorruptionKind::ChecksumMismatch { .. })));
+    assert_eq!(
+        vec![Some(43), Some(135)],
+        report
+            .corruptions
+            .iter()
+            .map(|x| x.offset)
+            .collect::<Vec<_>>(),
+    );
+
+    Ok(())
+}